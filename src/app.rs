@@ -1,18 +1,88 @@
+use std::collections::HashMap;
 use std::error;
 use std::fs;
+use std::io;
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::sync::{
+    Arc,
+    mpsc::{self, Receiver},
+};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
 
+use autoconnect::{Action, Network};
+use backend::{Backend, Helper, NetworkManager, Systemd, Unmanaged, network_manager, systemd};
 use block::Title;
-use clipboard::ClipboardContext;
-use clipboard::ClipboardProvider;
-use connection::Connection;
+use column::{Column, ColumnChooser};
+use confirm::{Confirm, ConfirmPolicy, Destructive};
+use connection::{Connection, Origin};
+use details::Details;
+use drift::Drift;
+use filter::Filter;
+use import::Import;
+use input::Input;
+use layout::Flex;
+use log::Log;
+use menu::{Menu, MenuAction};
+use problems::Problems;
+use qr::Qr;
 use ratatui::prelude::*;
 use ratatui::widgets::*;
-use state::State;
+use settings::Settings;
+use sort::Sort;
+use source::{Found, Source};
+pub use state::State;
+use switch::Switch;
+use theme::Theme;
+pub use yank::Yank;
+use yank::YankMenu;
 
-use crate::wg::WgConfig;
+use crate::wg::{
+    self, Health, STALE_HANDSHAKE, WgConfig, runtime,
+    types::{Key, key::Private},
+};
 
+mod autoconnect;
+mod backend;
+mod clipboard;
+mod column;
+mod confirm;
 mod connection;
+mod details;
+mod drift;
+mod filter;
+pub mod helper;
+mod import;
+mod input;
+mod log;
+mod menu;
+mod popup;
+mod problems;
+mod qr;
+mod reload;
+mod settings;
+mod sort;
+mod source;
 mod state;
+mod switch;
+mod theme;
+mod watchdog;
+mod yank;
+
+/// Directory of the wireguard configurations of `wg-quick` and its systemd units.
+///
+/// Imported configurations are saved there.
+const CONFIG_DIR: &str = "/etc/wireguard";
+
+/// Width under which connections are shown as cards instead of a table.
+const CARDS_WIDTH: u16 = 60;
+
+/// Maximum delay between the two clicks of a double click.
+const DOUBLE_CLICK: Duration = Duration::from_millis(400);
+
+/// How often the interfaces created by other tools are looked for.
+const UNMANAGED_POLL: Duration = Duration::from_secs(2);
 
 /// Application result type.
 pub type AppResult<T> = std::result::Result<T, Box<dyn error::Error>>;
@@ -22,53 +92,431 @@ pub struct App {
     /// Is the application running?
     pub running: bool,
     connections: Vec<Connection>,
+    /// Selection among the shown connections.
     table_state: TableState,
+    filter: Filter,
+    sort: Sort,
     state: State,
     nameservers: Vec<String>,
+    /// Message shown to the user until the next key press.
+    message: Option<String>,
+    settings: Settings,
+    theme: Theme,
+    /// Escape sequences to write to the terminal on the next draw.
+    terminal_output: Vec<u8>,
+    /// Area of the latest draw.
+    screen: Rect,
+    /// Where each shown connection was drawn, with its position among the shown connections.
+    row_areas: Vec<(Rect, usize)>,
+    /// Where each column header was drawn.
+    header_areas: Vec<(Rect, Column)>,
+    /// Time and position of the latest left click, to detect double clicks.
+    last_click: Option<(Instant, u16, u16)>,
+    log: Log,
+    /// Network changes, if some connection has rules.
+    network: Option<Receiver<Network>>,
+    /// The configurations of the interfaces created by other tools, not listed when configurations
+    /// are given on the command line.
+    unmanaged: Option<Receiver<HashMap<String, WgConfig>>>,
+    /// Where the configuration files are.
+    sources: Vec<Source>,
+    /// Whether the configurations are served by the root helper.
+    helper: bool,
+    /// Changes of the configuration files, if their directories can be watched.
+    file_changes: Option<Receiver<Vec<PathBuf>>>,
+    /// Enabling or disabling a unit at boot in the background, its result being the message.
+    boot: Option<JoinHandle<String>>,
 }
 
 impl App {
     /// Constructs a new instance of [`App`].
     ///
-    /// Loads the wireguard configurations from `/etc/wireguard`.
-    pub fn new() -> AppResult<Self> {
-        let files = fs::read_dir("/etc/wireguard")?;
+    /// Loads the wireguard configurations of the `paths` given on the command line, or of the
+    /// sources of the settings and NetworkManager if there are none.
+    ///
+    /// Configurations which can't be read are listed with their error.
+    pub fn new(paths: &[String]) -> AppResult<Self> {
+        let settings = Settings::load();
+        let mut log = Log::default();
+        let mut connections: Vec<Connection> = Vec::new();
+        let mut message = None;
+        for error in &settings.errors {
+            log.push(format!("Ignored the setting {error}"));
+        }
+        if !settings.errors.is_empty() {
+            message = Some("Invalid settings were ignored, see the log (L)".to_string());
+        }
 
-        let mut connections = Vec::new();
+        // run as a normal user, the root helper reads the files
+        let mut helper = paths.is_empty() && helper::available();
+        if helper {
+            match helper_connections(&settings) {
+                Ok(served) => connections.extend(served),
+                // e.g. a socket left by a dead helper
+                Err(e) => {
+                    log.push(format!(
+                        "Unable to reach the helper, reading the files: {e}"
+                    ));
+                    message = Some(format!("Unable to reach the helper: {e}"));
+                    helper = false;
+                }
+            }
+        }
+        let sources: Vec<_> = match (helper, paths.is_empty()) {
+            (true, _) => &[],
+            (false, true) => &settings.sources[..],
+            (false, false) => paths,
+        }
+        .iter()
+        .map(|path| Source::new(path))
+        .collect();
+        for found in sources.iter().flat_map(Source::files) {
+            if connections.iter().any(|c| c.name() == found.name) {
+                log.push(format!(
+                    "Ignored {}, {} is already loaded",
+                    found.path.display(),
+                    found.name
+                ));
+                continue;
+            }
+            connections.push(file_connection(&settings, found));
+        }
+        if settings.network_manager && paths.is_empty() {
+            for nm in network_manager::connections() {
+                // the file wins, both can't be up at the same time anyway
+                if connections.iter().any(|c| c.name() == nm.interface) {
+                    continue;
+                }
+                let mut con = match nm.config {
+                    Ok(config) => Connection::new(&nm.interface, WgConfig::from(config.as_str()))
+                        .with_backend(Arc::new(NetworkManager::new(&nm.uuid)))
+                        .with_origin(Origin::NetworkManager),
+                    Err(e) => Connection::unreadable(&nm.interface, Origin::NetworkManager, e),
+                };
+                con.set_watchdog(settings.watchdog(&nm.interface));
+                connections.push(con);
+            }
+        }
+        connections.sort();
 
-        for file in files {
-            let file = file?;
-            let config = fs::read_to_string(file.path())?;
-            connections.push(Connection::new(
-                file.file_name().to_string_lossy().trim_end_matches(".conf"),
-                WgConfig::from(config.as_str()),
+        let unreadable = connections.iter().filter(|c| c.error().is_some()).count();
+        if unreadable > 0 {
+            message = Some(format!(
+                "Unable to read {unreadable} configuration(s), see the problems (P)"
             ));
-            connections.sort();
         }
 
         let mut app = Self {
             running: true,
             connections,
             table_state: TableState::default().with_selected(0),
+            filter: Filter::default(),
+            sort: Sort::default(),
             state: State::Main,
             nameservers: vec![],
+            message,
+            theme: settings.theme(),
+            settings,
+            terminal_output: Vec::new(),
+            screen: Rect::default(),
+            row_areas: Vec::new(),
+            header_areas: Vec::new(),
+            last_click: None,
+            log,
+            network: None,
+            unmanaged: (paths.is_empty() && !helper).then(watch_unmanaged),
+            helper,
+            file_changes: reload::watch(&dirs(&sources)).ok(),
+            boot: None,
+            sources,
         };
+        // the first listing is awaited so that the connections start sorted
+        if let Some(configs) = app
+            .unmanaged
+            .as_ref()
+            .and_then(|r| r.recv_timeout(UNMANAGED_POLL).ok())
+        {
+            app.update_unmanaged(configs);
+        }
+        // connections already up aren't connected again
+        for con in &mut app.connections {
+            con.update();
+        }
+        app.sort_connections();
+        app.autoconnect();
         app.update_nameserver();
         Ok(app)
     }
 
+    /// Connects the connections set to autoconnect and starts watching the network if needed.
+    fn autoconnect(&mut self) {
+        for con in &mut self.connections {
+            let settings = self.settings.connection(con.name());
+            if !settings.is_some_and(|s| s.autoconnect) {
+                continue;
+            }
+            if con.status().is_connected() {
+                con.keep_up();
+            } else {
+                self.log.push(format!("Autoconnecting {}", con.name()));
+                con.connect();
+            }
+        }
+
+        let rules = self
+            .settings
+            .connections
+            .iter()
+            .any(|c| !c.rules.is_empty());
+        if rules {
+            let names = self.connections.iter().map(|c| c.name().to_string());
+            match autoconnect::watch(names.collect()) {
+                Ok(network) => self.network = Some(network),
+                Err(e) => self.log.push(format!(
+                    "Unable to watch the network, rules are ignored: {e}"
+                )),
+            }
+        }
+    }
+
+    /// Lists the wireguard interfaces without configuration, refreshes the configurations which
+    /// changed and forgets the interfaces which are gone.
+    fn update_unmanaged(&mut self, mut configs: HashMap<String, WgConfig>) {
+        self.connections
+            .retain(|c| *c.origin() != Origin::Unmanaged || configs.contains_key(c.name()));
+        for con in &mut self.connections {
+            if *con.origin() == Origin::Unmanaged
+                && let Some(config) = configs.remove(con.name())
+            {
+                con.set_config(config);
+            }
+        }
+        for (name, config) in configs {
+            if self.connections.iter().any(|c| c.name() == name) {
+                continue;
+            }
+            self.log
+                .push(format!("Found {name}, an interface without configuration"));
+            self.connections.push(
+                Connection::new(&name, config)
+                    .with_backend(Arc::new(Unmanaged))
+                    .with_origin(Origin::Unmanaged),
+            );
+        }
+    }
+
+    /// Reloads the configuration files at the changed `paths`, or in the changed directories.
+    ///
+    /// Connections whose file text didn't change are kept as they are, the others keep their
+    /// status and watchdog. Connected ones are flagged as still running the previous configuration.
+    fn reload_files(&mut self, paths: &[PathBuf]) {
+        let selected = self.selected().map(|c| c.name().to_string());
+        let mut files: Vec<Found> = Vec::new();
+        let mut add = |found: Found| {
+            if !files.iter().any(|f| f.path == found.path) {
+                files.push(found);
+            }
+        };
+        for path in paths {
+            if path.is_dir() {
+                // a source directory, or one of its ancestors, was created
+                self.sources
+                    .iter()
+                    .filter(|s| s.dir().starts_with(path))
+                    .flat_map(Source::files)
+                    .for_each(&mut add);
+            } else if path.exists() && self.sources.iter().any(|s| s.lists(path)) {
+                add(Found::read(path));
+            }
+        }
+
+        let log = &mut self.log;
+        let mut removed = Vec::new();
+        self.connections.retain(|c| match c.origin().path() {
+            Some(path) if paths.iter().any(|p| path.starts_with(p)) && !path.exists() => {
+                log.push(format!("Removed {}, {} is gone", c.name(), path.display()));
+                removed.push(c.name().to_string());
+                false
+            }
+            _ => true,
+        });
+        // a file of a later source may have the same name
+        for name in removed {
+            if let Some(path) = self
+                .sources
+                .iter()
+                .filter_map(|s| s.paths().ok())
+                .flatten()
+                .find(|p| p.exists() && source::connection_name(p) == name)
+            {
+                add(Found::read(&path));
+            }
+        }
+
+        let mut changed = Vec::new();
+        for file in files {
+            let same_file = self
+                .connections
+                .iter()
+                .position(|c| c.origin().path() == Some(file.path.as_path()));
+            let same_name = self.connections.iter().position(|c| c.name() == file.name);
+
+            match (same_file, same_name) {
+                (Some(i), _) => {
+                    let con = &mut self.connections[i];
+                    match &file.content {
+                        Ok(text) if con.error().is_none() => {
+                            if !con.reload(text) {
+                                continue;
+                            }
+                            if con.is_changed() {
+                                changed.push(con.name().to_string());
+                            }
+                        }
+                        Err(e) if con.error() == Some(e.to_string().as_str()) => continue,
+                        _ => *con = file_connection(&self.settings, file),
+                    }
+                    self.log
+                        .push(format!("Reloaded {}", self.connections[i].name()));
+                }
+                // the interface was brought up from this file by another tool
+                (None, Some(i)) if *self.connections[i].origin() == Origin::Unmanaged => {
+                    self.log.push(format!("Added {}", file.name));
+                    self.connections[i] = file_connection(&self.settings, file);
+                }
+                (None, Some(_)) => self.log.push(format!(
+                    "Ignored {}, {} is already loaded",
+                    file.path.display(),
+                    file.name
+                )),
+                (None, None) => {
+                    self.log.push(format!("Added {}", file.name));
+                    self.connections.push(file_connection(&self.settings, file));
+                }
+            }
+        }
+
+        if !changed.is_empty() {
+            let message = format!("{}: config changed, restart to apply", changed.join(", "));
+            self.log.push(message.clone());
+            self.message = Some(message);
+        }
+        self.sort_connections();
+        self.reselect(selected);
+    }
+
+    /// Applies the rules of each connection to the `network` the machine just joined.
+    fn network_changed(&mut self, network: &Network) {
+        self.log.push(format!("Network changed: {network}"));
+
+        for con in &mut self.connections {
+            let Some(settings) = self.settings.connection(con.name()) else {
+                continue;
+            };
+            match settings.rules.action(network) {
+                Some(Action::Connect) if !con.status().is_connected() => {
+                    self.log
+                        .push(format!("Connecting {} on {network}", con.name()));
+                    con.connect();
+                }
+                Some(Action::Disconnect) if con.status().is_connected() => {
+                    self.log
+                        .push(format!("Disconnecting {} on {network}", con.name()));
+                    con.disconnect();
+                }
+                _ => {}
+            }
+        }
+        self.update_nameserver();
+    }
+
     /// Handles the tick event of the terminal.
+    ///
+    /// Tells the user when a healthy connection goes stale and lets the watchdogs restart dead
+    /// connections.
     pub fn tick(&mut self) {
+        let name = self.selected().map(|c| c.name().to_string());
+        let changed: Vec<PathBuf> = self
+            .file_changes
+            .as_ref()
+            .map(|r| r.try_iter().flatten().collect())
+            .unwrap_or_default();
+        if !changed.is_empty() {
+            self.reload_files(&changed);
+        }
+        let configs = self.unmanaged.as_ref().and_then(|r| r.try_iter().last());
+        if let Some(configs) = configs {
+            self.update_unmanaged(configs);
+        }
+        if let Some(boot) = self.boot.take_if(|b| b.is_finished()) {
+            self.message = Some(
+                boot.join()
+                    .unwrap_or_else(|_| "Changing a connection at boot panicked".to_string()),
+            );
+        }
+        let now = Instant::now();
+        let mut stale = Vec::new();
         for con in &mut self.connections {
+            let before = con.status().health();
             con.update();
+            if before == Health::Healthy && con.status().health() == Health::Stale {
+                stale.push(con.name().to_string());
+            }
+            if let Some(failure) = con.failure() {
+                self.log.push(failure.clone());
+                self.message = Some(failure);
+            }
+            for event in con.watch(now) {
+                self.log.push(event);
+            }
+        }
+        if !stale.is_empty() {
+            let message = format!(
+                "No handshake for {}s on {}",
+                STALE_HANDSHAKE.num_seconds(),
+                stale.join(", ")
+            );
+            self.log.push(message.clone());
+            self.message = Some(message);
         }
+        // with the statuses up to date, to know which connections are up
+        if let Some(network) = self.network.as_ref().and_then(|r| r.try_iter().last()) {
+            self.network_changed(&network);
+        }
+        // status based sorts and filters may move, hide or show connections
+        self.sort_connections();
+        self.reselect(name);
+    }
+
+    /// Sorts the connections according to `sort`.
+    fn sort_connections(&mut self) {
+        let sort = &self.sort;
+        self.connections.sort_by(|a, b| sort.compare(a, b));
+    }
+
+    /// Changes the sort, keeping the selected connection.
+    fn update_sort(&mut self, update: impl FnOnce(&mut Sort)) {
+        let name = self.selected().map(|c| c.name().to_string());
+        update(&mut self.sort);
+        self.sort_connections();
+        self.reselect(name);
+    }
+
+    /// Sorts by the next sortable column.
+    pub fn next_sort_column(&mut self) {
+        self.update_sort(Sort::next_column);
+    }
+
+    /// Toggles between ascending and descending sort.
+    pub fn reverse_sort(&mut self) {
+        self.update_sort(|sort| sort.descending = !sort.descending);
     }
 
     /// Select the next element in the app
     pub fn down(&mut self) {
         let mut new = self.table_state.selected().unwrap_or(0) + 1;
 
-        if new >= self.connections.len() {
+        if new >= self.visible().len() {
             new = 0;
         }
 
@@ -82,14 +530,84 @@ impl App {
             .selected()
             .unwrap_or(0)
             .checked_sub(1)
-            .unwrap_or(self.connections.len() - 1);
+            .unwrap_or(self.visible().len().saturating_sub(1));
 
         self.table_state.select(Some(new));
     }
 
     pub fn selected(&self) -> Option<&Connection> {
-        self.connections
+        self.visible()
             .get(self.table_state.selected().unwrap_or(0))
+            .map(|&i| &self.connections[i])
+    }
+
+    fn selected_mut(&mut self) -> Option<&mut Connection> {
+        self.visible()
+            .get(self.table_state.selected().unwrap_or(0))
+            .map(|&i| &mut self.connections[i])
+    }
+
+    /// Indices in `connections` of the shown connections.
+    fn visible(&self) -> Vec<usize> {
+        (0..self.connections.len())
+            .filter(|&i| self.filter.matches(&self.connections[i]))
+            .collect()
+    }
+
+    /// Selects the connection called `name` if it's shown.
+    ///
+    /// Otherwise the selection is kept within the shown connections.
+    fn reselect(&mut self, name: Option<String>) {
+        let visible = self.visible();
+        let position = visible
+            .iter()
+            .position(|&i| Some(self.connections[i].name()) == name.as_deref());
+
+        match position {
+            Some(position) => self.table_state.select(Some(position)),
+            None => self.table_state.select(Some(
+                self.table_state
+                    .selected()
+                    .unwrap_or(0)
+                    .min(visible.len().saturating_sub(1)),
+            )),
+        }
+    }
+
+    /// Changes the filter, keeping the selected connection if it's still shown.
+    fn update_filter(&mut self, update: impl FnOnce(&mut Filter)) {
+        let name = self.selected().map(|c| c.name().to_string());
+        update(&mut self.filter);
+        self.reselect(name);
+    }
+
+    /// Opens the search bar.
+    pub fn search(&mut self) {
+        self.state = State::Search;
+    }
+
+    pub fn search_push(&mut self, c: char) {
+        self.update_filter(|filter| filter.query.push(c));
+    }
+
+    pub fn search_pop(&mut self) {
+        self.update_filter(|filter| filter.query.pop());
+    }
+
+    /// Closes the search bar and clears the search.
+    pub fn clear_search(&mut self) {
+        self.state = State::Main;
+        self.update_filter(|filter| filter.query = Input::default());
+    }
+
+    /// Toggles showing only connected connections.
+    pub fn toggle_connected_filter(&mut self) {
+        self.update_filter(|filter| filter.connected = !filter.connected);
+    }
+
+    /// Toggles showing only connections with a stale handshake.
+    pub fn toggle_stale_filter(&mut self) {
+        self.update_filter(|filter| filter.stale = !filter.stale);
     }
 
     /// Update the cached nameserver.
@@ -105,41 +623,748 @@ impl App {
     }
 
     /// Connects to the selected (hovered) connection
+    ///
+    /// If connected connections route the same networks, asks whether to disconnect them first.
+    /// They are always disconnected with the exclusive connect setting.
     pub fn connect_selected(&mut self) {
-        if let Some(con) = self.selected() {
-            let _ = con.connect();
+        let Some(con) = self.selected() else {
+            return;
+        };
+
+        if let Some(error) = con.error() {
+            self.message = Some(format!("Unable to read {}: {error}", con.name()));
+            return;
+        }
+        if *con.origin() == Origin::Unmanaged && !con.status().is_connected() {
+            self.message = Some(format!("{} has no configuration to connect", con.name()));
+            return;
+        }
+
+        let conflicts: Vec<_> = self
+            .connections
+            .iter()
+            .filter(|c| *c != con && c.status().is_connected())
+            .map(|c| (c.name().to_string(), con.conflicts(c)))
+            .filter(|(_, networks)| self.settings.exclusive_connect || !networks.is_empty())
+            .collect();
+
+        if conflicts.is_empty() || con.status().is_connected() {
+            if let Some(con) = self.selected_mut() {
+                con.connect();
+            }
+            self.update_nameserver();
+            return;
+        }
+
+        let switch = Switch {
+            target: con.name().to_string(),
+            conflicts,
+        };
+        match self.settings.exclusive_connect {
+            true => self.switch(&switch),
+            false => self.state = State::Switch(Box::new(switch)),
+        }
+    }
+
+    /// Disconnects the conflicting connections, then connects the one being switched to.
+    pub fn switch_connection(&mut self) {
+        if let State::Switch(switch) = mem::take(&mut self.state) {
+            self.switch(&switch);
+        }
+    }
+
+    fn switch(&mut self, switch: &Switch) {
+        let others = switch.others();
+        let mut tasks: Vec<_> = self
+            .connections
+            .iter_mut()
+            .filter(|c| others.iter().any(|name| name == c.name()))
+            .map(|c| c.task(false))
+            .collect();
+        match self
+            .connections
+            .iter_mut()
+            .find(|c| c.name() == switch.target)
+        {
+            Some(target) => {
+                tasks.push(target.task(true));
+                target.set_job(
+                    format!("Switching to {}", switch.target),
+                    connection::run(tasks),
+                );
+            }
+            None => {
+                connection::run(tasks);
+            }
+        }
+        self.message = Some(format!("Switching to {}", switch.target));
+    }
+
+    /// Connects the connection being switched to, keeping the conflicting ones.
+    pub fn connect_anyway(&mut self) {
+        let State::Switch(switch) = mem::take(&mut self.state) else {
+            return;
+        };
+
+        if let Some(con) = self
+            .connections
+            .iter_mut()
+            .find(|c| c.name() == switch.target)
+        {
+            con.connect();
         }
         self.update_nameserver();
     }
 
     /// Disconnects from the selected (hovered) connection
     pub fn disconnect_selected(&mut self) {
-        if let Some(con) = self.selected() {
-            let _ = con.disconnect();
+        if let Some(con) = self.selected_mut() {
+            con.disconnect();
         }
         self.update_nameserver();
     }
 
-    /// Disconnects all connections.
+    /// Disconnects all connections, once confirmed.
+    ///
+    /// Unmanaged interfaces are left alone, they belong to other tools.
     pub fn disconnect_all(&mut self) {
-        self.connections.iter().for_each(|c| {
-            let _ = c.disconnect();
+        self.confirm(Destructive::DisconnectAll);
+    }
+
+    /// Deletes the configuration of the selected connection, once confirmed.
+    ///
+    /// Connected connections must be disconnected first.
+    pub fn delete_selected(&mut self) {
+        let Some(con) = self.selected() else {
+            return;
+        };
+
+        if con.status().is_connected() {
+            self.message = Some(format!("Disconnect {} before deleting it", con.name()));
+            return;
+        }
+        if let Some(reason) = without_file(con) {
+            self.message = Some(reason);
+            return;
+        }
+        self.confirm(Destructive::Delete(con.name().to_string()));
+    }
+
+    /// Replaces the private key of the selected connection, once confirmed.
+    pub fn rotate_selected_key(&mut self) {
+        let Some(con) = self.selected() else {
+            return;
+        };
+
+        if let Some(reason) = without_file(con) {
+            self.message = Some(reason);
+            return;
+        }
+        if let Some(error) = con.error() {
+            self.message = Some(format!("Unable to read {}: {error}", con.name()));
+            return;
+        }
+        self.confirm(Destructive::RotateKey(con.name().to_string()));
+    }
+
+    /// Asks to confirm `action` if the confirmation policy requires it, runs it otherwise.
+    fn confirm(&mut self, action: Destructive) {
+        let ssh_interface = match self.settings.confirm {
+            ConfirmPolicy::Never => None,
+            _ => confirm::ssh_interface().filter(|interface| match &action {
+                Destructive::DisconnectAll => self.connections.iter().any(|c| {
+                    c.name() == interface
+                        && c.status().is_connected()
+                        && *c.origin() != Origin::Unmanaged
+                }),
+                Destructive::Delete(name) | Destructive::RotateKey(name) => name == interface,
+            }),
+        };
+
+        let needed = match self.settings.confirm {
+            ConfirmPolicy::Always => true,
+            ConfirmPolicy::Ssh => ssh_interface.is_some(),
+            ConfirmPolicy::Never => false,
+        };
+        match needed {
+            true => {
+                self.state = State::Confirm(Box::new(Confirm {
+                    action,
+                    ssh_interface,
+                }))
+            }
+            false => self.run_destructive(action),
+        }
+    }
+
+    /// Runs the action waiting for confirmation.
+    pub fn confirm_action(&mut self) {
+        if let State::Confirm(confirm) = mem::take(&mut self.state) {
+            self.run_destructive(confirm.action);
+        }
+    }
+
+    fn run_destructive(&mut self, action: Destructive) {
+        match action {
+            Destructive::DisconnectAll => {
+                self.connections
+                    .iter_mut()
+                    .filter(|c| *c.origin() != Origin::Unmanaged)
+                    .for_each(Connection::disconnect);
+                self.update_nameserver();
+            }
+            Destructive::Delete(name) => self.delete(&name),
+            Destructive::RotateKey(name) => self.rotate_key(&name),
+        }
+    }
+
+    /// Deletes the configuration file of the connection called `name`.
+    fn delete(&mut self, name: &str) {
+        let Some(path) = self
+            .connections
+            .iter()
+            .find(|c| c.name() == name)
+            .and_then(|c| c.origin().path())
+        else {
+            return;
+        };
+        if let Err(e) = fs::remove_file(path) {
+            self.message = Some(format!("Unable to delete {name}: {e}"));
+            return;
+        }
+
+        let selected = self.selected().map(|c| c.name().to_string());
+        self.connections.retain(|c| c.name() != name);
+        self.reselect(selected);
+        self.message = Some(format!("Deleted {name}"));
+    }
+
+    /// Generates a new private key for the connection called `name`.
+    ///
+    /// The key is written to the configuration file and applied to the interface if it's up.
+    fn rotate_key(&mut self, name: &str) {
+        let Some(con) = self.connections.iter_mut().find(|c| c.name() == name) else {
+            return;
+        };
+
+        let Some(path) = con.origin().path().map(Path::to_path_buf) else {
+            return;
+        };
+
+        let result = Key::<Private>::generate().and_then(|key| {
+            let text = wg::with_private_key(&fs::read_to_string(&path)?, &key);
+            source::write(&path, &text)?;
+            con.set_text(&text);
+            con.apply_private_key(&key)
+        });
+
+        self.message = Some(match result {
+            Ok(()) => format!("New public key of {name}: {}", con.pubkey().as_str()),
+            Err(e) => format!("Unable to rotate the key of {name}: {e}"),
         });
-        self.update_nameserver();
     }
 
     /// Enable the yank (copy) menu
     pub fn yank_menu(&mut self) {
         self.state = State::Yank;
+    }
+
+    /// Copies `yank` from the selected connection to the clipboard and closes the yank menu.
+    pub fn yank(&mut self, yank: Yank) {
+        self.state = State::Main;
+
+        let Some(con) = self.selected() else {
+            return;
+        };
+
+        let content = yank.content(con);
+        let result = self
+            .settings
+            .clipboard
+            .copy(&content, &mut self.terminal_output);
+
+        self.message = Some(match result {
+            Ok(()) => format!("Copied {}", yank.label()),
+            Err(e) => format!("Unable to copy to clipboard: {e}"),
+        });
+    }
+
+    /// Starts importing `text` as a new configuration.
+    ///
+    /// If an input is open, the text is typed in it instead.
+    pub fn paste(&mut self, text: &str) {
+        match &mut self.state {
+            State::Main => self.import(text),
+            State::Search => self.update_filter(|filter| filter.query.push_str(text)),
+            _ => {
+                if let Some(input) = self.input_mut() {
+                    input.push_str(text);
+                }
+            }
+        }
+    }
+
+    /// Starts importing the clipboard content as a new configuration.
+    pub fn import_clipboard(&mut self) {
+        match self.settings.clipboard.paste() {
+            Ok(text) => self.import(&text),
+            Err(e) => self.message = Some(format!("Unable to read clipboard: {e}")),
+        }
+    }
+
+    /// Starts importing `text`, unless the files are behind the root helper.
+    fn import(&mut self, text: &str) {
+        match self.helper {
+            true => {
+                self.message =
+                    Some("Configurations can't be imported through the helper".to_string())
+            }
+            false => self.state = State::Import(Box::new(Import::new(text))),
+        }
+    }
+
+    /// Saves the configuration being imported and adds it to the connections.
+    pub fn save_import(&mut self) {
+        let State::Import(import) = &mut self.state else {
+            return;
+        };
 
+        let name = import.name.value().to_string();
+        if self.connections.iter().any(|c| c.name() == name) {
+            import.set_error(format!("{name} already exists"));
+            return;
+        }
+        let Some(dir) = self.sources.iter().find_map(|s| s.dir_for(&name)) else {
+            import.set_error(format!("No writable source lists {name}.conf"));
+            return;
+        };
+        if let Err(e) = import.save(dir) {
+            import.set_error(format!("Unable to save: {e}"));
+            return;
+        }
+
+        let path = dir.join(format!("{name}.conf"));
+        if let State::Import(import) = mem::take(&mut self.state) {
+            let mut con = Connection::from_text(&name, &import.into_text())
+                .with_backend(file_backend(&self.settings, &path))
+                .with_origin(Origin::File(path));
+            con.set_watchdog(self.settings.watchdog(&name));
+            self.connections.push(con);
+            self.sort_connections();
+            self.message = Some(format!("Imported {name}"));
+            self.reselect(Some(name));
+        }
+    }
+
+    /// Shows the QR code of the selected connection's configuration file, or of a client
+    /// configuration generated from it for interfaces without file.
+    pub fn qr_code(&mut self) {
+        let Some(con) = self.selected() else {
+            return;
+        };
+        if *con.origin() == Origin::Helper {
+            self.message = Some(format!(
+                "The private key of {} stays with the helper",
+                con.name()
+            ));
+            return;
+        }
+
+        let config = con
+            .text()
+            .map_or_else(|| con.config().client(), str::to_string);
+        match Qr::new(con.name(), &config, wg::redact(&config)) {
+            Ok(qr) => self.state = State::Qr(Box::new(qr)),
+            Err(e) => self.message = Some(format!("Unable to create the QR code: {e}")),
+        }
+    }
+
+    /// Shows the QR code of the configuration of a new peer of the selected connection, e.g. a
+    /// phone, with fresh keys and the first free address of its network.
+    ///
+    /// The peer section to add to the connection is shown next to the code.
+    pub fn qr_new_peer(&mut self) {
+        let Some(con) = self.selected() else {
+            return;
+        };
+        let name = con.name().to_string();
+        let Some(port) = con.config().interface.listen_port() else {
+            self.message = Some(format!(
+                "{name} has no ListenPort, a new peer couldn't reach it"
+            ));
+            return;
+        };
+        let Some(address) = con.config().free_address() else {
+            self.message = Some(format!("No free address left in the network of {name}"));
+            return;
+        };
+        let key = match Key::generate() {
+            Ok(key) => key,
+            Err(e) => {
+                self.message = Some(format!("Unable to generate a private key: {e}"));
+                return;
+            }
+        };
+        let host = fs::read_to_string("/proc/sys/kernel/hostname").unwrap_or_default();
+        let endpoint = match format!("{}:{port}", host.trim()).parse() {
+            Ok(endpoint) => endpoint,
+            Err(e) => {
+                self.message = Some(format!("Unable to make the endpoint of {name}: {e}"));
+                return;
+            }
+        };
+
+        let config = con.config().new_peer(&key, address, &endpoint);
+        let text = format!(
+            "{}\nAdd to {name}:\n\n[Peer]\nPublicKey = {}\nAllowedIPs = {address}\n\nThe peer must reach {endpoint}.",
+            wg::redact(&config),
+            key.public_key().as_str(),
+        );
+        match Qr::new(&format!("New peer of {name}"), &config, text) {
+            Ok(qr) => self.state = State::Qr(Box::new(qr)),
+            Err(e) => self.message = Some(format!("Unable to create the QR code: {e}")),
+        }
+    }
+
+    /// Enables or disables the systemd unit of the selected connection at boot, in the background.
+    pub fn toggle_boot(&mut self) {
+        let Some(name) = self.selected().map(|c| c.name().to_string()) else {
+            return;
+        };
+
+        if self.boot.is_some() {
+            self.message = Some("Already changing a connection at boot".to_string());
+            return;
+        }
+
+        self.boot = Some(thread::spawn(move || {
+            let result = match systemd::state(&name) {
+                Some(state) if state.exists() => {
+                    systemd::set_enabled(&name, !state.is_enabled()).map(|_| !state.is_enabled())
+                }
+                _ => Err(io::Error::other(format!(
+                    "no {} unit",
+                    systemd::unit(&name)
+                ))),
+            };
+            match result {
+                Ok(true) => format!("{name} starts at boot"),
+                Ok(false) => format!("{name} no longer starts at boot"),
+                Err(e) => format!("Failed to change {name} at boot: {e}"),
+            }
+        }));
+    }
+
+    /// Compares the file of the selected connection with what its interface runs.
+    pub fn drift(&mut self) {
+        let Some(con) = self.selected() else {
+            return;
+        };
+        if let Some(reason) = not_running_file(con) {
+            self.message = Some(reason);
+            return;
+        }
+
+        match runtime::config(con.name()) {
+            Ok(running) => {
+                self.state = State::Drift(Box::new(Drift::new(con.name(), con.config(), &running)))
+            }
+            Err(e) => self.message = Some(format!("Unable to read {}: {e}", con.name())),
+        }
+    }
+
+    /// Makes the interface being compared run its file, without disconnecting it.
+    pub fn apply_file(&mut self) {
+        let State::Drift(drift) = &self.state else {
+            return;
+        };
+        let name = drift.name.clone();
+
+        match self.apply(&name) {
+            Ok(message) => {
+                self.message = Some(message);
+                self.state = State::Main;
+            }
+            Err(e) => {
+                if let State::Drift(drift) = &mut self.state {
+                    drift.set_error(format!("Unable to apply: {e}"));
+                }
+            }
+        }
+    }
+
+    /// Applies the file of the selected connection to its interface, without disconnecting it.
+    pub fn apply_selected(&mut self) {
+        let Some(con) = self.selected() else {
+            return;
+        };
+        if let Some(reason) = not_running_file(con) {
+            self.message = Some(reason);
+            return;
+        }
+
+        let name = con.name().to_string();
+        self.message = Some(match self.apply(&name) {
+            Ok(message) => message,
+            Err(e) => format!("Unable to apply {name}: {e}"),
+        });
+    }
+
+    /// Syncs the interface of the `name` connection with its file and reconciles its routes.
+    fn apply(&mut self, name: &str) -> io::Result<String> {
+        let Some(con) = self.connections.iter_mut().find(|c| c.name() == name) else {
+            return Err(io::Error::other(format!("{name} is gone")));
+        };
+
+        let Some(path) = con.origin().path() else {
+            return Err(io::Error::other(format!(
+                "{name} has no configuration file"
+            )));
+        };
+        let applied = runtime::apply(name, &fs::read_to_string(path)?)?;
+        con.applied();
+        let message = format!("Applied the file of {name} live, {applied}");
+        self.log.push(message.clone());
+        Ok(message)
+    }
+
+    /// Writes what the interface being compared runs to its file.
+    ///
+    /// Only the settings which differ are changed, comments and the other keys are kept.
+    pub fn save_running(&mut self) {
+        let State::Drift(drift) = &mut self.state else {
+            return;
+        };
+        let Some(con) = self.connections.iter_mut().find(|c| c.name() == drift.name) else {
+            return;
+        };
+        let Some(path) = con.origin().path().map(Path::to_path_buf) else {
+            return;
+        };
+
+        let result = fs::read_to_string(&path).and_then(|text| {
+            let text = drift.running_text(&text);
+            source::write(&path, &text)?;
+            // before the file watcher sees it, the interface runs it already
+            con.set_text(&text);
+            con.applied();
+            Ok(())
+        });
+        match result {
+            Ok(()) => {
+                let message = format!("Saved the running configuration of {}", con.name());
+                self.log.push(message.clone());
+                self.message = Some(message);
+                self.state = State::Main;
+            }
+            Err(e) => drift.set_error(format!("Unable to save: {e}")),
+        }
+    }
+
+    /// Shows what wtui did by itself.
+    pub fn log(&mut self) {
+        self.state = State::Log;
+    }
+
+    /// Shows every field of the selected connection.
+    pub fn details(&mut self) {
         if let Some(con) = self.selected() {
-            let pubkey = con.pubkey();
+            self.state = State::Details(Box::new(Details::new(con)));
+        }
+    }
+
+    /// Shows the problems found in the configurations, starting with the unreadable ones.
+    pub fn problems(&mut self) {
+        let names: Vec<_> = self.connections.iter().map(|c| c.name()).collect();
+        let configs: Vec<_> = self
+            .connections
+            .iter()
+            .filter(|c| c.error().is_none())
+            .map(|c| (c.name(), c.config()))
+            .collect();
+
+        let mut problems: Vec<_> = self
+            .connections
+            .iter()
+            .filter_map(|c| {
+                let source = match c.origin().path() {
+                    Some(path) => path.display().to_string(),
+                    None => c.name().to_string(),
+                };
+                Some(format!("{source}: {}", c.error()?))
+            })
+            .collect();
+        problems.extend(problems::analyze(&configs, &problems::lan_networks(&names)));
+        self.state = State::Problems(Problems(problems));
+    }
+
+    /// The text input of the current popup, if any.
+    pub fn input_mut(&mut self) -> Option<&mut Input> {
+        match &mut self.state {
+            State::Import(import) => Some(&mut import.name),
+            _ => None,
+        }
+    }
+
+    pub fn state(&self) -> &State {
+        &self.state
+    }
+
+    /// Closes the current popup, going back to the main view.
+    pub fn close_popup(&mut self) {
+        self.state = State::Main;
+    }
+
+    /// Opens the column chooser.
+    pub fn column_chooser(&mut self) {
+        self.state = State::Columns(0);
+    }
+
+    /// Moves the cursor of the column chooser by `offset`, wrapping around.
+    pub fn move_column_cursor(&mut self, offset: isize) {
+        if let State::Columns(cursor) = &mut self.state {
+            *cursor = (*cursor as isize + offset).rem_euclid(Column::ALL.len() as isize) as usize;
+        }
+    }
+
+    /// Shows or hides the column under the cursor of the column chooser.
+    pub fn toggle_column(&mut self) {
+        let State::Columns(cursor) = self.state else {
+            return;
+        };
+        let column = Column::ALL[cursor];
+        // the table is useless without names
+        if column == Column::Name {
+            return;
+        }
+
+        let hidden = &mut self.settings.hidden_columns;
+        match hidden.iter().position(|&c| c == column) {
+            Some(position) => {
+                hidden.remove(position);
+            }
+            None => hidden.push(column),
+        }
+    }
+
+    /// Closes the column chooser and saves the chosen columns.
+    pub fn save_columns(&mut self) {
+        self.state = State::Main;
+        if let Err(e) = self.settings.save() {
+            self.message = Some(format!("Unable to save settings: {e}"));
+        }
+    }
+
+    /// Whether the mouse should be captured.
+    pub fn mouse_capture(&self) -> bool {
+        self.settings.mouse
+    }
+
+    /// Handles a left click at the given screen position.
+    ///
+    /// Selects the clicked connection, toggling it on double click, sorts by the clicked header and
+    /// runs the clicked action of the context menu.
+    pub fn click(&mut self, column: u16, row: u16) {
+        let position = Position::new(column, row);
+
+        match &self.state {
+            State::Main => {}
+            State::Menu(menu) => {
+                match menu.action_at(self.screen, column, row) {
+                    Some(action) => self.run_menu_action(action),
+                    None => self.close_popup(),
+                }
+                return;
+            }
+            _ => return,
+        }
+
+        if let Some(&(_, clicked)) = self.header_areas.iter().find(|(a, _)| a.contains(position)) {
+            if !Sort::is_sortable(clicked) {
+                return;
+            }
+            match clicked == self.sort.column {
+                true => self.reverse_sort(),
+                false => self.update_sort(|sort| sort.column = clicked),
+            }
+            return;
+        }
+
+        let Some(&(_, clicked)) = self.row_areas.iter().find(|(a, _)| a.contains(position)) else {
+            return;
+        };
 
-            let mut ctx: ClipboardContext = ClipboardProvider::new().unwrap();
-            ctx.set_contents(pubkey.to_string()).unwrap();
+        let double_click = self.last_click.is_some_and(|(time, c, r)| {
+            time.elapsed() <= DOUBLE_CLICK
+                && self.table_state.selected() == Some(clicked)
+                && self
+                    .row_areas
+                    .iter()
+                    .any(|(a, p)| *p == clicked && a.contains(Position::new(c, r)))
+        });
+        self.table_state.select(Some(clicked));
+
+        if double_click {
+            self.last_click = None;
+            match self.selected().is_some_and(|c| c.status().is_connected()) {
+                true => self.disconnect_selected(),
+                false => self.connect_selected(),
+            }
+        } else {
+            self.last_click = Some((Instant::now(), column, row));
         }
     }
 
+    /// Handles a right click at the given screen position, opening the context menu of the clicked
+    /// connection.
+    pub fn right_click(&mut self, column: u16, row: u16) {
+        if !matches!(self.state, State::Main | State::Menu(_)) {
+            return;
+        }
+
+        let position = Position::new(column, row);
+        match self.row_areas.iter().find(|(a, _)| a.contains(position)) {
+            Some(&(_, clicked)) => {
+                self.table_state.select(Some(clicked));
+                self.state = State::Menu(Menu::new(column, row));
+            }
+            None => self.state = State::Main,
+        }
+    }
+
+    /// Moves the cursor of the context menu by `offset`.
+    pub fn move_menu_cursor(&mut self, offset: isize) {
+        if let State::Menu(menu) = &mut self.state {
+            menu.move_cursor(offset);
+        }
+    }
+
+    /// Runs the action under the cursor of the context menu.
+    pub fn run_selected_menu_action(&mut self) {
+        if let State::Menu(menu) = &self.state {
+            self.run_menu_action(menu.selected());
+        }
+    }
+
+    fn run_menu_action(&mut self, action: MenuAction) {
+        self.state = State::Main;
+        match action {
+            MenuAction::Connect => self.connect_selected(),
+            MenuAction::Disconnect => self.disconnect_selected(),
+            MenuAction::Yank => self.yank_menu(),
+            MenuAction::QrCode => self.qr_code(),
+        }
+    }
+
+    /// Takes the escape sequences waiting to be written to the terminal.
+    pub fn take_terminal_output(&mut self) -> Vec<u8> {
+        mem::take(&mut self.terminal_output)
+    }
+
+    pub fn clear_message(&mut self) {
+        self.message = None;
+    }
+
     /// Set running to false to quit the application.
     pub fn quit(&mut self) {
         self.running = false;
@@ -151,49 +1376,280 @@ impl Widget for &mut App {
     where
         Self: Sized,
     {
+        self.screen = area;
+
+        let filter = match self.state {
+            State::Search => {
+                let mut line = Line::from(&self.filter.query);
+                line.spans.insert(0, " /".into());
+                line.push_span(" ");
+                line
+            }
+            _ if !self.filter.describe().is_empty() => {
+                Line::from(format!(" {} ", self.filter.describe()))
+            }
+            _ => Line::default(),
+        };
+
         let border = Block::bordered()
             .border_type(BorderType::Rounded)
             .title(Title::from(" Connections "))
+            .title(filter.alignment(Alignment::Right))
             .title_alignment(Alignment::Center)
             .title_bottom(
                 Line::from(format!(" Nameservers: {} ", self.nameservers.join(", ")))
                     .alignment(Alignment::Left),
+            )
+            .title_bottom(
+                Line::from(
+                    self.message
+                        .as_ref()
+                        .map(|m| format!(" {m} "))
+                        .unwrap_or_default(),
+                )
+                .alignment(Alignment::Right),
             );
 
-        let list = Table::default()
-            .rows(self.connections.iter().map(Row::from))
-            .header(
-                Row::new([
-                    "Name",
-                    "Address",
-                    "MTU",
-                    "Received",
-                    "Sent",
-                    "Endpoint",
-                    "Allowed IPs",
-                    "Latest ",
-                    "Public Key",
-                    "DNS",
-                ])
-                .bold()
-                .underlined(),
+        let inner = border.inner(area);
+        if inner.width < CARDS_WIDTH {
+            self.render_cards(border, area, buf);
+        } else {
+            self.render_table(border, area, buf);
+        }
+
+        match &self.state {
+            State::Main | State::Search => {}
+            State::Yank => YankMenu.render(area, buf),
+            State::Import(import) => import.render(area, buf, &self.theme),
+            State::Qr(qr) => qr.render(area, buf, &self.theme),
+            State::Columns(cursor) => ColumnChooser {
+                cursor: *cursor,
+                hidden: &self.settings.hidden_columns,
+            }
+            .render(area, buf),
+            State::Menu(menu) => menu.render(area, buf),
+            State::Confirm(confirm) => confirm.render(area, buf, &self.theme),
+            State::Switch(switch) => switch.render(area, buf, &self.theme),
+            State::Drift(drift) => drift.render(area, buf, &self.theme),
+            State::Problems(problems) => problems.render(area, buf, &self.theme),
+            State::Log => self.log.render(area, buf),
+            State::Details(details) => {
+                if let Some(con) = self.connections.iter().find(|c| c.name() == details.name()) {
+                    details.render(area, buf, con, &self.theme)
+                }
+            }
+        }
+    }
+}
+
+impl App {
+    /// Renders the connections as a table, with as many columns as the width allows.
+    fn render_table(&mut self, border: Block, area: Rect, buf: &mut Buffer) {
+        const HIGHLIGHT_SYMBOL: &str = ">> ";
+
+        let inner = border.inner(area);
+        let shown: Vec<_> = Column::ALL
+            .into_iter()
+            .filter(|c| !self.settings.hidden_columns.contains(c))
+            .collect();
+        let columns = column::fitting(
+            &shown,
+            inner.width.saturating_sub(HIGHLIGHT_SYMBOL.len() as u16),
+        );
+
+        let header = columns
+            .iter()
+            .map(|&column| match column == self.sort.column {
+                true => format!("{} {}", column.title(), self.sort.indicator()),
+                false => column.title().to_string(),
+            });
+
+        let table = Table::default()
+            .rows(
+                self.visible()
+                    .into_iter()
+                    .map(|i| self.connections[i].row(&columns, &self.theme)),
             )
-            .widths([
-                Constraint::Max(12),
-                Constraint::Fill(1),
-                Constraint::Length(4),
-                Constraint::Max(8),
-                Constraint::Max(8),
-                Constraint::Fill(1),
-                Constraint::Fill(1),
-                Constraint::Max(12),
-                Constraint::Max(10),
-                Constraint::Fill(1),
-            ])
+            .header(Row::new(header).bold().underlined())
+            .widths(columns.iter().map(|c| c.constraint()))
+            .column_spacing(column::SPACING)
             .block(border)
-            .row_highlight_style(Style::new().reversed())
-            .highlight_symbol(">> ");
+            .row_highlight_style(self.theme.highlight)
+            .highlight_symbol(HIGHLIGHT_SYMBOL);
+
+        StatefulWidget::render(table, area, buf, &mut self.table_state);
+
+        // same layout as the table, to map clicks
+        let selection_width = match self.table_state.selected() {
+            Some(_) => HIGHLIGHT_SYMBOL.len() as u16,
+            None => 0,
+        };
+        let header_area = Rect {
+            x: inner.x + selection_width,
+            width: inner.width.saturating_sub(selection_width),
+            height: 1,
+            ..inner
+        };
+        let header_areas = Layout::horizontal(columns.iter().map(|c| c.constraint()))
+            .flex(Flex::Start)
+            .spacing(column::SPACING)
+            .split(header_area);
+        self.header_areas = header_areas.iter().copied().zip(columns.clone()).collect();
+
+        let visible = self.visible();
+        let heights = visible
+            .iter()
+            .map(|&i| self.connections[i].row_height(&columns));
+        self.row_areas = row_areas(inner, 1, self.table_state.offset(), heights);
+    }
+
+    /// Renders the connections as two lines cards, for narrow terminals.
+    fn render_cards(&mut self, border: Block, area: Rect, buf: &mut Buffer) {
+        let inner = border.inner(area);
+        let list = List::new(
+            self.visible()
+                .into_iter()
+                .map(|i| self.connections[i].card(&self.theme)),
+        )
+        .block(border)
+        .highlight_style(self.theme.highlight);
+
+        let mut state = ListState::default().with_selected(self.table_state.selected());
+        StatefulWidget::render(list, area, buf, &mut state);
 
-        StatefulWidget::render(list, area, buf, &mut self.table_state);
+        self.header_areas.clear();
+        let cards = self.visible().len();
+        self.row_areas = row_areas(inner, 0, state.offset(), (0..cards).map(|_| 2));
     }
 }
+
+/// Computes where rows are drawn in `area`, below `header_height`, when scrolled to `offset`.
+fn row_areas(
+    area: Rect,
+    header_height: u16,
+    offset: usize,
+    heights: impl Iterator<Item = u16>,
+) -> Vec<(Rect, usize)> {
+    let mut y = area.y + header_height;
+    let mut areas = Vec::new();
+
+    for (position, height) in heights.enumerate().skip(offset) {
+        if y + height > area.bottom() {
+            break;
+        }
+        areas.push((Rect::new(area.x, y, area.width, height), position));
+        y += height;
+    }
+
+    areas
+}
+
+/// The backend of the configuration file at `path`, going through systemd if enabled.
+///
+/// `wg-quick@` units only know the configurations of [CONFIG_DIR].
+fn file_backend(settings: &Settings, path: &Path) -> Arc<dyn Backend> {
+    let backend = settings.backend.backend(path);
+    match settings.systemd && systemd::is_running() && path.parent() == Some(Path::new(CONFIG_DIR))
+    {
+        true => Arc::new(Systemd::new(backend)),
+        false => backend,
+    }
+}
+
+/// Why wtui can't change the configuration of `con`, [None] if it's a file.
+fn without_file(con: &Connection) -> Option<String> {
+    match con.origin() {
+        Origin::File(_) => None,
+        Origin::NetworkManager => Some(format!("{} is managed by NetworkManager", con.name())),
+        Origin::Unmanaged => Some(format!("{} has no configuration file", con.name())),
+        Origin::Helper => Some(format!("{} is managed by the root helper", con.name())),
+    }
+}
+
+/// Why the interface of `con` can't be compared with, or synced to, its file.
+fn not_running_file(con: &Connection) -> Option<String> {
+    if let Some(reason) = without_file(con) {
+        return Some(reason);
+    }
+    if let Some(error) = con.error() {
+        return Some(format!("Unable to read {}: {error}", con.name()));
+    }
+    match con.status().is_connected() {
+        true => None,
+        false => Some(format!("{} is not connected", con.name())),
+    }
+}
+
+/// The connection of a configuration file, listed with its error if unreadable.
+fn file_connection(settings: &Settings, file: Found) -> Connection {
+    let origin = Origin::File(file.path.clone());
+    let mut con = match file.content {
+        Ok(text) => Connection::from_text(&file.name, &text)
+            .with_backend(file_backend(settings, &file.path))
+            .with_origin(origin),
+        Err(e) => Connection::unreadable(&file.name, origin, e.to_string()),
+    };
+    con.set_watchdog(settings.watchdog(&file.name));
+    con
+}
+
+/// The connections served by the root [helper], listed with their error if unreadable.
+fn helper_connections(settings: &Settings) -> io::Result<Vec<Connection>> {
+    let names = helper::request("list", None)?;
+    let backend: Arc<dyn Backend> = Arc::new(Helper::new(helper::Statuses::watch()));
+    Ok(names
+        .lines()
+        .map(|name| {
+            let mut con = match helper::config(name) {
+                Ok(config) => Connection::new(name, config)
+                    .with_backend(backend.clone())
+                    .with_origin(Origin::Helper),
+                Err(e) => Connection::unreadable(name, Origin::Helper, e.to_string()),
+            };
+            con.set_watchdog(settings.watchdog(name));
+            con
+        })
+        .collect())
+}
+
+/// The directories to watch for changes of the files of the `sources`.
+fn dirs(sources: &[Source]) -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = Vec::new();
+    for dir in sources.iter().map(Source::dir) {
+        if !dirs.iter().any(|d| d == dir) {
+            dirs.push(dir.to_path_buf());
+        }
+    }
+    dirs
+}
+
+/// Lists the wireguard interfaces every [`UNMANAGED_POLL`] and sends their configurations when
+/// some interface appears, disappears or is configured differently.
+///
+/// Watching stops with the next change after the receiver is dropped.
+fn watch_unmanaged() -> Receiver<HashMap<String, WgConfig>> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let mut last = None;
+        loop {
+            let settings: HashMap<String, String> = runtime::dump_all()
+                .map(|all| runtime::split_dumps(&all))
+                .unwrap_or_default()
+                .into_iter()
+                .map(|(name, dump)| (name, runtime::dump_settings(&dump)))
+                .collect();
+            if last.as_ref() != Some(&settings) {
+                let configs = settings
+                    .keys()
+                    .filter_map(|name| Some((name.clone(), runtime::config(name).ok()?)))
+                    .collect();
+                if sender.send(configs).is_err() {
+                    return;
+                }
+                last = Some(settings);
+            }
+            thread::sleep(UNMANAGED_POLL);
+        }
+    });
+    receiver
+}
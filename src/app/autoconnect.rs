@@ -0,0 +1,319 @@
+//! Connects and disconnects connections when the network changes.
+//!
+//! The network is looked at again in the background each time the kernel notifies a change of the
+//! links, addresses or routes: the default route, the local networks and the Wi-Fi SSID.
+use std::{
+    fmt::{self, Display},
+    io,
+    net::IpAddr,
+    process::{Command, Stdio},
+    str::FromStr,
+    sync::mpsc::{self, Receiver},
+    thread,
+    time::Duration,
+};
+
+use crate::wg::types::Cidr;
+
+use super::{
+    backend::netlink::{
+        NETLINK_ROUTE, RTMGRP_IPV4_IFADDR, RTMGRP_IPV4_ROUTE, RTMGRP_IPV6_IFADDR,
+        RTMGRP_IPV6_ROUTE, RTMGRP_LINK, Socket,
+    },
+    problems,
+};
+
+/// Time for a burst of notifications to end, e.g. a DHCP lease adding an address then routes.
+const SETTLE: Duration = Duration::from_secs(1);
+
+/// What the machine is connected to.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Network {
+    pub ssid: Option<String>,
+    /// Gateway of the default route.
+    pub gateway: Option<IpAddr>,
+    /// Networks of the local interfaces, wireguard ones excluded.
+    pub lan: Vec<Cidr>,
+}
+
+impl Network {
+    /// Looks at the current network, ignoring the `excluded` interfaces.
+    pub fn current(excluded: &[&str]) -> Self {
+        Self {
+            ssid: ssid(),
+            gateway: output("ip", &["route", "show", "default"])
+                .as_deref()
+                .and_then(parse_gateway),
+            lan: problems::lan_networks(excluded),
+        }
+    }
+}
+
+impl Display for Network {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.ssid, self.gateway) {
+            (Some(ssid), _) => write!(f, "Wi-Fi {ssid}"),
+            (None, Some(gateway)) => write!(f, "gateway {gateway}"),
+            (None, None) => write!(f, "no network"),
+        }
+    }
+}
+
+/// Sends the network each time it changes, starting with the current one.
+///
+/// The `excluded` interfaces are ignored. Watching stops with the next change after the receiver
+/// is dropped.
+pub fn watch(excluded: Vec<String>) -> io::Result<Receiver<Network>> {
+    let socket = Socket::open(NETLINK_ROUTE)?;
+    socket.subscribe(
+        RTMGRP_LINK
+            | RTMGRP_IPV4_IFADDR
+            | RTMGRP_IPV4_ROUTE
+            | RTMGRP_IPV6_IFADDR
+            | RTMGRP_IPV6_ROUTE,
+    )?;
+    let (sender, receiver) = mpsc::channel();
+
+    thread::spawn(move || {
+        let excluded: Vec<&str> = excluded.iter().map(String::as_str).collect();
+        let mut last = None;
+        loop {
+            let network = Network::current(&excluded);
+            if last.as_ref() != Some(&network) {
+                if sender.send(network.clone()).is_err() {
+                    return;
+                }
+                last = Some(network);
+            }
+
+            if socket.wait(None).is_err() {
+                return;
+            }
+            while socket.wait(Some(SETTLE)).unwrap_or(false) {}
+        }
+    });
+
+    Ok(receiver)
+}
+
+/// A fact about the network, e.g. `ssid Office` or `not network 10.1.0.0/16`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Condition {
+    negated: bool,
+    fact: Fact,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Fact {
+    Ssid(String),
+    Gateway(IpAddr),
+    /// A local network overlaps this one.
+    Network(Cidr),
+}
+
+impl Condition {
+    pub fn matches(&self, network: &Network) -> bool {
+        let matches = match &self.fact {
+            Fact::Ssid(ssid) => network.ssid.as_ref() == Some(ssid),
+            Fact::Gateway(gateway) => network.gateway == Some(*gateway),
+            Fact::Network(cidr) => network.lan.iter().any(|lan| lan.overlaps(cidr)),
+        };
+        matches != self.negated
+    }
+}
+
+impl FromStr for Condition {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (negated, s) = match s.strip_prefix("not ") {
+            Some(s) => (true, s.trim_start()),
+            None => (false, s),
+        };
+        let (kind, value) = s
+            .split_once(' ')
+            .ok_or_else(|| format!("missing value: {s}"))?;
+        let value = value.trim();
+
+        let fact = match kind {
+            "ssid" => Fact::Ssid(value.to_string()),
+            "gateway" => Fact::Gateway(
+                value
+                    .parse()
+                    .map_err(|_| format!("invalid gateway: {value}"))?,
+            ),
+            "network" => Fact::Network(value.parse()?),
+            _ => return Err(format!("unknown condition: {kind}")),
+        };
+        Ok(Self { negated, fact })
+    }
+}
+
+impl Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.negated {
+            write!(f, "not ")?;
+        }
+        match &self.fact {
+            Fact::Ssid(ssid) => write!(f, "ssid {ssid}"),
+            Fact::Gateway(gateway) => write!(f, "gateway {gateway}"),
+            Fact::Network(cidr) => write!(f, "network {cidr}"),
+        }
+    }
+}
+
+/// What to do with a connection when the network changes.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Action {
+    Connect,
+    Disconnect,
+}
+
+/// When to connect and disconnect a connection.
+///
+/// Each list of conditions must match as a whole, an empty list never matches.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Rules {
+    pub connect_when: Vec<Condition>,
+    pub disconnect_when: Vec<Condition>,
+}
+
+impl Rules {
+    pub fn is_empty(&self) -> bool {
+        self.connect_when.is_empty() && self.disconnect_when.is_empty()
+    }
+
+    /// What to do on `network`, disconnecting winning over connecting.
+    pub fn action(&self, network: &Network) -> Option<Action> {
+        let all = |conditions: &[Condition]| {
+            !conditions.is_empty() && conditions.iter().all(|c| c.matches(network))
+        };
+
+        if all(&self.disconnect_when) {
+            Some(Action::Disconnect)
+        } else if all(&self.connect_when) {
+            Some(Action::Connect)
+        } else {
+            None
+        }
+    }
+}
+
+/// Parses a comma separated list of conditions, failing on the first invalid one.
+pub fn parse_conditions(value: &str) -> Result<Vec<Condition>, String> {
+    value
+        .split(',')
+        .filter(|c| !c.trim().is_empty())
+        .map(str::parse)
+        .collect()
+}
+
+/// SSID of the Wi-Fi network, asking `iwgetid` then NetworkManager.
+fn ssid() -> Option<String> {
+    output("iwgetid", &["-r"])
+        .map(|ssid| ssid.trim().to_string())
+        .filter(|ssid| !ssid.is_empty())
+        .or_else(|| {
+            output("nmcli", &["-t", "-f", "active,ssid", "device", "wifi"])
+                .as_deref()
+                .and_then(parse_nmcli_ssid)
+        })
+}
+
+/// Output of a successful command.
+fn output(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program)
+        .args(args)
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Parses `ip route show default`, e.g. `default via 192.168.1.1 dev wlan0 proto dhcp`.
+fn parse_gateway(output: &str) -> Option<IpAddr> {
+    output.lines().find_map(|line| {
+        let mut words = line.split_whitespace();
+        words.find(|w| *w == "via")?;
+        words.next()?.parse().ok()
+    })
+}
+
+/// Parses `nmcli -t -f active,ssid device wifi`, e.g. `yes:Office`.
+fn parse_nmcli_ssid(output: &str) -> Option<String> {
+    output
+        .lines()
+        .find_map(|line| line.strip_prefix("yes:"))
+        .map(|ssid| ssid.replace("\\:", ":"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn condition() {
+        let condition: Condition = "not network 10.1.0.0/16".parse().unwrap();
+        assert_eq!(condition.to_string(), "not network 10.1.0.0/16");
+
+        let mut network = Network {
+            lan: vec!["10.1.2.0/24".parse().unwrap()],
+            ..Default::default()
+        };
+        assert!(!condition.matches(&network));
+        network.lan = vec!["192.168.1.0/24".parse().unwrap()];
+        assert!(condition.matches(&network));
+
+        let condition: Condition = "ssid Office Wi-Fi".parse().unwrap();
+        assert!(!condition.matches(&network));
+        network.ssid = Some("Office Wi-Fi".to_string());
+        assert!(condition.matches(&network));
+
+        assert!("ssid".parse::<Condition>().is_err());
+        assert!("moon phase full".parse::<Condition>().is_err());
+        assert!("gateway router".parse::<Condition>().is_err());
+        assert!(parse_conditions("ssid Office, gatway 10.0.0.1").is_err());
+        assert_eq!(parse_conditions(""), Ok(Vec::new()));
+    }
+
+    #[test]
+    fn action() {
+        let rules = Rules {
+            connect_when: parse_conditions("not network 10.1.0.0/16, not ssid Office").unwrap(),
+            disconnect_when: parse_conditions("ssid Office").unwrap(),
+        };
+        let mut network = Network {
+            ssid: Some("Home".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(rules.action(&network), Some(Action::Connect));
+
+        network.lan = vec!["10.1.2.0/24".parse().unwrap()];
+        assert_eq!(rules.action(&network), None);
+
+        network.ssid = Some("Office".to_string());
+        assert_eq!(rules.action(&network), Some(Action::Disconnect));
+
+        assert_eq!(Rules::default().action(&network), None);
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!(
+            parse_gateway("default via 192.168.1.1 dev wlan0 proto dhcp metric 600\n"),
+            Some("192.168.1.1".parse().unwrap())
+        );
+        assert_eq!(parse_gateway("default dev ppp0 scope link\n"), None);
+
+        assert_eq!(
+            parse_nmcli_ssid("no:Neighbour\nyes:Office\\:5G\n"),
+            Some("Office:5G".to_string())
+        );
+        assert_eq!(parse_nmcli_ssid("no:Neighbour\n"), None);
+    }
+}
@@ -0,0 +1,215 @@
+//! How connections are brought up and down.
+use std::{
+    fmt::{self, Display},
+    io,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    str::FromStr,
+    sync::Arc,
+};
+
+use super::helper;
+use crate::wg::{
+    runtime,
+    types::{Key, key::Private},
+};
+
+pub use native::Native;
+pub use network_manager::NetworkManager;
+pub use systemd::Systemd;
+
+mod native;
+pub(super) mod netlink;
+pub mod network_manager;
+pub mod systemd;
+
+/// Brings interfaces up and down.
+///
+/// Methods block until done, connections call them from background threads.
+pub trait Backend: Send + Sync {
+    fn up(&self, name: &str) -> io::Result<()>;
+    fn down(&self, name: &str) -> io::Result<()>;
+
+    /// The output of `wg show <name> dump`, an error if the interface is down.
+    fn dump(&self, name: &str) -> io::Result<String> {
+        runtime::dump(name)
+    }
+
+    /// Sets the private key of the running `name` interface, keeping its peers.
+    fn set_private_key(&self, name: &str, key: &Key<Private>) -> io::Result<()> {
+        runtime::set_private_key(name, key)
+    }
+}
+
+/// Which [Backend] connections use.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum BackendKind {
+    #[default]
+    WgQuick,
+    Native,
+}
+
+impl BackendKind {
+    /// The backend of the configuration file at `path`.
+    pub fn backend(self, path: &Path) -> Arc<dyn Backend> {
+        match self {
+            Self::WgQuick => Arc::new(WgQuick::new(path)),
+            Self::Native => Arc::new(Native::new(path)),
+        }
+    }
+}
+
+impl FromStr for BackendKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "wg-quick" => Ok(Self::WgQuick),
+            "native" => Ok(Self::Native),
+            _ => Err(format!("unknown backend: {s}")),
+        }
+    }
+}
+
+impl Display for BackendKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::WgQuick => "wg-quick",
+            Self::Native => "native",
+        })
+    }
+}
+
+/// Runs `wg-quick` on a configuration file.
+pub struct WgQuick {
+    path: PathBuf,
+}
+
+impl WgQuick {
+    pub fn new(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+        }
+    }
+
+    fn run(&self, action: &str, name: &str) -> io::Result<()> {
+        let status = Command::new("wg-quick")
+            .arg(action)
+            .arg(&self.path)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()?;
+
+        match status.success() {
+            true => Ok(()),
+            false => Err(io::Error::other(format!("wg-quick {action} {name} failed"))),
+        }
+    }
+}
+
+impl Backend for WgQuick {
+    fn up(&self, name: &str) -> io::Result<()> {
+        self.run("up", name)
+    }
+
+    fn down(&self, name: &str) -> io::Result<()> {
+        self.run("down", name)
+    }
+}
+
+/// Goes through the root [helper](super::helper), for wtui run by a normal user.
+pub struct Helper {
+    statuses: Arc<helper::Statuses>,
+}
+
+impl Helper {
+    /// A backend reading the status of the interfaces from `statuses`, polled in the background.
+    pub fn new(statuses: Arc<helper::Statuses>) -> Self {
+        Self { statuses }
+    }
+}
+
+impl Backend for Helper {
+    fn up(&self, name: &str) -> io::Result<()> {
+        helper::request("up", Some(name)).map(|_| ())
+    }
+
+    fn down(&self, name: &str) -> io::Result<()> {
+        helper::request("down", Some(name)).map(|_| ())
+    }
+
+    fn dump(&self, name: &str) -> io::Result<String> {
+        self.statuses
+            .dump(name)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("{name} is down")))
+    }
+}
+
+/// Deletes interfaces created by other tools, which can't be brought up without a configuration.
+pub struct Unmanaged;
+
+impl Backend for Unmanaged {
+    fn up(&self, name: &str) -> io::Result<()> {
+        Err(io::Error::other(format!("{name} has no configuration")))
+    }
+
+    fn down(&self, name: &str) -> io::Result<()> {
+        let status = Command::new("ip")
+            .args(["link", "delete", "dev", name])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()?;
+
+        match status.success() {
+            true => Ok(()),
+            false => Err(io::Error::other(format!(
+                "ip link delete dev {name} failed"
+            ))),
+        }
+    }
+}
+
+/// Records the calls instead of touching interfaces.
+#[cfg(test)]
+#[derive(Default)]
+pub struct Mock {
+    calls: std::sync::Mutex<Vec<String>>,
+    /// Whether calls fail.
+    failing: bool,
+}
+
+#[cfg(test)]
+impl Mock {
+    /// A backend failing every call.
+    pub fn failing() -> Self {
+        Self {
+            failing: true,
+            ..Self::default()
+        }
+    }
+
+    /// Calls received so far, e.g. `up wg0`.
+    pub fn calls(&self) -> Vec<String> {
+        self.calls.lock().unwrap().clone()
+    }
+
+    fn result(&self) -> io::Result<()> {
+        match self.failing {
+            true => Err(io::Error::other("mock failure")),
+            false => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+impl Backend for Mock {
+    fn up(&self, name: &str) -> io::Result<()> {
+        self.calls.lock().unwrap().push(format!("up {name}"));
+        self.result()
+    }
+
+    fn down(&self, name: &str) -> io::Result<()> {
+        self.calls.lock().unwrap().push(format!("down {name}"));
+        self.result()
+    }
+}
@@ -0,0 +1,1190 @@
+//! `wg-quick up` and `down` done by wtui itself, through netlink.
+//!
+//! Only the kernel and a shell for the hooks are needed to connect, disconnect and read the status:
+//! no `wg-quick`, `ip` nor `wg`.
+//! `resolvconf` is used for DNS when installed.
+use std::{
+    cmp::Reverse,
+    ffi::CString,
+    fmt::{self, Display},
+    fs,
+    io::{self, Write},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use base64::{Engine, prelude::BASE64_STANDARD};
+
+use super::{
+    Backend,
+    netlink::{
+        self, Message, NETLINK_GENERIC, NETLINK_ROUTE, NLM_F_CREATE, NLM_F_DUMP, NLM_F_EXCL, Socket,
+    },
+};
+use crate::wg::{
+    Hook, Interface, WgConfig,
+    types::{Cidr, Key, key::Private},
+};
+
+// rtnetlink message types
+const RTM_NEWLINK: u16 = 16;
+const RTM_DELLINK: u16 = 17;
+const RTM_NEWADDR: u16 = 20;
+const RTM_NEWROUTE: u16 = 24;
+const RTM_NEWRULE: u16 = 32;
+const RTM_DELRULE: u16 = 33;
+
+// link attributes
+const IFLA_IFNAME: u16 = 3;
+const IFLA_MTU: u16 = 4;
+const IFLA_LINKINFO: u16 = 18;
+const IFLA_INFO_KIND: u16 = 1;
+
+// address attributes
+const IFA_ADDRESS: u16 = 1;
+const IFA_LOCAL: u16 = 2;
+
+// route attributes and values
+const RTA_DST: u16 = 1;
+const RTA_OIF: u16 = 4;
+const RTA_TABLE: u16 = 15;
+const RTPROT_BOOT: u8 = 3;
+const RT_SCOPE_LINK: u8 = 253;
+const RTN_UNICAST: u8 = 1;
+
+// rule attributes and values
+const FRA_FWMARK: u16 = 10;
+const FRA_SUPPRESS_PREFIXLEN: u16 = 14;
+const FRA_TABLE: u16 = 15;
+const FR_ACT_TO_TBL: u8 = 1;
+const FIB_RULE_INVERT: u32 = 0x2;
+
+// generic netlink controller
+const GENL_ID_CTRL: u16 = 0x10;
+const CTRL_CMD_GETFAMILY: u8 = 3;
+const CTRL_ATTR_FAMILY_ID: u16 = 1;
+const CTRL_ATTR_FAMILY_NAME: u16 = 2;
+
+// wireguard generic netlink family
+const WG_CMD_GET_DEVICE: u8 = 0;
+const WG_CMD_SET_DEVICE: u8 = 1;
+const WG_GENL_VERSION: u8 = 1;
+const WGDEVICE_A_IFNAME: u16 = 2;
+const WGDEVICE_A_PRIVATE_KEY: u16 = 3;
+const WGDEVICE_A_PUBLIC_KEY: u16 = 4;
+const WGDEVICE_A_FLAGS: u16 = 5;
+const WGDEVICE_A_LISTEN_PORT: u16 = 6;
+const WGDEVICE_A_FWMARK: u16 = 7;
+const WGDEVICE_A_PEERS: u16 = 8;
+const WGDEVICE_F_REPLACE_PEERS: u32 = 1;
+const WGPEER_A_PUBLIC_KEY: u16 = 1;
+const WGPEER_A_PRESHARED_KEY: u16 = 2;
+const WGPEER_A_FLAGS: u16 = 3;
+const WGPEER_A_ENDPOINT: u16 = 4;
+const WGPEER_A_PERSISTENT_KEEPALIVE_INTERVAL: u16 = 5;
+const WGPEER_A_LAST_HANDSHAKE_TIME: u16 = 6;
+const WGPEER_A_RX_BYTES: u16 = 7;
+const WGPEER_A_TX_BYTES: u16 = 8;
+const WGPEER_A_ALLOWEDIPS: u16 = 9;
+const WGPEER_F_REPLACE_ALLOWEDIPS: u32 = 2;
+const WGALLOWEDIP_A_FAMILY: u16 = 1;
+const WGALLOWEDIP_A_IPADDR: u16 = 2;
+const WGALLOWEDIP_A_CIDR_MASK: u16 = 3;
+
+/// Main routing table.
+const MAIN_TABLE: u32 = 254;
+/// Table of the default routes when `Table` isn't set, also used as the mark, like `wg-quick`.
+const DEFAULT_TABLE: u32 = 51820;
+/// Where `/etc/resolv.conf` is saved while connections override it, with their nameservers.
+const RESOLV_BACKUP_DIR: &str = "/run/wtui";
+
+/// Brings interfaces up and down without external programs.
+///
+/// Configurations with several peers and `SaveConfig` aren't supported.
+pub struct Native {
+    /// The configuration file.
+    path: PathBuf,
+}
+
+impl Native {
+    pub fn new(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+        }
+    }
+}
+
+impl Backend for Native {
+    fn up(&self, name: &str) -> io::Result<()> {
+        let config = read(&self.path, name)?;
+
+        hooks(name, &config.interface, Hook::PreUp)?;
+        let mut route = Socket::open(NETLINK_ROUTE)?;
+        add_link(&mut route, name)?;
+        let index = link_index(name)?;
+
+        if let Err(e) = configure(&mut route, name, index, &config) {
+            // leave the system as it was
+            let _ = remove_rules(&mut route, &config);
+            let _ = delete_link(&mut route, index);
+            let _ = unset_dns(name);
+            return Err(e);
+        }
+
+        hooks(name, &config.interface, Hook::PostUp)
+    }
+
+    fn down(&self, name: &str) -> io::Result<()> {
+        let index = link_index(name)?;
+        // the interface goes down even if its configuration is gone
+        let config = read(&self.path, name).ok();
+        let mut route = Socket::open(NETLINK_ROUTE)?;
+
+        if let Some(config) = &config {
+            hooks(name, &config.interface, Hook::PreDown)?;
+            remove_rules(&mut route, config)?;
+        }
+        delete_link(&mut route, index)?;
+        unset_dns(name)?;
+        match &config {
+            Some(config) => hooks(name, &config.interface, Hook::PostDown),
+            None => Ok(()),
+        }
+    }
+
+    fn dump(&self, name: &str) -> io::Result<String> {
+        let mut genl = Socket::open(NETLINK_GENERIC)?;
+        let family = wireguard_family(&mut genl)?;
+
+        let mut message = Message::new(family, NLM_F_DUMP);
+        message
+            .header(&[WG_CMD_GET_DEVICE, WG_GENL_VERSION, 0, 0])
+            .attr_str(WGDEVICE_A_IFNAME, name);
+        Ok(Device::parse(&genl.request(message)?).to_string())
+    }
+
+    fn set_private_key(&self, name: &str, key: &Key<Private>) -> io::Result<()> {
+        let mut genl = Socket::open(NETLINK_GENERIC)?;
+        let family = wireguard_family(&mut genl)?;
+        genl.request(private_key_message(family, name, key)?)?;
+        Ok(())
+    }
+}
+
+/// Reads and checks the configuration at `path` of the `name` interface.
+fn read(path: &Path, name: &str) -> io::Result<WgConfig> {
+    let text = fs::read_to_string(path)?;
+
+    if text.lines().filter(|l| l.trim() == "[Peer]").count() > 1 {
+        return Err(io::Error::other(format!(
+            "{name} has several peers, use the wg-quick backend"
+        )));
+    }
+    let config = WgConfig::from(text.as_str());
+    match config.validate().first() {
+        Some(problem) => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{name}: {problem}"),
+        )),
+        None => Ok(config),
+    }
+}
+
+/// Configures the new `index` link, like `wg-quick up` does once the link exists.
+fn configure(route: &mut Socket, name: &str, index: u32, config: &WgConfig) -> io::Result<()> {
+    let routing = Routing::new(&config.interface, config.peer.allowed_ips())?;
+
+    let endpoint = match config.peer.endpoint() {
+        Some(endpoint) => {
+            let ip =
+                endpoint.resolve()?.into_iter().next().ok_or_else(|| {
+                    io::Error::other(format!("{} doesn't resolve", endpoint.host()))
+                })?;
+            Some(SocketAddr::new(ip, endpoint.port()))
+        }
+        None => None,
+    };
+    let mut genl = Socket::open(NETLINK_GENERIC)?;
+    let family = wireguard_family(&mut genl)?;
+    genl.request(device_message(
+        family,
+        name,
+        config,
+        routing.fwmark,
+        endpoint,
+    )?)?;
+
+    for address in config.interface.address() {
+        add_address(route, index, address)?;
+    }
+    set_link_up(route, index, config.interface.configured_mtu())?;
+    set_dns(name, config.interface.dns())?;
+
+    for (network, table) in &routing.routes {
+        add_route(route, index, network, *table)?;
+    }
+    for (family, table) in routing.policy() {
+        if family == libc::AF_INET as u8 {
+            // lets replies to the marked packets pass the reverse path filter, best effort
+            let _ = fs::write("/proc/sys/net/ipv4/conf/all/src_valid_mark", "1");
+        }
+        for rule in rules(family, table) {
+            route.request(rule.into_message(RTM_NEWRULE, NLM_F_CREATE | NLM_F_EXCL))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Removes the policy routing rules added for the default routes of `config`.
+fn remove_rules(route: &mut Socket, config: &WgConfig) -> io::Result<()> {
+    let routing = Routing::new(&config.interface, config.peer.allowed_ips())?;
+    for (family, table) in routing.policy() {
+        for rule in rules(family, table) {
+            match route.request(rule.into_message(RTM_DELRULE, 0)) {
+                // already gone
+                Err(e) if e.raw_os_error() == Some(libc::ENOENT) => {}
+                result => result.map(|_| ())?,
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Where the routes of the AllowedIPs go.
+#[derive(Debug, PartialEq)]
+struct Routing {
+    /// Networks with their table, the most specific first.
+    routes: Vec<(Cidr, u32)>,
+    /// Mark of the packets sent by the interface.
+    fwmark: Option<u32>,
+    /// Table of the default routes, used by packets without the mark.
+    default_table: Option<u32>,
+}
+
+impl Routing {
+    /// Follows `wg-quick`: default routes get their own table unless `Table` is set.
+    fn new(interface: &Interface, allowed_ips: &[Cidr]) -> io::Result<Self> {
+        let table = match interface.table() {
+            Some("off") => {
+                return Ok(Self {
+                    routes: Vec::new(),
+                    fwmark: interface.fwmark(),
+                    default_table: None,
+                });
+            }
+            None | Some("auto") => None,
+            Some("main") => Some(MAIN_TABLE),
+            Some(table) => Some(
+                table
+                    .parse()
+                    .map_err(|_| io::Error::other(format!("table {table} must be a number")))?,
+            ),
+        };
+
+        let mut networks = allowed_ips.to_vec();
+        networks.sort_by_key(|n| Reverse(n.prefix()));
+        let default_table = (table.is_none() && networks.iter().any(|n| n.prefix() == 0))
+            .then(|| interface.fwmark().unwrap_or(DEFAULT_TABLE));
+
+        let routes = networks
+            .into_iter()
+            .map(|network| match (table, default_table) {
+                (Some(table), _) => (network, table),
+                (None, Some(default)) if network.prefix() == 0 => (network, default),
+                (None, _) => (network, MAIN_TABLE),
+            })
+            .collect();
+
+        Ok(Self {
+            routes,
+            fwmark: default_table.or(interface.fwmark()),
+            default_table,
+        })
+    }
+
+    /// Families needing policy routing, with the table of their default route.
+    fn policy(&self) -> impl Iterator<Item = (u8, u32)> + '_ {
+        self.routes
+            .iter()
+            .filter(|(network, table)| network.prefix() == 0 && Some(*table) == self.default_table)
+            .map(|(network, table)| (family(network.addr()), *table))
+    }
+}
+
+/// A policy routing rule.
+struct Rule {
+    family: u8,
+    table: u32,
+    /// Packets without this mark match.
+    not_fwmark: Option<u32>,
+    /// Routes of this prefix length or shorter are ignored.
+    suppress_prefixlength: Option<u32>,
+}
+
+/// Rules of `wg-quick` for default routes:
+/// `not fwmark <table> table <table>` and `table main suppress_prefixlength 0`.
+fn rules(family: u8, table: u32) -> [Rule; 2] {
+    [
+        Rule {
+            family,
+            table,
+            not_fwmark: Some(table),
+            suppress_prefixlength: None,
+        },
+        Rule {
+            family,
+            table: MAIN_TABLE,
+            not_fwmark: None,
+            suppress_prefixlength: Some(0),
+        },
+    ]
+}
+
+impl Rule {
+    fn into_message(self, kind: u16, flags: u16) -> Message {
+        let flags_field = match self.not_fwmark {
+            Some(_) => FIB_RULE_INVERT,
+            None => 0,
+        };
+        let mut header = vec![
+            self.family,
+            0,
+            0,
+            0,
+            table_byte(self.table),
+            0,
+            0,
+            FR_ACT_TO_TBL,
+        ];
+        header.extend_from_slice(&flags_field.to_ne_bytes());
+
+        let mut message = Message::new(kind, flags);
+        message.header(&header).attr_u32(FRA_TABLE, self.table);
+        if let Some(mark) = self.not_fwmark {
+            message.attr_u32(FRA_FWMARK, mark);
+        }
+        if let Some(length) = self.suppress_prefixlength {
+            message.attr_u32(FRA_SUPPRESS_PREFIXLEN, length);
+        }
+        message
+    }
+}
+
+fn link_index(name: &str) -> io::Result<u32> {
+    let name = CString::new(name).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    // SAFETY: `name` is a valid nul terminated string
+    match unsafe { libc::if_nametoindex(name.as_ptr()) } {
+        0 => Err(io::Error::last_os_error()),
+        index => Ok(index),
+    }
+}
+
+/// `struct ifinfomsg` of the link `index`.
+fn link_header(index: u32, flags: u32, change: u32) -> Vec<u8> {
+    let mut header = vec![libc::AF_UNSPEC as u8, 0, 0, 0];
+    header.extend_from_slice(&index.to_ne_bytes());
+    header.extend_from_slice(&flags.to_ne_bytes());
+    header.extend_from_slice(&change.to_ne_bytes());
+    header
+}
+
+/// `ip link add <name> type wireguard`
+fn add_link(route: &mut Socket, name: &str) -> io::Result<()> {
+    let mut message = Message::new(RTM_NEWLINK, NLM_F_CREATE | NLM_F_EXCL);
+    message
+        .header(&link_header(0, 0, 0))
+        .attr_str(IFLA_IFNAME, name)
+        .nest(IFLA_LINKINFO)
+        .attr_str(IFLA_INFO_KIND, "wireguard")
+        .end();
+    route.request(message).map(|_| ())
+}
+
+/// `ip link delete <name>`, which also removes its addresses and routes.
+fn delete_link(route: &mut Socket, index: u32) -> io::Result<()> {
+    let mut message = Message::new(RTM_DELLINK, 0);
+    message.header(&link_header(index, 0, 0));
+    route.request(message).map(|_| ())
+}
+
+/// `ip link set <name> mtu <mtu> up`, keeping the MTU of the kernel if [None].
+fn set_link_up(route: &mut Socket, index: u32, mtu: Option<u16>) -> io::Result<()> {
+    let up = libc::IFF_UP as u32;
+    let mut message = Message::new(RTM_NEWLINK, 0);
+    message.header(&link_header(index, up, up));
+    if let Some(mtu) = mtu {
+        message.attr_u32(IFLA_MTU, mtu.into());
+    }
+    route.request(message).map(|_| ())
+}
+
+/// `ip address add <address> dev <name>`
+fn add_address(route: &mut Socket, index: u32, address: &Cidr) -> io::Result<()> {
+    let mut header = vec![family(address.addr()), address.prefix(), 0, 0];
+    header.extend_from_slice(&index.to_ne_bytes());
+    let ip = ip_bytes(address.addr());
+
+    let mut message = Message::new(RTM_NEWADDR, NLM_F_CREATE | NLM_F_EXCL);
+    message
+        .header(&header)
+        .attr(IFA_LOCAL, &ip)
+        .attr(IFA_ADDRESS, &ip);
+    route.request(message).map(|_| ())
+}
+
+/// `ip route add <network> dev <name> table <table>`
+///
+/// Routes already there, like the one of the address network, are kept.
+fn add_route(route: &mut Socket, index: u32, network: &Cidr, table: u32) -> io::Result<()> {
+    let network = network.network();
+    let header = [
+        family(network.addr()),
+        network.prefix(),
+        0,
+        0,
+        table_byte(table),
+        RTPROT_BOOT,
+        RT_SCOPE_LINK,
+        RTN_UNICAST,
+        0,
+        0,
+        0,
+        0,
+    ];
+
+    let mut message = Message::new(RTM_NEWROUTE, NLM_F_CREATE | NLM_F_EXCL);
+    message
+        .header(&header)
+        .attr(RTA_DST, &ip_bytes(network.addr()))
+        .attr_u32(RTA_OIF, index)
+        .attr_u32(RTA_TABLE, table);
+    match route.request(message) {
+        Err(e) if e.raw_os_error() == Some(libc::EEXIST) => Ok(()),
+        result => result.map(|_| ()),
+    }
+}
+
+/// Id of the wireguard generic netlink family.
+fn wireguard_family(genl: &mut Socket) -> io::Result<u16> {
+    let mut message = Message::new(GENL_ID_CTRL, 0);
+    message
+        .header(&[CTRL_CMD_GETFAMILY, 1, 0, 0])
+        .attr_str(CTRL_ATTR_FAMILY_NAME, "wireguard");
+
+    let not_loaded = || io::Error::other("wireguard kernel module not loaded");
+    genl.request(message)
+        .map_err(|e| match e.kind() {
+            io::ErrorKind::NotFound => not_loaded(),
+            _ => e,
+        })?
+        .iter()
+        // skip the genlmsghdr
+        .flat_map(|answer| netlink::attributes(answer.get(4..).unwrap_or_default()))
+        .find(|(kind, _)| *kind == CTRL_ATTR_FAMILY_ID)
+        .and_then(|(_, id)| Some(u16::from_ne_bytes(id.try_into().ok()?)))
+        .ok_or_else(not_loaded)
+}
+
+/// `wg setconf <name>`: the keys, port, mark and the peer, replacing the current ones.
+fn device_message(
+    family: u16,
+    name: &str,
+    config: &WgConfig,
+    fwmark: Option<u32>,
+    endpoint: Option<SocketAddr>,
+) -> io::Result<Message> {
+    let interface = &config.interface;
+    let peer = &config.peer;
+
+    let mut message = Message::new(family, 0);
+    message
+        .header(&[WG_CMD_SET_DEVICE, WG_GENL_VERSION, 0, 0])
+        .attr_str(WGDEVICE_A_IFNAME, name)
+        .attr(WGDEVICE_A_PRIVATE_KEY, &key_bytes(interface.private_key())?)
+        .attr_u32(WGDEVICE_A_FLAGS, WGDEVICE_F_REPLACE_PEERS)
+        .attr_u32(WGDEVICE_A_FWMARK, fwmark.unwrap_or(0));
+    if let Some(port) = interface.listen_port() {
+        message.attr_u16(WGDEVICE_A_LISTEN_PORT, port);
+    }
+
+    message
+        .nest(WGDEVICE_A_PEERS)
+        .nest(0)
+        .attr(WGPEER_A_PUBLIC_KEY, &key_bytes(peer.pubkey())?)
+        .attr_u32(WGPEER_A_FLAGS, WGPEER_F_REPLACE_ALLOWEDIPS);
+    if let Some(key) = peer.preshared_key() {
+        message.attr(WGPEER_A_PRESHARED_KEY, &key_bytes(key)?);
+    }
+    if let Some(endpoint) = endpoint {
+        message.attr(WGPEER_A_ENDPOINT, &sockaddr(endpoint));
+    }
+    if let Some(keepalive) = peer.persistent_keepalive() {
+        message.attr_u16(WGPEER_A_PERSISTENT_KEEPALIVE_INTERVAL, keepalive);
+    }
+    message.nest(WGPEER_A_ALLOWEDIPS);
+    for network in peer.allowed_ips() {
+        message
+            .nest(0)
+            .attr_u16(WGALLOWEDIP_A_FAMILY, family_of(network.addr()))
+            .attr(WGALLOWEDIP_A_IPADDR, &ip_bytes(network.addr()))
+            .attr_u8(WGALLOWEDIP_A_CIDR_MASK, network.prefix())
+            .end();
+    }
+    message.end().end().end();
+
+    Ok(message)
+}
+
+/// `wg set <name> private-key`: only the private key, the peers are kept.
+fn private_key_message(family: u16, name: &str, key: &Key<Private>) -> io::Result<Message> {
+    let mut message = Message::new(family, 0);
+    message
+        .header(&[WG_CMD_SET_DEVICE, WG_GENL_VERSION, 0, 0])
+        .attr_str(WGDEVICE_A_IFNAME, name)
+        .attr(WGDEVICE_A_PRIVATE_KEY, &key_bytes(key)?);
+    Ok(message)
+}
+
+/// A device as answered to `WG_CMD_GET_DEVICE`, shown like `wg show <name> dump`.
+#[derive(Debug, Default)]
+struct Device {
+    private_key: Option<Vec<u8>>,
+    public_key: Option<Vec<u8>>,
+    listen_port: u16,
+    fwmark: u32,
+    peers: Vec<DevicePeer>,
+}
+
+#[derive(Debug, Default)]
+struct DevicePeer {
+    public_key: Vec<u8>,
+    preshared_key: Option<Vec<u8>>,
+    endpoint: Option<SocketAddr>,
+    allowed_ips: Vec<String>,
+    /// Seconds since the epoch, 0 if never.
+    latest_handshake: i64,
+    received: u64,
+    sent: u64,
+    keepalive: u16,
+}
+
+impl Device {
+    /// Reads the `answers` to a dump, the peers of large devices being split across them.
+    fn parse(answers: &[Vec<u8>]) -> Self {
+        let mut device = Self::default();
+        // skip the genlmsghdr
+        for (kind, value) in answers
+            .iter()
+            .flat_map(|answer| netlink::attributes(answer.get(4..).unwrap_or_default()))
+        {
+            match kind {
+                WGDEVICE_A_PRIVATE_KEY => device.private_key = non_zero_key(value),
+                WGDEVICE_A_PUBLIC_KEY => device.public_key = non_zero_key(value),
+                WGDEVICE_A_LISTEN_PORT => device.listen_port = number(value) as u16,
+                WGDEVICE_A_FWMARK => device.fwmark = number(value) as u32,
+                WGDEVICE_A_PEERS => {
+                    for (_, peer) in netlink::attributes(value) {
+                        device.add_peer(DevicePeer::parse(peer));
+                    }
+                }
+                _ => {}
+            }
+        }
+        device
+    }
+
+    /// Adds `peer`, or its allowed IPs when it continues the previous answer.
+    fn add_peer(&mut self, peer: DevicePeer) {
+        match self.peers.last_mut() {
+            Some(last) if last.public_key == peer.public_key => {
+                last.allowed_ips.extend(peer.allowed_ips)
+            }
+            _ => self.peers.push(peer),
+        }
+    }
+}
+
+impl DevicePeer {
+    fn parse(attributes: &[u8]) -> Self {
+        let mut peer = Self::default();
+        for (kind, value) in netlink::attributes(attributes) {
+            match kind {
+                WGPEER_A_PUBLIC_KEY => peer.public_key = value.to_vec(),
+                WGPEER_A_PRESHARED_KEY => peer.preshared_key = non_zero_key(value),
+                WGPEER_A_ENDPOINT => peer.endpoint = socket_addr(value),
+                WGPEER_A_PERSISTENT_KEEPALIVE_INTERVAL => peer.keepalive = number(value) as u16,
+                // struct __kernel_timespec, seconds first
+                WGPEER_A_LAST_HANDSHAKE_TIME => {
+                    peer.latest_handshake = number(value.get(..8).unwrap_or_default()) as i64
+                }
+                WGPEER_A_RX_BYTES => peer.received = number(value),
+                WGPEER_A_TX_BYTES => peer.sent = number(value),
+                WGPEER_A_ALLOWEDIPS => peer
+                    .allowed_ips
+                    .extend(netlink::attributes(value).filter_map(|(_, ip)| allowed_ip(ip))),
+                _ => {}
+            }
+        }
+        peer
+    }
+}
+
+impl Display for Device {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let fwmark = match self.fwmark {
+            0 => "off".to_string(),
+            mark => format!("0x{mark:x}"),
+        };
+        writeln!(
+            f,
+            "{}\t{}\t{}\t{fwmark}",
+            base64_key(self.private_key.as_deref()),
+            base64_key(self.public_key.as_deref()),
+            self.listen_port
+        )?;
+
+        for peer in &self.peers {
+            let allowed_ips = match peer.allowed_ips.is_empty() {
+                true => "(none)".to_string(),
+                false => peer.allowed_ips.join(","),
+            };
+            let keepalive = match peer.keepalive {
+                0 => "off".to_string(),
+                secs => secs.to_string(),
+            };
+            writeln!(
+                f,
+                "{}\t{}\t{}\t{allowed_ips}\t{}\t{}\t{}\t{keepalive}",
+                base64_key(Some(&peer.public_key)),
+                base64_key(peer.preshared_key.as_deref()),
+                peer.endpoint
+                    .map_or("(none)".to_string(), |endpoint| endpoint.to_string()),
+                peer.latest_handshake,
+                peer.received,
+                peer.sent
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// A key, [None] if all zeros which means unset for the kernel.
+fn non_zero_key(value: &[u8]) -> Option<Vec<u8>> {
+    value.iter().any(|b| *b != 0).then(|| value.to_vec())
+}
+
+fn base64_key(key: Option<&[u8]>) -> String {
+    key.map_or("(none)".to_string(), |key| BASE64_STANDARD.encode(key))
+}
+
+/// An unsigned number of up to 8 bytes, in native order.
+fn number(value: &[u8]) -> u64 {
+    let mut bytes = [0u8; 8];
+    let len = value.len().min(8);
+    match cfg!(target_endian = "little") {
+        true => bytes[..len].copy_from_slice(&value[..len]),
+        false => bytes[8 - len..].copy_from_slice(&value[..len]),
+    }
+    u64::from_ne_bytes(bytes)
+}
+
+/// `<ip>/<prefix>` of a `WGALLOWEDIP_A_*` nest.
+fn allowed_ip(attributes: &[u8]) -> Option<String> {
+    let mut ip = None;
+    let mut prefix = None;
+    for (kind, value) in netlink::attributes(attributes) {
+        match kind {
+            WGALLOWEDIP_A_IPADDR => ip = ip_addr(value),
+            WGALLOWEDIP_A_CIDR_MASK => prefix = value.first().copied(),
+            _ => {}
+        }
+    }
+    Some(format!("{}/{}", ip?, prefix?))
+}
+
+fn ip_addr(bytes: &[u8]) -> Option<IpAddr> {
+    match bytes.len() {
+        4 => Some(IpAddr::V4(Ipv4Addr::from(<[u8; 4]>::try_from(bytes).ok()?))),
+        16 => Some(IpAddr::V6(Ipv6Addr::from(
+            <[u8; 16]>::try_from(bytes).ok()?,
+        ))),
+        _ => None,
+    }
+}
+
+/// The address in a `struct sockaddr_in` or `struct sockaddr_in6`, see [sockaddr].
+fn socket_addr(bytes: &[u8]) -> Option<SocketAddr> {
+    let family = u16::from_ne_bytes(bytes.get(..2)?.try_into().ok()?);
+    let port = u16::from_be_bytes(bytes.get(2..4)?.try_into().ok()?);
+    match i32::from(family) {
+        libc::AF_INET => {
+            let ip = <[u8; 4]>::try_from(bytes.get(4..8)?).ok()?;
+            Some(SocketAddr::V4(SocketAddrV4::new(ip.into(), port)))
+        }
+        libc::AF_INET6 => {
+            let flowinfo = u32::from_be_bytes(bytes.get(4..8)?.try_into().ok()?);
+            let ip = <[u8; 16]>::try_from(bytes.get(8..24)?).ok()?;
+            let scope_id = u32::from_ne_bytes(bytes.get(24..28)?.try_into().ok()?);
+            Some(SocketAddr::V6(SocketAddrV6::new(
+                ip.into(),
+                port,
+                flowinfo,
+                scope_id,
+            )))
+        }
+        _ => None,
+    }
+}
+
+/// Runs the commands of `hook`, `%i` being replaced by the interface name.
+fn hooks(name: &str, interface: &Interface, hook: Hook) -> io::Result<()> {
+    for command in interface.commands(hook) {
+        let status = Command::new("sh")
+            .args(["-c", &command.replace("%i", name)])
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()?;
+        if !status.success() {
+            return Err(io::Error::other(format!(
+                "{} failed: {command}",
+                hook.key()
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Uses the `dns` servers while the `name` connection is up.
+///
+/// Goes through `resolvconf` when installed, like `wg-quick`, otherwise `/etc/resolv.conf` lists
+/// the servers of every connection up, see [Resolv].
+fn set_dns(name: &str, dns: &[IpAddr]) -> io::Result<()> {
+    if dns.is_empty() {
+        return Ok(());
+    }
+    let content: String = dns.iter().map(|ip| format!("nameserver {ip}\n")).collect();
+
+    match Command::new("resolvconf")
+        .args(["-a", &format!("tun.{name}"), "-m", "0", "-x"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+    {
+        Ok(mut child) => {
+            child
+                .stdin
+                .take()
+                .expect("stdin is piped")
+                .write_all(content.as_bytes())?;
+            match child.wait()?.success() {
+                true => Ok(()),
+                false => Err(io::Error::other("resolvconf failed")),
+            }
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Resolv::system().add(name, &content),
+        Err(e) => Err(e),
+    }
+}
+
+/// Stops using the DNS servers of the `name` connection.
+fn unset_dns(name: &str) -> io::Result<()> {
+    if Resolv::system().remove(name)? {
+        return Ok(());
+    }
+
+    match Command::new("resolvconf")
+        .args(["-d", &format!("tun.{name}"), "-f"])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+    {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+/// `/etc/resolv.conf` replaced without `resolvconf`.
+///
+/// The original is saved once, when the first connection sets its servers, and restored when
+/// the last one goes down. The servers of each connection are kept next to it, so that those of
+/// the other connections stay when one goes down.
+struct Resolv {
+    /// Where the original and the servers are saved.
+    dir: PathBuf,
+    resolv_conf: PathBuf,
+}
+
+impl Resolv {
+    fn system() -> Self {
+        Self {
+            dir: PathBuf::from(RESOLV_BACKUP_DIR),
+            resolv_conf: PathBuf::from("/etc/resolv.conf"),
+        }
+    }
+
+    fn backup(&self) -> PathBuf {
+        self.dir.join("resolv.conf")
+    }
+
+    fn servers(&self, name: &str) -> PathBuf {
+        self.dir.join(format!("dns.{name}"))
+    }
+
+    /// Adds the `content` of the `name` connection, e.g. `nameserver 10.0.0.1` lines.
+    fn add(&self, name: &str, content: &str) -> io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        if !self.backup().exists() {
+            fs::copy(&self.resolv_conf, self.backup())?;
+        }
+        fs::write(self.servers(name), content)?;
+        self.write()
+    }
+
+    /// Removes the servers of the `name` connection, false if it has none here.
+    fn remove(&self, name: &str) -> io::Result<bool> {
+        match fs::remove_file(self.servers(name)) {
+            Ok(()) => self.write().map(|_| true),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Writes the servers of the connections up, or restores the original without any.
+    fn write(&self) -> io::Result<()> {
+        let mut files: Vec<_> = fs::read_dir(&self.dir)?
+            .filter_map(Result::ok)
+            .map(|entry| entry.path())
+            .filter(|path| {
+                path.file_name()
+                    .is_some_and(|name| name.to_string_lossy().starts_with("dns."))
+            })
+            .collect();
+        files.sort();
+
+        if files.is_empty() {
+            fs::copy(self.backup(), &self.resolv_conf)?;
+            return fs::remove_file(self.backup());
+        }
+        let mut content = String::new();
+        for file in files {
+            content.push_str(&fs::read_to_string(file)?);
+        }
+        fs::write(&self.resolv_conf, content)
+    }
+}
+
+/// The 32 bytes of a base64 key.
+fn key_bytes(key: &str) -> io::Result<Vec<u8>> {
+    BASE64_STANDARD
+        .decode(key)
+        .ok()
+        .filter(|bytes| bytes.len() == 32)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "invalid key"))
+}
+
+/// `struct sockaddr_in` or `struct sockaddr_in6` of `addr`.
+fn sockaddr(addr: SocketAddr) -> Vec<u8> {
+    let mut bytes = family_of(addr.ip()).to_ne_bytes().to_vec();
+    bytes.extend_from_slice(&addr.port().to_be_bytes());
+    match addr {
+        SocketAddr::V4(addr) => {
+            bytes.extend_from_slice(&addr.ip().octets());
+            bytes.extend_from_slice(&[0; 8]);
+        }
+        SocketAddr::V6(addr) => {
+            bytes.extend_from_slice(&addr.flowinfo().to_be_bytes());
+            bytes.extend_from_slice(&addr.ip().octets());
+            bytes.extend_from_slice(&addr.scope_id().to_ne_bytes());
+        }
+    }
+    bytes
+}
+
+fn ip_bytes(ip: IpAddr) -> Vec<u8> {
+    match ip {
+        IpAddr::V4(ip) => ip.octets().to_vec(),
+        IpAddr::V6(ip) => ip.octets().to_vec(),
+    }
+}
+
+fn family_of(ip: IpAddr) -> u16 {
+    match ip {
+        IpAddr::V4(_) => libc::AF_INET as u16,
+        IpAddr::V6(_) => libc::AF_INET6 as u16,
+    }
+}
+
+fn family(ip: IpAddr) -> u8 {
+    family_of(ip) as u8
+}
+
+/// Table in the one byte header field, tables above 255 only fit in the attribute.
+fn table_byte(table: u32) -> u8 {
+    u8::try_from(table).unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(extra_interface: &str, allowed_ips: &str) -> WgConfig {
+        WgConfig::from(
+            format!(
+                "[Interface]
+PrivateKey = oMVUWFwDf+20fIfeRUe7c0rlUKSYnHk2K0y2920SX1c=
+Address = 10.0.0.2/24
+{extra_interface}
+
+[Peer]
+PublicKey = 60TUAvOo+Wi4SCyir581cCyBx4wIcHtrIrUgBv/iqRM=
+AllowedIPs = {allowed_ips}"
+            )
+            .as_str(),
+        )
+    }
+
+    fn routing_of(config: &WgConfig) -> Routing {
+        Routing::new(&config.interface, config.peer.allowed_ips()).unwrap()
+    }
+
+    #[test]
+    fn split_routes() {
+        let config = config("", "10.0.0.0/24, 0.0.0.0/0, ::/0, 10.1.0.0/16");
+        let routing = routing_of(&config);
+
+        assert_eq!(
+            routing.routes,
+            vec![
+                ("10.0.0.0/24".parse().unwrap(), MAIN_TABLE),
+                ("10.1.0.0/16".parse().unwrap(), MAIN_TABLE),
+                ("0.0.0.0/0".parse().unwrap(), DEFAULT_TABLE),
+                ("::/0".parse().unwrap(), DEFAULT_TABLE),
+            ]
+        );
+        assert_eq!(routing.fwmark, Some(DEFAULT_TABLE));
+        assert_eq!(
+            routing.policy().collect::<Vec<_>>(),
+            vec![
+                (libc::AF_INET as u8, DEFAULT_TABLE),
+                (libc::AF_INET6 as u8, DEFAULT_TABLE)
+            ]
+        );
+    }
+
+    #[test]
+    fn table_setting() {
+        let routing = routing_of(&config("Table = 1234", "0.0.0.0/0"));
+        assert_eq!(routing.routes, vec![("0.0.0.0/0".parse().unwrap(), 1234)]);
+        assert_eq!(routing.policy().count(), 0);
+        assert_eq!(routing.fwmark, None);
+
+        let routing = routing_of(&config("Table = off\nFwMark = 42", "0.0.0.0/0"));
+        assert!(routing.routes.is_empty());
+        assert_eq!(routing.fwmark, Some(42));
+
+        let routing = routing_of(&config("FwMark = 0x1000", "0.0.0.0/0"));
+        assert_eq!(routing.policy().collect::<Vec<_>>(), vec![(2, 0x1000)]);
+
+        let config = config("Table = vpn", "0.0.0.0/0");
+        assert!(Routing::new(&config.interface, config.peer.allowed_ips()).is_err());
+    }
+
+    #[test]
+    fn device() {
+        let config = config("ListenPort = 51820", "10.0.0.0/24, ::/0");
+        let bytes = device_message(30, "wg0", &config, Some(7), Some(endpoint_addr()))
+            .unwrap()
+            .finish(1)
+            .unwrap();
+
+        // netlink and genl headers
+        let attrs: Vec<_> = netlink::attributes(&bytes[20..]).collect();
+        let kinds: Vec<_> = attrs.iter().map(|(kind, _)| *kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                WGDEVICE_A_IFNAME,
+                WGDEVICE_A_PRIVATE_KEY,
+                WGDEVICE_A_FLAGS,
+                WGDEVICE_A_FWMARK,
+                WGDEVICE_A_LISTEN_PORT,
+                WGDEVICE_A_PEERS
+            ]
+        );
+        assert_eq!(attrs[3].1, &7u32.to_ne_bytes()[..]);
+
+        let (_, peer) = netlink::attributes(attrs[5].1).next().unwrap();
+        let peer: Vec<_> = netlink::attributes(peer).collect();
+        let endpoint = peer.iter().find(|(kind, _)| *kind == WGPEER_A_ENDPOINT);
+        assert_eq!(endpoint.unwrap().1, &sockaddr(endpoint_addr())[..]);
+        let (_, allowed_ips) = peer
+            .iter()
+            .find(|(kind, _)| *kind == WGPEER_A_ALLOWEDIPS)
+            .unwrap();
+        let masks: Vec<_> = netlink::attributes(allowed_ips)
+            .map(|(_, ip)| {
+                netlink::attributes(ip)
+                    .find(|(kind, _)| *kind == WGALLOWEDIP_A_CIDR_MASK)
+                    .unwrap()
+                    .1[0]
+            })
+            .collect();
+        assert_eq!(masks, vec![24, 0]);
+    }
+
+    #[test]
+    fn private_key() {
+        let key = Key::from("oMVUWFwDf+20fIfeRUe7c0rlUKSYnHk2K0y2920SX1c=");
+        let bytes = private_key_message(30, "wg0", &key)
+            .unwrap()
+            .finish(1)
+            .unwrap();
+
+        let attrs: Vec<_> = netlink::attributes(&bytes[20..]).collect();
+        assert_eq!(attrs.len(), 2);
+        assert_eq!(attrs[1].0, WGDEVICE_A_PRIVATE_KEY);
+        assert_eq!(attrs[1].1, &key_bytes(&key).unwrap()[..]);
+        assert!(private_key_message(30, "wg0", &Key::from("")).is_err());
+    }
+
+    fn endpoint_addr() -> SocketAddr {
+        SocketAddr::new(IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1)), 51820)
+    }
+
+    #[test]
+    fn socket_addresses() {
+        let v4 = sockaddr(endpoint_addr());
+        assert_eq!(v4.len(), 16);
+        assert_eq!(&v4[2..8], &[0xca, 0x6c, 192, 0, 2, 1]);
+
+        let v6 = sockaddr(SocketAddr::V6(SocketAddrV6::new(
+            Ipv6Addr::LOCALHOST,
+            51820,
+            0,
+            0,
+        )));
+        assert_eq!(v6.len(), 28);
+        assert_eq!(v6[23], 1);
+
+        assert_eq!(socket_addr(&v4), Some(endpoint_addr()));
+        assert_eq!(
+            socket_addr(&v6),
+            Some("[::1]:51820".parse::<SocketAddr>().unwrap())
+        );
+    }
+
+    #[test]
+    fn device_dump() {
+        let key = |byte| [byte; 32];
+        let allowed_ip = |message: &mut Message, ip: IpAddr, prefix| {
+            message
+                .nest(0)
+                .attr_u16(WGALLOWEDIP_A_FAMILY, family_of(ip))
+                .attr(WGALLOWEDIP_A_IPADDR, &ip_bytes(ip))
+                .attr_u8(WGALLOWEDIP_A_CIDR_MASK, prefix)
+                .end();
+        };
+
+        let mut first = Message::new(30, 0);
+        first
+            .header(&[WG_CMD_GET_DEVICE, WG_GENL_VERSION, 0, 0])
+            .attr(WGDEVICE_A_PRIVATE_KEY, &key(1))
+            .attr(WGDEVICE_A_PUBLIC_KEY, &key(2))
+            .attr_u16(WGDEVICE_A_LISTEN_PORT, 51820)
+            .attr_u32(WGDEVICE_A_FWMARK, 0)
+            .nest(WGDEVICE_A_PEERS)
+            .nest(0)
+            .attr(WGPEER_A_PUBLIC_KEY, &key(3))
+            .attr(WGPEER_A_PRESHARED_KEY, &[0; 32])
+            .attr(WGPEER_A_ENDPOINT, &sockaddr(endpoint_addr()))
+            .attr_u16(WGPEER_A_PERSISTENT_KEEPALIVE_INTERVAL, 25)
+            .attr(
+                WGPEER_A_LAST_HANDSHAKE_TIME,
+                &[&1700000000i64.to_ne_bytes()[..], &[0; 8]].concat(),
+            )
+            .attr(WGPEER_A_RX_BYTES, &1024u64.to_ne_bytes())
+            .attr(WGPEER_A_TX_BYTES, &2048u64.to_ne_bytes())
+            .nest(WGPEER_A_ALLOWEDIPS);
+        allowed_ip(&mut first, "10.0.0.0".parse().unwrap(), 24);
+        first.end().end().end();
+
+        // the allowed IPs of the peer continue in the next answer
+        let mut second = Message::new(30, 0);
+        second
+            .header(&[WG_CMD_GET_DEVICE, WG_GENL_VERSION, 0, 0])
+            .nest(WGDEVICE_A_PEERS)
+            .nest(0)
+            .attr(WGPEER_A_PUBLIC_KEY, &key(3))
+            .nest(WGPEER_A_ALLOWEDIPS);
+        allowed_ip(&mut second, "::".parse().unwrap(), 0);
+        second
+            .end()
+            .end()
+            .nest(0)
+            .attr(WGPEER_A_PUBLIC_KEY, &key(4))
+            .end()
+            .end();
+
+        let answers = [
+            first.finish(1).unwrap()[16..].to_vec(),
+            second.finish(1).unwrap()[16..].to_vec(),
+        ];
+        let (one, two, three, four) = (
+            BASE64_STANDARD.encode(key(1)),
+            BASE64_STANDARD.encode(key(2)),
+            BASE64_STANDARD.encode(key(3)),
+            BASE64_STANDARD.encode(key(4)),
+        );
+        assert_eq!(
+            Device::parse(&answers).to_string(),
+            format!(
+                "{one}\t{two}\t51820\toff
+{three}\t(none)\t192.0.2.1:51820\t10.0.0.0/24,::/0\t1700000000\t1024\t2048\t25
+{four}\t(none)\t(none)\t(none)\t0\t0\t0\toff
+"
+            )
+        );
+    }
+
+    #[test]
+    fn resolv() {
+        let dir = std::env::temp_dir().join(format!("wtui-resolv-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let resolv = Resolv {
+            dir: dir.join("backup"),
+            resolv_conf: dir.join("resolv.conf"),
+        };
+        let read = || fs::read_to_string(&resolv.resolv_conf).unwrap();
+        fs::write(&resolv.resolv_conf, "nameserver 192.168.1.1\n").unwrap();
+
+        resolv.add("wg0", "nameserver 10.0.0.1\n").unwrap();
+        resolv.add("wg1", "nameserver 10.1.0.1\n").unwrap();
+        assert_eq!(read(), "nameserver 10.0.0.1\nnameserver 10.1.0.1\n");
+
+        // the first one going down leaves the servers of the other
+        assert!(resolv.remove("wg0").unwrap());
+        assert_eq!(read(), "nameserver 10.1.0.1\n");
+        assert!(!resolv.remove("wg0").unwrap());
+
+        assert!(resolv.remove("wg1").unwrap());
+        assert_eq!(read(), "nameserver 192.168.1.1\n");
+        assert!(!resolv.backup().exists());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn keys() {
+        assert_eq!(
+            key_bytes("oMVUWFwDf+20fIfeRUe7c0rlUKSYnHk2K0y2920SX1c=")
+                .unwrap()
+                .len(),
+            32
+        );
+        assert!(key_bytes("c2hvcnQ=").is_err());
+    }
+}
@@ -0,0 +1,370 @@
+//! Minimal netlink client, enough to configure links, addresses, routes and wireguard devices.
+//!
+//! Constants come from the kernel headers `linux/netlink.h`, `linux/rtnetlink.h`,
+//! `linux/if_link.h`, `linux/fib_rules.h`, `linux/genetlink.h` and `linux/wireguard.h`.
+use std::{
+    io, mem,
+    os::fd::{AsRawFd, FromRawFd, OwnedFd},
+    time::Duration,
+};
+
+pub const NETLINK_ROUTE: i32 = libc::NETLINK_ROUTE;
+pub const NETLINK_GENERIC: i32 = libc::NETLINK_GENERIC;
+
+// multicast groups of NETLINK_ROUTE, `RTMGRP_*`
+pub const RTMGRP_LINK: u32 = 0x1;
+pub const RTMGRP_IPV4_IFADDR: u32 = 0x10;
+pub const RTMGRP_IPV4_ROUTE: u32 = 0x40;
+pub const RTMGRP_IPV6_IFADDR: u32 = 0x100;
+pub const RTMGRP_IPV6_ROUTE: u32 = 0x400;
+
+// message flags
+pub const NLM_F_REQUEST: u16 = 0x1;
+pub const NLM_F_ACK: u16 = 0x4;
+pub const NLM_F_DUMP: u16 = 0x300;
+pub const NLM_F_EXCL: u16 = 0x200;
+pub const NLM_F_CREATE: u16 = 0x400;
+
+// message types
+const NLMSG_ERROR: u16 = 0x2;
+const NLMSG_DONE: u16 = 0x3;
+
+// attribute flags
+const NLA_F_NESTED: u16 = 0x8000;
+const NLA_F_NET_BYTEORDER: u16 = 0x4000;
+
+/// Size of the netlink message header.
+const HEADER: usize = 16;
+
+/// A netlink socket, talking to the kernel.
+pub struct Socket {
+    fd: OwnedFd,
+    seq: u32,
+}
+
+impl Socket {
+    /// Opens a socket for the netlink `protocol`, e.g. [NETLINK_ROUTE].
+    pub fn open(protocol: i32) -> io::Result<Self> {
+        // SAFETY: plain syscall, the descriptor is owned right after
+        let fd = unsafe {
+            libc::socket(
+                libc::AF_NETLINK,
+                libc::SOCK_RAW | libc::SOCK_CLOEXEC,
+                protocol,
+            )
+        };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(Self {
+            // SAFETY: `fd` is a freshly opened descriptor nobody else owns
+            fd: unsafe { OwnedFd::from_raw_fd(fd) },
+            seq: 0,
+        })
+    }
+
+    /// Joins the multicast `groups`, e.g. [RTMGRP_LINK], to be notified of their changes.
+    pub fn subscribe(&self, groups: u32) -> io::Result<()> {
+        // SAFETY: all zeroes is a valid sockaddr_nl
+        let mut addr: libc::sockaddr_nl = unsafe { mem::zeroed() };
+        addr.nl_family = libc::AF_NETLINK as libc::sa_family_t;
+        addr.nl_groups = groups;
+        // SAFETY: `addr` is valid for its size
+        let result = unsafe {
+            libc::bind(
+                self.fd.as_raw_fd(),
+                (&raw const addr).cast(),
+                mem::size_of::<libc::sockaddr_nl>() as libc::socklen_t,
+            )
+        };
+        match result {
+            0 => Ok(()),
+            _ => Err(io::Error::last_os_error()),
+        }
+    }
+
+    /// Waits for notifications, at most `timeout` or forever if [None], and discards them.
+    ///
+    /// `false` if none arrived in time.
+    pub fn wait(&self, timeout: Option<Duration>) -> io::Result<bool> {
+        let mut poll = libc::pollfd {
+            fd: self.fd.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        let timeout = timeout.map_or(-1, |t| t.as_millis().min(i32::MAX as u128) as i32);
+        // SAFETY: a single valid pollfd
+        match unsafe { libc::poll(&mut poll, 1, timeout) } {
+            ..0 => Err(io::Error::last_os_error()),
+            0 => Ok(false),
+            _ => {
+                let mut buf = vec![0u8; 32 * 1024];
+                // SAFETY: the buffer is valid for its length
+                let received = unsafe {
+                    libc::recv(self.fd.as_raw_fd(), buf.as_mut_ptr().cast(), buf.len(), 0)
+                };
+                match received {
+                    ..0 => Err(io::Error::last_os_error()),
+                    _ => Ok(true),
+                }
+            }
+        }
+    }
+
+    /// Sends `message` and waits for the kernel to acknowledge it.
+    ///
+    /// Returns the payloads of the answers received before the acknowledgement.
+    pub fn request(&mut self, message: Message) -> io::Result<Vec<Vec<u8>>> {
+        self.seq += 1;
+        let bytes = message.finish(self.seq)?;
+
+        // SAFETY: the buffer is valid for its length
+        let sent =
+            unsafe { libc::send(self.fd.as_raw_fd(), bytes.as_ptr().cast(), bytes.len(), 0) };
+        if sent < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let mut answers = Vec::new();
+        let mut buf = vec![0u8; 32 * 1024];
+        loop {
+            // SAFETY: the buffer is valid for its length
+            let received =
+                unsafe { libc::recv(self.fd.as_raw_fd(), buf.as_mut_ptr().cast(), buf.len(), 0) };
+            if received < 0 {
+                return Err(io::Error::last_os_error());
+            }
+
+            for (kind, seq, payload) in messages(&buf[..received as usize]) {
+                if seq != self.seq {
+                    continue;
+                }
+                match kind {
+                    NLMSG_ERROR => {
+                        let errno = payload
+                            .get(..4)
+                            .map(|b| i32::from_ne_bytes(b.try_into().expect("4 bytes")))
+                            .unwrap_or(-libc::EBADMSG);
+                        return match errno {
+                            0 => Ok(answers),
+                            _ => Err(io::Error::from_raw_os_error(-errno)),
+                        };
+                    }
+                    NLMSG_DONE => return Ok(answers),
+                    _ => answers.push(payload.to_vec()),
+                }
+            }
+        }
+    }
+}
+
+/// Splits a buffer received from the kernel into `(type, sequence number, payload)`.
+fn messages(mut buf: &[u8]) -> impl Iterator<Item = (u16, u32, &[u8])> {
+    std::iter::from_fn(move || {
+        if buf.len() < HEADER {
+            return None;
+        }
+        let len = u32::from_ne_bytes(buf[..4].try_into().expect("4 bytes")) as usize;
+        if len < HEADER || len > buf.len() {
+            return None;
+        }
+        let kind = u16::from_ne_bytes(buf[4..6].try_into().expect("2 bytes"));
+        let seq = u32::from_ne_bytes(buf[8..12].try_into().expect("4 bytes"));
+        let payload = &buf[HEADER..len];
+        buf = &buf[align(len).min(buf.len())..];
+        Some((kind, seq, payload))
+    })
+}
+
+/// A netlink message being built: a family specific header followed by attributes.
+pub struct Message {
+    kind: u16,
+    flags: u16,
+    buf: Vec<u8>,
+    /// Start of the nested attributes not ended yet.
+    nests: Vec<usize>,
+    /// Whether an attribute exceeded the 64 KiB of its length, making the message invalid.
+    oversized: bool,
+}
+
+impl Message {
+    /// A request of type `kind`, [NLM_F_REQUEST] and [NLM_F_ACK] are added to `flags`.
+    pub fn new(kind: u16, flags: u16) -> Self {
+        Self {
+            kind,
+            flags: flags | NLM_F_REQUEST | NLM_F_ACK,
+            buf: vec![0; HEADER],
+            nests: Vec::new(),
+            oversized: false,
+        }
+    }
+
+    /// Appends the family specific header, e.g. an `ifinfomsg`.
+    pub fn header(&mut self, bytes: &[u8]) -> &mut Self {
+        self.buf.extend_from_slice(bytes);
+        self.pad();
+        self
+    }
+
+    pub fn attr(&mut self, kind: u16, value: &[u8]) -> &mut Self {
+        let Ok(len) = u16::try_from(4 + value.len()) else {
+            self.oversized = true;
+            return self;
+        };
+        self.buf.extend_from_slice(&len.to_ne_bytes());
+        self.buf.extend_from_slice(&kind.to_ne_bytes());
+        self.buf.extend_from_slice(value);
+        self.pad();
+        self
+    }
+
+    pub fn attr_u8(&mut self, kind: u16, value: u8) -> &mut Self {
+        self.attr(kind, &[value])
+    }
+
+    pub fn attr_u16(&mut self, kind: u16, value: u16) -> &mut Self {
+        self.attr(kind, &value.to_ne_bytes())
+    }
+
+    pub fn attr_u32(&mut self, kind: u16, value: u32) -> &mut Self {
+        self.attr(kind, &value.to_ne_bytes())
+    }
+
+    /// A nul terminated string attribute.
+    pub fn attr_str(&mut self, kind: u16, value: &str) -> &mut Self {
+        let mut bytes = value.as_bytes().to_vec();
+        bytes.push(0);
+        self.attr(kind, &bytes)
+    }
+
+    /// Starts an attribute containing the next ones, until [Message::end].
+    pub fn nest(&mut self, kind: u16) -> &mut Self {
+        self.nests.push(self.buf.len());
+        self.attr(kind | NLA_F_NESTED, &[])
+    }
+
+    /// Ends the latest nested attribute.
+    pub fn end(&mut self) -> &mut Self {
+        let start = self.nests.pop().expect("end matches a nest");
+        match u16::try_from(self.buf.len() - start) {
+            Ok(len) => self.buf[start..start + 2].copy_from_slice(&len.to_ne_bytes()),
+            Err(_) => self.oversized = true,
+        }
+        self
+    }
+
+    /// The bytes to send, with the header filled.
+    ///
+    /// Fails if an attribute is too large, e.g. a peer with thousands of allowed IPs.
+    pub(super) fn finish(mut self, seq: u32) -> io::Result<Vec<u8>> {
+        debug_assert!(self.nests.is_empty(), "every nest is ended");
+        if self.oversized {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "netlink attribute larger than 64 KiB",
+            ));
+        }
+        let len = self.buf.len() as u32;
+        self.buf[..4].copy_from_slice(&len.to_ne_bytes());
+        self.buf[4..6].copy_from_slice(&self.kind.to_ne_bytes());
+        self.buf[6..8].copy_from_slice(&self.flags.to_ne_bytes());
+        self.buf[8..12].copy_from_slice(&seq.to_ne_bytes());
+        // port id 0, set by the kernel
+        Ok(self.buf)
+    }
+
+    fn pad(&mut self) {
+        self.buf.resize(align(self.buf.len()), 0);
+    }
+}
+
+/// Splits attributes into `(type, value)`, the type without its flags.
+pub fn attributes(mut bytes: &[u8]) -> impl Iterator<Item = (u16, &[u8])> {
+    std::iter::from_fn(move || {
+        if bytes.len() < 4 {
+            return None;
+        }
+        let len = u16::from_ne_bytes([bytes[0], bytes[1]]) as usize;
+        let kind = u16::from_ne_bytes([bytes[2], bytes[3]]) & !(NLA_F_NESTED | NLA_F_NET_BYTEORDER);
+        if len < 4 || len > bytes.len() {
+            return None;
+        }
+        let value = &bytes[4..len];
+        bytes = &bytes[align(len).min(bytes.len())..];
+        Some((kind, value))
+    })
+}
+
+/// Netlink aligns everything on 4 bytes.
+fn align(len: usize) -> usize {
+    (len + 3) & !3
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn build() {
+        let mut message = Message::new(16, NLM_F_CREATE);
+        message
+            .header(&[1, 2, 3])
+            .attr_str(3, "wg0")
+            .nest(18)
+            .attr_str(1, "wireguard")
+            .end();
+        let bytes = message.finish(7).unwrap();
+
+        assert_eq!(bytes.len() % 4, 0);
+        assert_eq!(
+            u32::from_ne_bytes(bytes[..4].try_into().unwrap()) as usize,
+            bytes.len()
+        );
+        assert_eq!(
+            u16::from_ne_bytes([bytes[6], bytes[7]]),
+            NLM_F_CREATE | NLM_F_REQUEST | NLM_F_ACK
+        );
+
+        // header padded to 4 bytes
+        let attrs: Vec<_> = attributes(&bytes[HEADER + 4..]).collect();
+        assert_eq!(attrs.len(), 2);
+        assert_eq!(attrs[0], (3, &b"wg0\0"[..]));
+        assert_eq!(attrs[1].0, 18);
+        let nested: Vec<_> = attributes(attrs[1].1).collect();
+        assert_eq!(nested, vec![(1, &b"wireguard\0"[..])]);
+    }
+
+    #[test]
+    fn oversized() {
+        let mut message = Message::new(16, 0);
+        message.attr(1, &[0; 70_000]);
+        assert!(message.finish(1).is_err());
+
+        // each attribute is small, the nest isn't
+        let mut message = Message::new(16, 0);
+        message.nest(1);
+        for _ in 0..20 {
+            message.attr(2, &[0; 4000]);
+        }
+        message.end();
+        assert!(message.finish(1).is_err());
+    }
+
+    #[test]
+    fn split() {
+        let mut message = Message::new(NLMSG_ERROR, 0);
+        message.header(&(-17i32).to_ne_bytes());
+        let mut bytes = message.finish(1).unwrap();
+        let mut second = Message::new(NLMSG_DONE, 0);
+        second.header(&0i32.to_ne_bytes());
+        bytes.extend(second.finish(2).unwrap());
+
+        let messages: Vec<_> = messages(&bytes).collect();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].0, NLMSG_ERROR);
+        assert_eq!(messages[0].1, 1);
+        assert_eq!(messages[0].2, &(-17i32).to_ne_bytes()[..]);
+        assert_eq!(messages[1].1, 2);
+    }
+}
@@ -0,0 +1,405 @@
+//! WireGuard connections of NetworkManager, read over its D-Bus API and activated through `nmcli`.
+use std::{
+    collections::HashMap,
+    io,
+    net::{Ipv4Addr, Ipv6Addr},
+    process::{Command, Stdio},
+};
+
+use zbus::{
+    blocking::{Connection, Proxy},
+    zvariant::{OwnedObjectPath, OwnedValue},
+};
+
+use super::Backend;
+
+const DESTINATION: &str = "org.freedesktop.NetworkManager";
+
+/// Settings of a connection by setting then key, e.g. `wireguard` then `mtu`.
+type Settings = HashMap<String, HashMap<String, OwnedValue>>;
+
+/// A `wireguard` connection of NetworkManager.
+pub struct NmConnection {
+    pub uuid: String,
+    /// Name of the interface, also the name of the connection in wtui.
+    pub interface: String,
+    /// Its settings written as a wireguard configuration, or why they can't be.
+    pub config: Result<String, String>,
+}
+
+/// Lists the WireGuard connections of NetworkManager.
+///
+/// Empty if NetworkManager isn't running.
+pub fn connections() -> Vec<NmConnection> {
+    let Ok(bus) = Connection::system() else {
+        return Vec::new();
+    };
+    let paths: Vec<OwnedObjectPath> = match Proxy::new(
+        &bus,
+        DESTINATION,
+        "/org/freedesktop/NetworkManager/Settings",
+        "org.freedesktop.NetworkManager.Settings",
+    )
+    .and_then(|settings| settings.call("ListConnections", &()))
+    {
+        Ok(paths) => paths,
+        Err(_) => return Vec::new(),
+    };
+
+    paths
+        .into_iter()
+        // e.g. removed meanwhile
+        .filter_map(|path| connection(&bus, path).ok().flatten())
+        .collect()
+}
+
+/// The connection at `path`, [None] if it isn't a WireGuard one or has no interface name.
+fn connection(bus: &Connection, path: OwnedObjectPath) -> zbus::Result<Option<NmConnection>> {
+    let proxy = Proxy::new(
+        bus,
+        DESTINATION,
+        path,
+        "org.freedesktop.NetworkManager.Settings.Connection",
+    )?;
+    let settings: Settings = proxy.call("GetSettings", &())?;
+    if value::<String>(&settings, "connection", "type").as_deref() != Some("wireguard") {
+        return Ok(None);
+    }
+    let (Some(uuid), Some(interface)) = (
+        value::<String>(&settings, "connection", "uuid"),
+        value::<String>(&settings, "connection", "interface-name").filter(|i| !i.is_empty()),
+    ) else {
+        return Ok(None);
+    };
+    // the keys, refused when not allowed or kept by an agent
+    let secrets: Settings = proxy
+        .call("GetSecrets", &("wireguard",))
+        .unwrap_or_default();
+
+    Ok(Some(NmConnection {
+        uuid,
+        interface,
+        config: Wireguard::read(&settings, &secrets).to_config(),
+    }))
+}
+
+/// The value of `key` in `dict`, [None] if missing or of another type.
+fn field<T: TryFrom<OwnedValue>>(dict: &HashMap<String, OwnedValue>, key: &str) -> Option<T> {
+    T::try_from(dict.get(key)?.try_clone().ok()?).ok()
+}
+
+/// The value of `key` of the `setting`.
+fn value<T: TryFrom<OwnedValue>>(settings: &Settings, setting: &str, key: &str) -> Option<T> {
+    field(settings.get(setting)?, key)
+}
+
+/// The settings of a WireGuard connection used by wtui, zero meaning unset.
+#[derive(Debug, Default)]
+struct Wireguard {
+    private_key: Option<String>,
+    addresses: Vec<String>,
+    listen_port: u32,
+    mtu: u32,
+    fwmark: u32,
+    dns: Vec<String>,
+    peers: Vec<Peer>,
+}
+
+#[derive(Debug, Default)]
+struct Peer {
+    public_key: String,
+    allowed_ips: Vec<String>,
+    endpoint: Option<String>,
+    persistent_keepalive: u32,
+    preshared_key: Option<String>,
+}
+
+impl Wireguard {
+    /// Reads the `settings` of a connection, with the keys of its `secrets`.
+    fn read(settings: &Settings, secrets: &Settings) -> Self {
+        let secret_peers: Vec<HashMap<String, OwnedValue>> =
+            value(secrets, "wireguard", "peers").unwrap_or_default();
+        let preshared_key = |public_key: &str| {
+            secret_peers
+                .iter()
+                .find(|peer| field::<String>(peer, "public-key").as_deref() == Some(public_key))
+                .and_then(|peer| field::<String>(peer, "preshared-key"))
+                .filter(|key| !key.is_empty())
+        };
+
+        let mut addresses = Vec::new();
+        for family in ["ipv4", "ipv6"] {
+            let data: Vec<HashMap<String, OwnedValue>> =
+                value(settings, family, "address-data").unwrap_or_default();
+            addresses.extend(data.iter().filter_map(|address| {
+                Some(format!(
+                    "{}/{}",
+                    field::<String>(address, "address")?,
+                    field::<u32>(address, "prefix")?
+                ))
+            }));
+        }
+
+        // in network byte order
+        let dns4: Vec<u32> = value(settings, "ipv4", "dns").unwrap_or_default();
+        let dns6: Vec<Vec<u8>> = value(settings, "ipv6", "dns").unwrap_or_default();
+        let dns =
+            dns4.into_iter()
+                .map(|ip| Ipv4Addr::from(ip.to_ne_bytes()).to_string())
+                .chain(dns6.into_iter().filter_map(|ip| {
+                    Some(Ipv6Addr::from(<[u8; 16]>::try_from(ip).ok()?).to_string())
+                }))
+                .collect();
+
+        let peers: Vec<HashMap<String, OwnedValue>> =
+            value(settings, "wireguard", "peers").unwrap_or_default();
+
+        Self {
+            private_key: value(secrets, "wireguard", "private-key")
+                .or_else(|| value(settings, "wireguard", "private-key"))
+                .filter(|key: &String| !key.is_empty()),
+            addresses,
+            listen_port: value(settings, "wireguard", "listen-port").unwrap_or_default(),
+            mtu: value(settings, "wireguard", "mtu").unwrap_or_default(),
+            fwmark: value(settings, "wireguard", "fwmark").unwrap_or_default(),
+            dns,
+            peers: peers
+                .iter()
+                .filter_map(|peer| {
+                    let public_key = field::<String>(peer, "public-key")?;
+                    Some(Peer {
+                        allowed_ips: field(peer, "allowed-ips").unwrap_or_default(),
+                        endpoint: field(peer, "endpoint").filter(|e: &String| !e.is_empty()),
+                        persistent_keepalive: field(peer, "persistent-keepalive")
+                            .unwrap_or_default(),
+                        preshared_key: field(peer, "preshared-key")
+                            .filter(|key: &String| !key.is_empty())
+                            .or_else(|| preshared_key(&public_key)),
+                        public_key,
+                    })
+                })
+                .collect(),
+        }
+    }
+
+    /// Writes the settings as a wireguard configuration, failing without peers.
+    fn to_config(&self) -> Result<String, String> {
+        if self.peers.is_empty() {
+            return Err("No peer in the NetworkManager connection".to_string());
+        }
+
+        let mut config = String::from("[Interface]\n");
+        if let Some(key) = &self.private_key {
+            config.push_str(&format!("PrivateKey = {key}\n"));
+        }
+        config.push_str(&format!("Address = {}\n", self.addresses.join(", ")));
+        if self.listen_port != 0 {
+            config.push_str(&format!("ListenPort = {}\n", self.listen_port));
+        }
+        if self.mtu != 0 {
+            config.push_str(&format!("MTU = {}\n", self.mtu));
+        }
+        if self.fwmark != 0 {
+            config.push_str(&format!("FwMark = {:#x}\n", self.fwmark));
+        }
+        if !self.dns.is_empty() {
+            config.push_str(&format!("DNS = {}\n", self.dns.join(", ")));
+        }
+
+        for peer in &self.peers {
+            config.push_str(&format!("\n[Peer]\nPublicKey = {}\n", peer.public_key));
+            if let Some(key) = &peer.preshared_key {
+                config.push_str(&format!("PresharedKey = {key}\n"));
+            }
+            config.push_str(&format!("AllowedIPs = {}\n", peer.allowed_ips.join(", ")));
+            if let Some(endpoint) = &peer.endpoint {
+                config.push_str(&format!("Endpoint = {endpoint}\n"));
+            }
+            if peer.persistent_keepalive != 0 {
+                config.push_str(&format!(
+                    "PersistentKeepalive = {}\n",
+                    peer.persistent_keepalive
+                ));
+            }
+        }
+
+        Ok(config)
+    }
+}
+
+/// Activates and deactivates a NetworkManager connection.
+pub struct NetworkManager {
+    uuid: String,
+}
+
+impl NetworkManager {
+    pub fn new(uuid: &str) -> Self {
+        Self {
+            uuid: uuid.to_string(),
+        }
+    }
+
+    fn run(&self, action: &str) -> io::Result<()> {
+        let status = Command::new("nmcli")
+            .args(["connection", action, "uuid", &self.uuid])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()?;
+
+        match status.success() {
+            true => Ok(()),
+            false => Err(io::Error::other(format!(
+                "nmcli connection {action} {} failed",
+                self.uuid
+            ))),
+        }
+    }
+}
+
+impl Backend for NetworkManager {
+    fn up(&self, _name: &str) -> io::Result<()> {
+        self.run("up")
+    }
+
+    fn down(&self, _name: &str) -> io::Result<()> {
+        self.run("down")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use zbus::zvariant::Value;
+
+    use super::*;
+
+    fn owned<'a>(value: impl Into<Value<'a>>) -> OwnedValue {
+        value.into().try_into().unwrap()
+    }
+
+    fn dict<'a>(entries: Vec<(&str, Value<'a>)>) -> HashMap<String, Value<'a>> {
+        entries
+            .into_iter()
+            .map(|(key, value)| (key.to_string(), value))
+            .collect()
+    }
+
+    fn setting_map(entries: Vec<(&str, Vec<(&str, OwnedValue)>)>) -> Settings {
+        entries
+            .into_iter()
+            .map(|(setting, values)| {
+                let values = values
+                    .into_iter()
+                    .map(|(key, value)| (key.to_string(), value))
+                    .collect();
+                (setting.to_string(), values)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn config() {
+        let peer = "60TUAvOo+Wi4SCyir581cCyBx4wIcHtrIrUgBv/iqRM=";
+        let settings = setting_map(vec![
+            (
+                "wireguard",
+                vec![
+                    ("listen-port", owned(0u32)),
+                    ("fwmark", owned(0u32)),
+                    ("mtu", owned(1380u32)),
+                    (
+                        "peers",
+                        owned(vec![
+                            dict(vec![
+                                ("public-key", Value::from(peer)),
+                                (
+                                    "allowed-ips",
+                                    Value::from(vec!["10.1.0.0/16", "10.2.0.0/16"]),
+                                ),
+                                ("endpoint", Value::from("[2001:db8::1]:51820")),
+                                ("persistent-keepalive", Value::from(25u32)),
+                            ]),
+                            dict(vec![
+                                (
+                                    "public-key",
+                                    Value::from("ZGO4p6PvGDBT6Mr1rAuFrPrFsGoFMKg5ILKLnLDz2Q4="),
+                                ),
+                                ("allowed-ips", Value::from(vec!["10.3.0.0/16"])),
+                            ]),
+                        ]),
+                    ),
+                ],
+            ),
+            (
+                "ipv4",
+                vec![
+                    (
+                        "address-data",
+                        owned(vec![dict(vec![
+                            ("address", Value::from("10.0.0.2")),
+                            ("prefix", Value::from(24u32)),
+                        ])]),
+                    ),
+                    ("dns", owned(vec![u32::from_ne_bytes([10, 1, 0, 1])])),
+                ],
+            ),
+            (
+                "ipv6",
+                vec![(
+                    "dns",
+                    owned(vec![
+                        "2001:db8::53"
+                            .parse::<Ipv6Addr>()
+                            .unwrap()
+                            .octets()
+                            .to_vec(),
+                    ]),
+                )],
+            ),
+        ]);
+        let secrets = setting_map(vec![(
+            "wireguard",
+            vec![
+                (
+                    "private-key",
+                    owned("oMVUWFwDf+20fIfeRUe7c0rlUKSYnHk2K0y2920SX1c="),
+                ),
+                (
+                    "peers",
+                    owned(vec![dict(vec![
+                        ("public-key", Value::from(peer)),
+                        (
+                            "preshared-key",
+                            Value::from("FpCyhws9cxwWoV4xELtfJvjJN+zQVRPISllRWgeopVE="),
+                        ),
+                    ])]),
+                ),
+            ],
+        )]);
+
+        assert_eq!(
+            Wireguard::read(&settings, &secrets).to_config().unwrap(),
+            "[Interface]
+PrivateKey = oMVUWFwDf+20fIfeRUe7c0rlUKSYnHk2K0y2920SX1c=
+Address = 10.0.0.2/24
+MTU = 1380
+DNS = 10.1.0.1, 2001:db8::53
+
+[Peer]
+PublicKey = 60TUAvOo+Wi4SCyir581cCyBx4wIcHtrIrUgBv/iqRM=
+PresharedKey = FpCyhws9cxwWoV4xELtfJvjJN+zQVRPISllRWgeopVE=
+AllowedIPs = 10.1.0.0/16, 10.2.0.0/16
+Endpoint = [2001:db8::1]:51820
+PersistentKeepalive = 25
+
+[Peer]
+PublicKey = ZGO4p6PvGDBT6Mr1rAuFrPrFsGoFMKg5ILKLnLDz2Q4=
+AllowedIPs = 10.3.0.0/16
+"
+        );
+    }
+
+    #[test]
+    fn no_peer() {
+        assert!(Wireguard::default().to_config().is_err());
+    }
+}
@@ -0,0 +1,211 @@
+//! `wg-quick@<name>.service` units, over the systemd D-Bus API and through `journalctl`.
+use std::{
+    io,
+    path::Path,
+    process::{Command, Stdio},
+    sync::Arc,
+};
+
+use zbus::{
+    blocking::{Connection, Proxy},
+    zvariant::OwnedObjectPath,
+};
+
+use super::Backend;
+
+/// Tells whether systemd is the service manager of this machine.
+pub fn is_running() -> bool {
+    Path::new("/run/systemd/system").exists()
+}
+
+/// Name of the unit of the `name` connection.
+pub fn unit(name: &str) -> String {
+    format!("wg-quick@{name}.service")
+}
+
+/// State of a unit, from its systemd properties.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct UnitState {
+    /// `loaded` if the unit exists, e.g. `not-found`.
+    pub load: String,
+    /// e.g. `active`, `inactive` or `failed`.
+    pub active: String,
+    /// Whether it starts at boot, e.g. `enabled` or `disabled`.
+    pub file: String,
+}
+
+impl UnitState {
+    pub fn exists(&self) -> bool {
+        self.load == "loaded"
+    }
+
+    pub fn is_active(&self) -> bool {
+        matches!(self.active.as_str(), "active" | "activating" | "reloading")
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.file.starts_with("enabled")
+    }
+}
+
+const DESTINATION: &str = "org.freedesktop.systemd1";
+const PATH: &str = "/org/freedesktop/systemd1";
+const MANAGER: &str = "org.freedesktop.systemd1.Manager";
+const UNIT: &str = "org.freedesktop.systemd1.Unit";
+
+fn manager(bus: &Connection) -> zbus::Result<Proxy<'static>> {
+    Proxy::new(bus, DESTINATION, PATH, MANAGER)
+}
+
+fn bus_error(error: zbus::Error) -> io::Error {
+    match error {
+        zbus::Error::MethodError(_, Some(description), _) => io::Error::other(description),
+        zbus::Error::InputOutput(error) => io::Error::new(error.kind(), error.to_string()),
+        error => io::Error::other(error),
+    }
+}
+
+/// State of the unit of the `name` connection, [None] if systemd can't be reached.
+pub fn state(name: &str) -> Option<UnitState> {
+    unit_state(name).ok()
+}
+
+fn unit_state(name: &str) -> zbus::Result<UnitState> {
+    let bus = Connection::system()?;
+    let path: OwnedObjectPath = manager(&bus)?.call("LoadUnit", &(unit(name),))?;
+    let unit = Proxy::new(&bus, DESTINATION, path, UNIT)?;
+
+    Ok(UnitState {
+        load: unit.get_property("LoadState")?,
+        active: unit.get_property("ActiveState")?,
+        file: unit.get_property("UnitFileState")?,
+    })
+}
+
+/// The latest `lines` of the journal of the unit of the `name` connection.
+pub fn journal(name: &str, lines: usize) -> io::Result<Vec<String>> {
+    let output = Command::new("journalctl")
+        .args(["--unit", &unit(name), "--lines", &lines.to_string()])
+        .args(["--no-pager", "--output", "short", "--quiet"])
+        .stderr(Stdio::null())
+        .output()?;
+
+    match output.status.success() {
+        true => Ok(String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .map(str::to_string)
+            .collect()),
+        false => Err(io::Error::other("journalctl failed")),
+    }
+}
+
+/// Enables or disables the unit of the `name` connection at boot.
+pub fn set_enabled(name: &str, enabled: bool) -> io::Result<()> {
+    let bus = Connection::system().map_err(bus_error)?;
+    let manager = manager(&bus).map_err(bus_error)?;
+    let units = [unit(name)];
+
+    let _: (bool, Vec<(String, String, String)>) = match enabled {
+        // not only for this boot, don't replace symlinks
+        true => manager.call("EnableUnitFiles", &(&units, false, false)),
+        false => manager
+            .call::<_, _, Vec<(String, String, String)>>("DisableUnitFiles", &(&units, false))
+            .map(|changes| (false, changes)),
+    }
+    .map_err(bus_error)?;
+
+    // like systemctl, so that the change is seen right away
+    manager.call::<_, _, ()>("Reload", &()).map_err(bus_error)
+}
+
+/// Starts or stops the unit of the `name` connection, waiting for the job to finish.
+fn run_job(method: &str, name: &str) -> io::Result<()> {
+    let bus = Connection::system().map_err(bus_error)?;
+    let manager = manager(&bus).map_err(bus_error)?;
+
+    // subscribe before starting the job so its end can't be missed
+    manager
+        .call::<_, _, ()>("Subscribe", &())
+        .map_err(bus_error)?;
+    let removed = manager.receive_signal("JobRemoved").map_err(bus_error)?;
+    let job: OwnedObjectPath = manager
+        .call(method, &(unit(name), "replace"))
+        .map_err(bus_error)?;
+
+    for message in removed {
+        let (_, path, _, result): (u32, OwnedObjectPath, String, String) =
+            message.body().deserialize().map_err(bus_error)?;
+        if path != job {
+            continue;
+        }
+        return match result.as_str() {
+            "done" => Ok(()),
+            result => Err(io::Error::other(format!(
+                "{method} {} failed: {result}, see the journal",
+                unit(name)
+            ))),
+        };
+    }
+
+    Err(io::Error::other("systemd went away"))
+}
+
+/// Starts and stops connections having a unit through it, keeping systemd in sync.
+///
+/// Other connections go through the `fallback` backend.
+pub struct Systemd {
+    fallback: Arc<dyn Backend>,
+}
+
+impl Systemd {
+    pub fn new(fallback: Arc<dyn Backend>) -> Self {
+        Self { fallback }
+    }
+}
+
+impl Backend for Systemd {
+    fn up(&self, name: &str) -> io::Result<()> {
+        match state(name) {
+            Some(state) if state.exists() => run_job("StartUnit", name),
+            _ => self.fallback.up(name),
+        }
+    }
+
+    fn down(&self, name: &str) -> io::Result<()> {
+        match state(name) {
+            // brought up by systemd, or systemd would think it's still running
+            Some(state) if state.exists() && state.is_active() => run_job("StopUnit", name),
+            _ => self.fallback.down(name),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(load: &str, active: &str, file: &str) -> UnitState {
+        UnitState {
+            load: load.to_string(),
+            active: active.to_string(),
+            file: file.to_string(),
+        }
+    }
+
+    #[test]
+    fn unit_state() {
+        let unit = state("loaded", "active", "enabled");
+        assert!(unit.exists());
+        assert!(unit.is_active());
+        assert!(unit.is_enabled());
+
+        let unit = state("loaded", "inactive", "disabled");
+        assert!(unit.exists());
+        assert!(!unit.is_active());
+        assert!(!unit.is_enabled());
+
+        let unit = state("not-found", "inactive", "");
+        assert!(!unit.exists());
+        assert!(!unit.is_enabled());
+    }
+}
@@ -0,0 +1,185 @@
+//! Clipboard access, working locally as well as over SSH.
+use std::{
+    env,
+    fmt::{self, Display},
+    io::{self, Write},
+    process::{Command, Stdio},
+    str::FromStr,
+};
+
+use ::clipboard::{ClipboardContext, ClipboardProvider};
+use base64::{Engine, prelude::BASE64_STANDARD};
+
+use super::AppResult;
+
+/// How to access the clipboard.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Clipboard {
+    /// Picks one of the other variants depending on the environment.
+    #[default]
+    Auto,
+    /// The clipboard of the local X11 or Wayland display.
+    Native,
+    /// OSC 52 escape sequence, handled by the terminal emulator even over SSH.
+    Osc52,
+    /// OSC 52 wrapped in a tmux passthrough sequence.
+    Tmux,
+}
+
+impl Clipboard {
+    /// Resolves [`Clipboard::Auto`] from the environment variables.
+    pub fn resolve(self) -> Self {
+        match self {
+            Clipboard::Auto => Self::detect(|var| env::var_os(var).is_some_and(|v| !v.is_empty())),
+            clipboard => clipboard,
+        }
+    }
+
+    /// Picks a clipboard depending on which environment variables are set.
+    ///
+    /// Over SSH, the display (if any) is the one of the server, so the terminal emulator is used.
+    fn detect(is_set: impl Fn(&str) -> bool) -> Self {
+        let ssh = is_set("SSH_CONNECTION") || is_set("SSH_TTY");
+        let display = is_set("WAYLAND_DISPLAY") || is_set("DISPLAY");
+
+        match (ssh, display, is_set("TMUX")) {
+            (false, true, _) => Clipboard::Native,
+            (_, _, true) => Clipboard::Tmux,
+            _ => Clipboard::Osc52,
+        }
+    }
+
+    /// Copies `text` to the clipboard.
+    ///
+    /// Escape sequences are written to `terminal` instead of stdout/stderr so they don't get mixed
+    /// with the drawing of the interface.
+    pub fn copy(self, text: &str, terminal: &mut impl Write) -> AppResult<()> {
+        match self.resolve() {
+            Clipboard::Native if is_wayland() => {
+                let mut child = Command::new("wl-copy")
+                    .stdin(Stdio::piped())
+                    .stdout(Stdio::null())
+                    .stderr(Stdio::null())
+                    .spawn()?;
+                child
+                    .stdin
+                    .take()
+                    .ok_or_else(|| io::Error::other("no stdin for wl-copy"))?
+                    .write_all(text.as_bytes())?;
+                if !child.wait()?.success() {
+                    return Err(io::Error::other("wl-copy failed").into());
+                }
+            }
+            Clipboard::Native => {
+                let mut ctx: ClipboardContext = ClipboardProvider::new()?;
+                ctx.set_contents(text.to_string())?;
+            }
+            Clipboard::Osc52 => terminal.write_all(osc52(text).as_bytes())?,
+            Clipboard::Tmux => terminal.write_all(tmux_passthrough(&osc52(text)).as_bytes())?,
+            Clipboard::Auto => unreachable!("Auto is always resolved"),
+        }
+        Ok(())
+    }
+
+    /// Gets the content of the clipboard.
+    ///
+    /// Only supported by the native clipboard, terminals seldom allow reading it.
+    pub fn paste(self) -> AppResult<String> {
+        match self.resolve() {
+            Clipboard::Native if is_wayland() => {
+                let output = Command::new("wl-paste")
+                    .arg("--no-newline")
+                    .stderr(Stdio::null())
+                    .output()?;
+                Ok(String::from_utf8(output.stdout)?)
+            }
+            Clipboard::Native => {
+                let mut ctx: ClipboardContext = ClipboardProvider::new()?;
+                ctx.get_contents()
+            }
+            _ => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "reading is only supported by the native clipboard, paste in the terminal instead",
+            )
+            .into()),
+        }
+    }
+}
+
+fn is_wayland() -> bool {
+    env::var_os("WAYLAND_DISPLAY").is_some_and(|v| !v.is_empty())
+}
+
+/// The OSC 52 sequence setting the clipboard content to `text`.
+fn osc52(text: &str) -> String {
+    format!("\x1b]52;c;{}\x07", BASE64_STANDARD.encode(text))
+}
+
+/// Wraps `sequence` so tmux forwards it to the outer terminal.
+///
+/// Requires `allow-passthrough` to be enabled in tmux.
+fn tmux_passthrough(sequence: &str) -> String {
+    format!("\x1bPtmux;{}\x1b\\", sequence.replace('\x1b', "\x1b\x1b"))
+}
+
+impl FromStr for Clipboard {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "auto" => Ok(Clipboard::Auto),
+            "native" => Ok(Clipboard::Native),
+            "osc52" => Ok(Clipboard::Osc52),
+            "tmux" => Ok(Clipboard::Tmux),
+            _ => Err(format!("unknown clipboard {s}")),
+        }
+    }
+}
+
+impl Display for Clipboard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Clipboard::Auto => "auto",
+            Clipboard::Native => "native",
+            Clipboard::Osc52 => "osc52",
+            Clipboard::Tmux => "tmux",
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detect() {
+        let detect = |vars: &[&str]| Clipboard::detect(|var| vars.contains(&var));
+
+        assert_eq!(detect(&["DISPLAY"]), Clipboard::Native);
+        assert_eq!(detect(&["WAYLAND_DISPLAY", "TMUX"]), Clipboard::Native);
+        assert_eq!(detect(&["SSH_CONNECTION", "DISPLAY"]), Clipboard::Osc52);
+        assert_eq!(detect(&["SSH_TTY", "TMUX"]), Clipboard::Tmux);
+        assert_eq!(detect(&["TMUX"]), Clipboard::Tmux);
+        assert_eq!(detect(&[]), Clipboard::Osc52);
+    }
+
+    #[test]
+    fn sequences() {
+        assert_eq!(osc52("wtui"), "\x1b]52;c;d3R1aQ==\x07");
+        assert_eq!(
+            tmux_passthrough(&osc52("wtui")),
+            "\x1bPtmux;\x1b\x1b]52;c;d3R1aQ==\x07\x1b\\"
+        );
+    }
+
+    #[test]
+    fn osc52_writes_to_terminal() {
+        let mut terminal = Vec::new();
+
+        Clipboard::Osc52
+            .copy("wtui", &mut terminal)
+            .expect("writing to a Vec never fails");
+
+        assert_eq!(terminal, b"\x1b]52;c;d3R1aQ==\x07");
+    }
+}
@@ -0,0 +1,308 @@
+//! Columns of the connections table.
+use std::{
+    fmt::{self, Display},
+    str::FromStr,
+};
+
+use ratatui::{prelude::*, widgets::*};
+
+use super::{
+    connection::{Connection, Origin},
+    popup,
+    theme::Theme,
+};
+use crate::wg::types::{Cidr, Endpoint};
+
+/// Space between two columns.
+pub const SPACING: u16 = 1;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Column {
+    Name,
+    Status,
+    Address,
+    Mtu,
+    Received,
+    Sent,
+    Rate,
+    Endpoint,
+    AllowedIps,
+    Handshake,
+    PublicKey,
+    Dns,
+    Source,
+}
+
+impl Column {
+    pub const ALL: [Column; 13] = [
+        Column::Name,
+        Column::Status,
+        Column::Address,
+        Column::Mtu,
+        Column::Received,
+        Column::Sent,
+        Column::Rate,
+        Column::Endpoint,
+        Column::AllowedIps,
+        Column::Handshake,
+        Column::PublicKey,
+        Column::Dns,
+        Column::Source,
+    ];
+
+    /// Identifier used in the settings file.
+    pub fn id(self) -> &'static str {
+        match self {
+            Column::Name => "name",
+            Column::Status => "status",
+            Column::Address => "address",
+            Column::Mtu => "mtu",
+            Column::Received => "received",
+            Column::Sent => "sent",
+            Column::Rate => "rate",
+            Column::Endpoint => "endpoint",
+            Column::AllowedIps => "allowed-ips",
+            Column::Handshake => "handshake",
+            Column::PublicKey => "public-key",
+            Column::Dns => "dns",
+            Column::Source => "source",
+        }
+    }
+
+    /// Text of the header.
+    pub fn title(self) -> &'static str {
+        match self {
+            Column::Name => "Name",
+            Column::Status => "Status",
+            Column::Address => "Address",
+            Column::Mtu => "MTU",
+            Column::Received => "Received",
+            Column::Sent => "Sent",
+            Column::Rate => "Rate",
+            Column::Endpoint => "Endpoint",
+            Column::AllowedIps => "Allowed IPs",
+            Column::Handshake => "Latest ",
+            Column::PublicKey => "Public Key",
+            Column::Dns => "DNS",
+            Column::Source => "Source",
+        }
+    }
+
+    pub fn constraint(self) -> Constraint {
+        match self {
+            Column::Name => Constraint::Max(12),
+            Column::Status => Constraint::Max(12),
+            Column::Address => Constraint::Fill(1),
+            Column::Mtu => Constraint::Length(4),
+            Column::Received => Constraint::Max(8),
+            Column::Sent => Constraint::Max(8),
+            Column::Rate => Constraint::Max(10),
+            Column::Endpoint => Constraint::Fill(1),
+            Column::AllowedIps => Constraint::Fill(1),
+            Column::Handshake => Constraint::Max(12),
+            Column::PublicKey => Constraint::Max(10),
+            Column::Dns => Constraint::Fill(1),
+            Column::Source => Constraint::Max(14),
+        }
+    }
+
+    /// Width under which the column is unreadable.
+    fn min_width(self) -> u16 {
+        match self {
+            Column::Name => 6,
+            Column::Status => 6,
+            Column::Address => 15,
+            Column::Mtu => 4,
+            Column::Received => 8,
+            Column::Sent => 8,
+            Column::Rate => 10,
+            Column::Endpoint => 15,
+            Column::AllowedIps => 15,
+            Column::Handshake => 12,
+            Column::PublicKey => 10,
+            Column::Dns => 9,
+            Column::Source => 6,
+        }
+    }
+
+    /// Columns with the lowest priority are the first hidden when the terminal is too narrow.
+    fn priority(self) -> u8 {
+        match self {
+            Column::Source => 0,
+            Column::PublicKey => 1,
+            Column::Dns => 2,
+            Column::Mtu => 3,
+            Column::Rate => 4,
+            Column::Sent => 5,
+            Column::AllowedIps => 6,
+            Column::Handshake => 7,
+            Column::Address => 8,
+            Column::Received => 9,
+            Column::Endpoint => 10,
+            Column::Status => 11,
+            Column::Name => 12,
+        }
+    }
+
+    /// Content of this column for `con`.
+    pub fn cell(self, con: &Connection, theme: &Theme) -> Text<'static> {
+        let config = con.config();
+
+        // the configuration is empty, only say what's wrong
+        if con.error().is_some() {
+            return match self {
+                Column::Name => con.name().to_string().bold().into(),
+                Column::Status => "unreadable".into(),
+                Column::Source => con.origin().label().into(),
+                _ => Text::default(),
+            };
+        }
+
+        match self {
+            Column::Name => con.name().to_string().bold().into(),
+            // the row color still tells the health
+            Column::Status if *con.origin() == Origin::Unmanaged => "unmanaged".into(),
+            Column::Status if con.is_changed() => "changed".into(),
+            Column::Status => con.status().health().label().into(),
+            Column::Address => Cidr::join(config.interface.address(), ", ").into(),
+            Column::Mtu => config.interface.mtu().to_string().into(),
+            Column::Received => con
+                .status()
+                .bytes_received()
+                .map(|b| b.text(theme.unit))
+                .unwrap_or_default(),
+            Column::Sent => con
+                .status()
+                .bytes_sent()
+                .map(|b| b.text(theme.unit))
+                .unwrap_or_default(),
+            Column::Rate => con
+                .rate()
+                .map(|r| r.rate_text(theme.unit))
+                .unwrap_or_default(),
+            Column::Endpoint => config
+                .peer
+                .endpoint()
+                .map(Endpoint::to_string)
+                .unwrap_or_default()
+                .into(),
+            Column::AllowedIps => Cidr::join(config.peer.allowed_ips(), "\n").into(),
+            Column::Handshake => con.status().handshake_since().into(),
+            Column::PublicKey => config.interface.pubkey().truncated().into(),
+            Column::Dns => Text::from(
+                config
+                    .interface
+                    .dns()
+                    .iter()
+                    .map(|ip| ip.to_string())
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            ),
+            Column::Source => con.origin().label().into(),
+        }
+    }
+}
+
+impl FromStr for Column {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|c| c.id() == s.trim().to_lowercase())
+            .ok_or_else(|| format!("unknown column {s}"))
+    }
+}
+
+impl Display for Column {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.id())
+    }
+}
+
+/// Keeps the columns with the highest priority fitting in `width`.
+pub fn fitting(columns: &[Column], width: u16) -> Vec<Column> {
+    let mut columns = columns.to_vec();
+
+    while columns.len() > 1 && min_width(&columns) > width {
+        let lowest = columns
+            .iter()
+            .copied()
+            .min_by_key(|c| c.priority())
+            .expect("there is more than one column");
+        columns.retain(|&c| c != lowest);
+    }
+
+    columns
+}
+
+/// Width needed to show `columns`.
+fn min_width(columns: &[Column]) -> u16 {
+    columns.iter().map(|c| c.min_width()).sum::<u16>()
+        + SPACING * (columns.len() as u16).saturating_sub(1)
+}
+
+/// Popup to choose the shown columns.
+pub struct ColumnChooser<'a> {
+    /// Position of the hovered column in [`Column::ALL`].
+    pub cursor: usize,
+    pub hidden: &'a [Column],
+}
+
+impl Widget for ColumnChooser<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer)
+    where
+        Self: Sized,
+    {
+        let mut lines: Vec<Line> = Column::ALL
+            .into_iter()
+            .enumerate()
+            .map(|(i, column)| {
+                let checkbox = match self.hidden.contains(&column) {
+                    true => "[ ] ",
+                    false => "[x] ",
+                };
+                let line = Line::from(vec![checkbox.into(), column.title().trim().into()]);
+                match i == self.cursor {
+                    true => line.reversed(),
+                    false => line,
+                }
+            })
+            .collect();
+        lines.push(Line::default());
+        lines.push(Line::from("Space: toggle, Esc: save & close").italic());
+
+        let area = popup::centered(area, 36, lines.len() as u16 + 2);
+        Clear.render(area, buf);
+        Paragraph::new(lines)
+            .block(popup::block("Columns"))
+            .render(area, buf);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_id() {
+        for column in Column::ALL {
+            assert_eq!(column.id().parse(), Ok(column));
+        }
+        assert_eq!(" Allowed-IPs".parse(), Ok(Column::AllowedIps));
+        assert!("latest".parse::<Column>().is_err());
+    }
+
+    #[test]
+    fn fitting_columns() {
+        assert_eq!(fitting(&Column::ALL, 200), Column::ALL);
+
+        let columns = fitting(&Column::ALL, 100);
+        assert!(!columns.contains(&Column::PublicKey));
+        assert!(!columns.contains(&Column::Dns));
+        assert!(columns.contains(&Column::Endpoint));
+        assert!(min_width(&columns) <= 100);
+
+        assert_eq!(fitting(&Column::ALL, 0), vec![Column::Name]);
+    }
+}
@@ -0,0 +1,161 @@
+//! Confirmation of destructive actions.
+use std::{
+    env,
+    fmt::{self, Display},
+    net::IpAddr,
+    process::{Command, Stdio},
+    str::FromStr,
+};
+
+use ratatui::{prelude::*, widgets::*};
+
+use super::{popup, theme::Theme};
+
+/// When destructive actions must be confirmed.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ConfirmPolicy {
+    #[default]
+    Always,
+    /// Only when the action drops the interface carrying the current SSH session.
+    Ssh,
+    Never,
+}
+
+impl FromStr for ConfirmPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "always" => Ok(Self::Always),
+            "ssh" => Ok(Self::Ssh),
+            "never" => Ok(Self::Never),
+            _ => Err(format!("unknown confirmation policy: {s}")),
+        }
+    }
+}
+
+impl Display for ConfirmPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Always => "always",
+            Self::Ssh => "ssh",
+            Self::Never => "never",
+        })
+    }
+}
+
+/// An action that can't be undone.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Destructive {
+    DisconnectAll,
+    /// Deletes the configuration file of the named connection.
+    Delete(String),
+    /// Replaces the private key of the named connection.
+    RotateKey(String),
+}
+
+impl Destructive {
+    pub fn description(&self) -> String {
+        match self {
+            Self::DisconnectAll => "Disconnect all connections?".to_string(),
+            Self::Delete(name) => format!("Delete the configuration of {name}?"),
+            Self::RotateKey(name) => format!("Replace the private key of {name}?"),
+        }
+    }
+}
+
+/// A destructive action waiting for confirmation.
+pub struct Confirm {
+    pub action: Destructive,
+    /// Interface carrying the SSH session, if the action drops it.
+    pub ssh_interface: Option<String>,
+}
+
+impl Confirm {
+    pub fn render(&self, area: Rect, buf: &mut Buffer, theme: &Theme) {
+        let mut lines = vec![Line::from(self.action.description())];
+
+        if let Some(interface) = &self.ssh_interface {
+            lines.push(Line::default());
+            lines.push(Line::styled(
+                format!("Your SSH session goes through {interface} and will be cut"),
+                theme.error,
+            ));
+        }
+        if let Destructive::RotateKey(_) = self.action {
+            lines.push(Line::default());
+            lines.push(Line::from(
+                "The peer must be given the new public key to reconnect",
+            ));
+        }
+
+        lines.push(Line::default());
+        lines.push(Line::from("y: confirm, n: cancel").italic());
+
+        let width = lines.iter().map(Line::width).max().unwrap_or_default() as u16 + 4;
+        let area = popup::centered(area, width, lines.len() as u16 + 2);
+        Clear.render(area, buf);
+        Paragraph::new(lines)
+            .block(popup::block("Confirm"))
+            .render(area, buf);
+    }
+}
+
+/// The interface routing the current SSH session, if running over SSH.
+///
+/// Found by asking the kernel which interface routes the SSH client address.
+pub fn ssh_interface() -> Option<String> {
+    let client = ssh_client(&env::var("SSH_CONNECTION").ok()?)?;
+
+    let output = Command::new("ip")
+        .args(["route", "get", &client.to_string()])
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+
+    route_device(&String::from_utf8_lossy(&output.stdout)).map(str::to_string)
+}
+
+/// Client address of a `SSH_CONNECTION` value, `<client ip> <client port> <server ip> <server port>`.
+fn ssh_client(ssh_connection: &str) -> Option<IpAddr> {
+    ssh_connection.split_whitespace().next()?.parse().ok()
+}
+
+/// The device of an `ip route get` output, e.g. `10.0.0.1 dev wg0 src 10.0.0.2 uid 0`.
+fn route_device(output: &str) -> Option<&str> {
+    let mut words = output.split_whitespace();
+    words.find(|&w| w == "dev")?;
+    words.next()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+
+    use super::*;
+
+    #[test]
+    fn ssh_connection() {
+        assert_eq!(
+            ssh_client("192.168.1.10 51234 192.168.1.2 22"),
+            Some(IpAddr::V4(Ipv4Addr::new(192, 168, 1, 10)))
+        );
+        assert_eq!(ssh_client(""), None);
+    }
+
+    #[test]
+    fn device() {
+        assert_eq!(
+            route_device("10.6.0.1 dev wg0 src 10.6.0.2 uid 0 \n    cache "),
+            Some("wg0")
+        );
+        assert_eq!(
+            route_device("1.1.1.1 via 192.168.1.1 dev eth0 src 192.168.1.2"),
+            Some("eth0")
+        );
+        assert_eq!(
+            route_device("RTNETLINK answers: Network is unreachable"),
+            None
+        );
+    }
+}
@@ -1,14 +1,30 @@
 //! A single VPN connection
 use std::{
-    io::{self},
-    process::{Command, Stdio},
+    io,
+    path::{Path, PathBuf},
+    sync::Arc,
+    thread::{self, JoinHandle},
+    time::Instant,
 };
 
-use ratatui::{prelude::*, style::Styled, widgets::Row};
+use ratatui::{
+    prelude::*,
+    widgets::{ListItem, Row},
+};
 
+use super::{
+    CONFIG_DIR,
+    backend::{Backend, WgQuick},
+    column::Column,
+    theme::Theme,
+    watchdog::{Check, Watchdog, WatchdogPolicy},
+};
 use crate::wg::{
-    ConnectionStatus, WgConfig,
-    types::{Key, key::Public},
+    ConnectionStatus, Health, WgConfig,
+    types::{
+        Byte, Cidr, Endpoint, Key,
+        key::{Private, Public},
+    },
 };
 
 pub struct Connection {
@@ -19,102 +35,408 @@ pub struct Connection {
     /// Whether this VPN connection is active or not.
     status: ConnectionStatus,
     config: WgConfig,
+    /// The text of the file the configuration was read from, to tell when it really changes.
+    text: Option<String>,
+    /// Bytes received and sent per second between the two latest updates.
+    rate: Option<Byte>,
+    /// When the status was last updated.
+    updated_at: Option<Instant>,
+    backend: Arc<dyn Backend>,
+    origin: Origin,
+    /// Why the configuration couldn't be read, the connection is then only listed.
+    error: Option<String>,
+    /// Whether the file changed while connected: the interface still runs the previous
+    /// configuration.
+    changed: bool,
+    watchdog: Option<Watchdog>,
+    /// Restart started by the watchdog.
+    restart: Option<JoinHandle<io::Result<()>>>,
+    /// Connection or disconnection asked by the user, with what it does to report its failure,
+    /// e.g. `Connecting wg0`.
+    job: Option<(String, JoinHandle<io::Result<()>>)>,
+}
+
+/// Where a connection comes from.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Origin {
+    /// A configuration file.
+    File(PathBuf),
+    NetworkManager,
+    /// An interface created by another tool, without configuration file.
+    Unmanaged,
+    /// A configuration of `/etc/wireguard` served by the root [helper](super::helper).
+    Helper,
 }
 
+impl Origin {
+    /// The directory of the file, or the program managing the connection.
+    pub fn label(&self) -> String {
+        match self {
+            Origin::File(path) => path
+                .parent()
+                .map(|dir| dir.display().to_string())
+                .unwrap_or_default(),
+            Origin::NetworkManager => "NetworkManager".to_string(),
+            Origin::Unmanaged => "unmanaged".to_string(),
+            Origin::Helper => "helper".to_string(),
+        }
+    }
+
+    /// The configuration file, [None] if managed by another program.
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            Origin::File(path) => Some(path),
+            Origin::NetworkManager | Origin::Unmanaged | Origin::Helper => None,
+        }
+    }
+}
+
+/// Something to run in the background, see [`run`].
+pub type Task = Box<dyn FnOnce() -> io::Result<()> + Send>;
+
 impl Connection {
+    /// A connection of the `name.conf` file of `/etc/wireguard`.
     pub fn new(name: &str, config: WgConfig) -> Self {
+        let path = Path::new(CONFIG_DIR).join(format!("{name}.conf"));
         Self {
             name: name.to_string(),
             status: Default::default(),
             config,
+            text: None,
+            rate: None,
+            updated_at: None,
+            backend: Arc::new(WgQuick::new(&path)),
+            origin: Origin::File(path),
+            error: None,
+            changed: false,
+            watchdog: None,
+            restart: None,
+            job: None,
+        }
+    }
+
+    /// The connection of a configuration file containing `text`.
+    pub fn from_text(name: &str, text: &str) -> Self {
+        Self {
+            text: Some(text.to_string()),
+            ..Self::new(name, WgConfig::from(text))
+        }
+    }
+
+    /// A connection whose configuration couldn't be read, listed to show the `error`.
+    pub fn unreadable(name: &str, origin: Origin, error: String) -> Self {
+        Self {
+            origin,
+            error: Some(error),
+            ..Self::new(name, WgConfig::from(""))
+        }
+    }
+
+    /// Uses `backend` to connect and disconnect.
+    pub fn with_backend(mut self, backend: Arc<dyn Backend>) -> Self {
+        self.backend = backend;
+        self
+    }
+
+    pub fn with_origin(mut self, origin: Origin) -> Self {
+        self.origin = origin;
+        self
+    }
+
+    pub fn origin(&self) -> &Origin {
+        &self.origin
+    }
+
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    /// Restarts the connection when it dies by itself, according to `policy`.
+    pub fn set_watchdog(&mut self, policy: Option<WatchdogPolicy>) {
+        self.watchdog = policy.map(Watchdog::new);
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn config(&self) -> &WgConfig {
+        &self.config
+    }
+
+    /// The text of the configuration file, as last read or written.
+    pub fn text(&self) -> Option<&str> {
+        self.text.as_deref()
+    }
+
+    /// Replaces the configuration by the `text` wtui wrote to its file.
+    pub fn set_text(&mut self, text: &str) {
+        self.config = WgConfig::from(text);
+        self.text = Some(text.to_string());
+    }
+
+    /// Replaces the configuration of an interface without file, after another tool changed it.
+    pub fn set_config(&mut self, config: WgConfig) {
+        self.config = config;
+    }
+
+    /// Replaces the configuration after its file changed on disk to `text`, flagging the
+    /// connection as [changed](Self::is_changed) if it's up.
+    ///
+    /// Returns `false` if the text is the same, e.g. the file was only touched.
+    pub fn reload(&mut self, text: &str) -> bool {
+        if self.text.as_deref() == Some(text) {
+            return false;
         }
+        self.changed |= self.status.is_connected();
+        self.set_text(text);
+        true
+    }
+
+    /// Tells the interface runs the configuration now, after applying it without reconnecting.
+    pub fn applied(&mut self) {
+        self.changed = false;
+    }
+
+    /// Tells whether the interface runs a previous version of the configuration.
+    pub fn is_changed(&self) -> bool {
+        self.changed
+    }
+
+    pub fn status(&self) -> &ConnectionStatus {
+        &self.status
+    }
+
+    /// Current throughput, received and sent bytes combined.
+    ///
+    /// [None] if disconnected or not updated twice yet.
+    pub fn rate(&self) -> Option<Byte> {
+        self.rate
     }
 
     pub fn update(&mut self) {
-        self.status.update(&self.name);
+        let previous = total_bytes(&self.status);
+        let dump = self.backend.dump(&self.name).ok();
+        self.status.update(dump.as_deref());
+        // the next connection uses the file
+        self.changed &= self.status.is_connected();
+
+        let now = Instant::now();
+        self.rate = match (previous, total_bytes(&self.status), self.updated_at) {
+            (Some(previous), Some(current), Some(updated_at)) => {
+                let elapsed = now.duration_since(updated_at).as_secs_f64();
+                let bytes = current.saturating_sub(previous) as f64;
+                (elapsed > 0.).then(|| Byte::from((bytes / elapsed) as usize))
+            }
+            _ => None,
+        };
+        self.updated_at = Some(now);
+    }
+
+    /// A row of the connections table, made of the given columns.
+    pub fn row(&self, columns: &[Column], theme: &Theme) -> Row<'static> {
+        Row::new(columns.iter().map(|column| column.cell(self, theme)))
+            .height(self.row_height(columns))
+            .style(self.style(theme))
+    }
+
+    /// Height of the row made of the given columns.
+    pub fn row_height(&self, columns: &[Column]) -> u16 {
+        // only multiline columns need the extra height
+        match columns.contains(&Column::AllowedIps) || columns.contains(&Column::Dns) {
+            true => self
+                .config
+                .peer
+                .allowed_ips()
+                .len()
+                .max(self.config.interface.dns().len())
+                .max(1) as u16,
+            false => 1,
+        }
     }
 
-    /// Connects to this connection.
+    /// A two lines summary, for terminals too narrow for the table.
+    pub fn card(&self, theme: &Theme) -> ListItem<'static> {
+        let status = Column::Status.cell(self, theme);
+        let mut first = Line::from(vec![
+            self.name.clone().bold(),
+            " ".into(),
+            status.to_string().into(),
+            " ".into(),
+            self.config
+                .peer
+                .endpoint()
+                .map(Endpoint::to_string)
+                .unwrap_or_default()
+                .into(),
+        ]);
+        first.spans.retain(|s| !s.content.is_empty());
+
+        let mut second = Line::from(vec![
+            "  ".into(),
+            Cidr::join(self.config.interface.address(), ", ").into(),
+        ]);
+        if let (Some(received), Some(sent)) =
+            (self.status.bytes_received(), self.status.bytes_sent())
+        {
+            second.push_span(" ↓");
+            second
+                .spans
+                .extend(received.text(theme.unit).lines.remove(0).spans);
+            second.push_span(" ↑");
+            second
+                .spans
+                .extend(sent.text(theme.unit).lines.remove(0).spans);
+            second.push_span(format!(" {}", self.status.handshake_since()));
+        }
+
+        ListItem::new(vec![first, second]).style(self.style(theme))
+    }
+
+    /// Style of the connection depending on its health.
+    fn style(&self, theme: &Theme) -> Style {
+        if self.error.is_some() {
+            return theme.error;
+        }
+        match self.status.health() {
+            Health::Healthy => theme.connected,
+            Health::Stale => theme.stale,
+            Health::NoHandshake => theme.idle,
+            Health::Down => theme.disconnected,
+        }
+    }
+
+    /// Connects to this connection, in the background.
+    ///
+    /// Does nothing if the connection is already connected or its configuration is unreadable.
+    pub fn connect(&mut self) {
+        if !self.status.is_connected() && self.error.is_none() {
+            let job = run(vec![self.task(true)]);
+            self.set_job(format!("Connecting {}", self.name), job);
+        }
+    }
+
+    /// Lets the watchdog keep this connection up, when it was up already, e.g. set to autoconnect.
+    pub fn keep_up(&mut self) {
+        if let Some(watchdog) = &mut self.watchdog {
+            watchdog.set_wanted(true);
+        }
+    }
+
+    /// Disconnects from this connection, in the background.
     ///
-    /// Does nothing and return [Ok] if the connection is already connected.
-    pub fn connect(&self) -> io::Result<()> {
+    /// Does nothing if the connection is already disconnected.
+    pub fn disconnect(&mut self) {
         if self.status.is_connected() {
-            return Ok(());
+            let job = run(vec![self.task(false)]);
+            self.set_job(format!("Disconnecting {}", self.name), job);
         }
+    }
+
+    /// Keeps the running `job` doing `action` on this connection, to report its failure.
+    pub fn set_job(&mut self, action: String, job: JoinHandle<io::Result<()>>) {
+        self.job = Some((action, job));
+    }
 
-        Command::new("wg-quick")
-            .arg("up")
-            .arg(&self.name)
-            .stdout(Stdio::null())
-            .stderr(Stdio::null())
-            .spawn()
-            .map(|_| ())
+    /// Why the latest connection or disconnection failed, once finished.
+    pub fn failure(&mut self) -> Option<String> {
+        let (action, job) = self.job.take_if(|(_, job)| job.is_finished())?;
+        job.join()
+            .unwrap_or_else(|_| Err(io::Error::other("panicked")))
+            .err()
+            .map(|e| format!("{action} failed: {e}"))
     }
 
-    /// Disconnects from this connection.
+    /// A task connecting (`up`) or disconnecting this connection, as asked by the user.
+    pub fn task(&mut self, up: bool) -> Task {
+        self.changed = false;
+        if let Some(watchdog) = &mut self.watchdog {
+            watchdog.set_wanted(up);
+        }
+        self.backend_task(up)
+    }
+
+    fn backend_task(&self, up: bool) -> Task {
+        let backend = Arc::clone(&self.backend);
+        let name = self.name.clone();
+        match up {
+            true => Box::new(move || backend.up(&name)),
+            false => Box::new(move || backend.down(&name)),
+        }
+    }
+
+    /// Lets the watchdog check the connection, restarting it if needed.
     ///
-    /// Does nothing and return [Ok] if the connection is already disconnected.
-    pub fn disconnect(&self) -> io::Result<()> {
-        if !self.status.is_connected() {
-            return Ok(());
+    /// Returns what happened, to log.
+    pub fn watch(&mut self, now: Instant) -> Vec<String> {
+        let mut events = Vec::new();
+
+        if let Some(restart) = self.restart.take_if(|r| r.is_finished())
+            && let Err(e) = restart
+                .join()
+                .unwrap_or_else(|_| Err(io::Error::other("panicked")))
+        {
+            events.push(format!("Restarting {} failed: {e}", self.name));
+        }
+        // wait for the running restart
+        if self.restart.is_some() {
+            return events;
         }
+        let Some(watchdog) = &mut self.watchdog else {
+            return events;
+        };
 
-        Command::new("wg-quick")
-            .arg("down")
-            .arg(&self.name)
-            .stdout(Stdio::null())
-            .stderr(Stdio::null())
-            .spawn()
-            .map(|_| ())
+        let keepalive = self.config.peer.persistent_keepalive().is_some();
+        match watchdog.check(self.status.health(), keepalive, now) {
+            Check::Idle => {}
+            Check::Restart { attempt } => {
+                events.push(format!(
+                    "{} is {}, restarting it (attempt {attempt})",
+                    self.name,
+                    self.status.health().label()
+                ));
+                self.restart = Some(run(vec![self.backend_task(false), self.backend_task(true)]));
+            }
+            Check::GiveUp => events.push(format!("Gave up restarting {}", self.name)),
+            Check::Recovered => events.push(format!("{} is back up", self.name)),
+        }
+
+        events
+    }
+
+    /// Sets the private key of the running interface, through its backend.
+    ///
+    /// Does nothing and return [Ok] if the connection is disconnected.
+    pub fn apply_private_key(&self, key: &Key<Private>) -> io::Result<()> {
+        match self.status.is_connected() {
+            true => self.backend.set_private_key(&self.name, key),
+            false => Ok(()),
+        }
     }
 
     pub fn pubkey(&self) -> &Key<Public> {
         self.config.interface.pubkey()
     }
-}
 
-impl From<&Connection> for Row<'_> {
-    fn from(con: &Connection) -> Self {
-        let row_height = con
-            .config
-            .peer
-            .allowed_ips()
-            .len()
-            .max(con.config.interface.dns().len()) as u16;
-
-        Self::new([
-            con.name.clone().set_style(Style::new().bold()).into(),
-            con.config.interface.address().to_string().into(),
-            con.config.interface.mtu().to_string().into(),
-            con.status
-                .bytes_received()
-                .map(Text::from)
-                .unwrap_or_default(),
-            con.status.bytes_sent().map(Text::from).unwrap_or_default(),
-            con.config.peer.endpoint().to_string().into(),
-            con.config.peer.allowed_ips().join("\n").into(),
-            con.status.handshake_since().into(),
-            con.config.interface.pubkey().truncated().into(),
-            Text::from(
-                con.config
-                    .interface
-                    .dns()
-                    .iter()
-                    .map(|ip| ip.to_string())
-                    .collect::<Vec<_>>()
-                    .join("\n"),
-            ),
-        ])
-        .height(row_height)
-        .set_style(match con.status.bytes_received() {
-            Some(b) if **b > 0 => Style::new().green(),
-            Some(b) if **b == 0 => Style::new().yellow(),
-            None => Style::new().add_modifier(Modifier::DIM),
-            Some(_) => unreachable!("Some arms already cover every cases"),
-        })
+    /// Networks routed by both this connection and `other`.
+    pub fn conflicts(&self, other: &Connection) -> Vec<Cidr> {
+        self.config.peer.overlapping(&other.config.peer)
     }
 }
 
+/// Runs the tasks one after the other in a background thread, stopping at the first error.
+///
+/// For instance, disconnecting conflicting connections is done before connecting another one, so
+/// that their routes are removed before the new ones are added.
+pub fn run(tasks: Vec<Task>) -> JoinHandle<io::Result<()>> {
+    thread::spawn(move || tasks.into_iter().try_for_each(|task| task()))
+}
+
+/// Received and sent bytes combined.
+fn total_bytes(status: &ConnectionStatus) -> Option<usize> {
+    Some(**status.bytes_received()? + **status.bytes_sent()?)
+}
+
 impl PartialEq for Connection {
     fn eq(&self, other: &Self) -> bool {
         self.name == other.name
@@ -134,3 +456,102 @@ impl Ord for Connection {
         self.name.cmp(&other.name)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::app::backend::Mock;
+
+    #[test]
+    fn watchdog_restarts() {
+        let mock = Arc::new(Mock::default());
+        let mut con = Connection::new("wg0", WgConfig::from("")).with_backend(mock.clone());
+        con.set_watchdog(Some(WatchdogPolicy {
+            delay: Duration::from_secs(10),
+            retries: 1,
+        }));
+        let start = Instant::now();
+
+        run(vec![con.task(true)]).join().unwrap().unwrap();
+        con.status = ConnectionStatus::Connected {
+            latest_handshake: Some(chrono::Utc::now()),
+            bytes_received: 0.into(),
+            bytes_sent: 0.into(),
+            endpoint: None,
+        };
+        assert!(con.watch(start).is_empty());
+
+        con.status = ConnectionStatus::Disconnected;
+        assert!(con.watch(start).is_empty());
+        assert_eq!(
+            con.watch(start + Duration::from_secs(10)),
+            vec!["wg0 is down, restarting it (attempt 1)"]
+        );
+
+        con.restart.take().unwrap().join().unwrap().unwrap();
+        assert_eq!(mock.calls(), vec!["up wg0", "down wg0", "up wg0"]);
+    }
+
+    #[test]
+    fn no_restart_after_disconnect() {
+        let mock = Arc::new(Mock::default());
+        let mut con = Connection::new("wg0", WgConfig::from("")).with_backend(mock.clone());
+        con.set_watchdog(Some(WatchdogPolicy::default()));
+        let start = Instant::now();
+
+        con.status = ConnectionStatus::Connected {
+            latest_handshake: Some(chrono::Utc::now()),
+            bytes_received: 0.into(),
+            bytes_sent: 0.into(),
+            endpoint: None,
+        };
+        con.watch(start);
+        run(vec![con.task(false)]).join().unwrap().unwrap();
+        con.status = ConnectionStatus::Disconnected;
+
+        assert!(con.watch(start + Duration::from_secs(3600)).is_empty());
+        assert_eq!(mock.calls(), vec!["down wg0"]);
+    }
+
+    #[test]
+    fn failure() {
+        let mut con =
+            Connection::new("wg0", WgConfig::from("")).with_backend(Arc::new(Mock::failing()));
+        con.connect();
+        while con.job.as_ref().is_some_and(|(_, job)| !job.is_finished()) {
+            thread::sleep(Duration::from_millis(1));
+        }
+
+        assert_eq!(
+            con.failure().as_deref(),
+            Some("Connecting wg0 failed: mock failure")
+        );
+        // reported once
+        assert_eq!(con.failure(), None);
+    }
+
+    #[test]
+    fn changed_while_connected() {
+        let mut con = Connection::from_text("wg0", "");
+        assert!(!con.reload(""));
+        assert!(con.reload("[Interface]\nMTU = 1280"));
+        assert!(!con.is_changed());
+
+        con.status = ConnectionStatus::Connected {
+            latest_handshake: None,
+            bytes_received: 0.into(),
+            bytes_sent: 0.into(),
+            endpoint: None,
+        };
+        assert!(con.reload("[Interface]\nMTU = 1380"));
+        assert!(con.is_changed());
+        assert_eq!(con.config().interface.mtu(), 1380);
+
+        // reconnecting uses the file
+        let mut con = con.with_backend(Arc::new(Mock::default()));
+        con.task(true)().unwrap();
+        assert!(!con.is_changed());
+    }
+}
@@ -0,0 +1,177 @@
+//! Detailed view of a connection.
+use std::{
+    net::IpAddr,
+    sync::{Arc, Mutex},
+    thread,
+};
+
+use ratatui::{prelude::*, widgets::*};
+
+use super::{
+    backend::systemd::{self, UnitState},
+    column::Column,
+    connection::Connection,
+    popup,
+    theme::Theme,
+};
+
+/// Result of resolving the endpoint hostname.
+type Resolution = Result<Vec<IpAddr>, String>;
+
+/// State of the systemd unit, if systemd answered, and its latest journal lines.
+type Unit = (Option<UnitState>, Vec<String>);
+
+/// Journal lines shown.
+const JOURNAL_LINES: usize = 5;
+
+/// Every field of a connection, with its endpoint resolved.
+pub struct Details {
+    name: String,
+    /// IPs of the endpoint host, set by a background resolution.
+    ///
+    /// [None] while resolving or if the endpoint is already an IP.
+    resolved: Arc<Mutex<Option<Resolution>>>,
+    /// The systemd unit, set by a background query.
+    ///
+    /// [None] if systemd isn't running.
+    unit: Option<Arc<Mutex<Option<Unit>>>>,
+}
+
+impl Details {
+    /// Starts resolving the endpoint of `con` and querying its systemd unit in the background.
+    pub fn new(con: &Connection) -> Self {
+        let resolved = Arc::new(Mutex::new(None));
+
+        if let Some(endpoint) = con.config().peer.endpoint().filter(|e| e.ip().is_none()) {
+            let endpoint = endpoint.clone();
+            let resolved = Arc::clone(&resolved);
+            thread::spawn(move || {
+                let resolution = endpoint.resolve().map_err(|e| e.to_string());
+                *resolved.lock().expect("resolution thread never panics") = Some(resolution);
+            });
+        }
+
+        let unit = systemd::is_running().then(|| {
+            let unit = Arc::new(Mutex::new(None));
+            let name = con.name().to_string();
+            let shared = Arc::clone(&unit);
+            thread::spawn(move || {
+                let state = systemd::state(&name);
+                let journal = match &state {
+                    Some(state) if state.exists() => {
+                        systemd::journal(&name, JOURNAL_LINES).unwrap_or_default()
+                    }
+                    _ => Vec::new(),
+                };
+                *shared.lock().expect("unit thread never panics") = Some((state, journal));
+            });
+            unit
+        });
+
+        Self {
+            name: con.name().to_string(),
+            resolved,
+            unit,
+        }
+    }
+
+    /// Name of the shown connection.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn render(&self, area: Rect, buf: &mut Buffer, con: &Connection, theme: &Theme) {
+        let mut rows = Vec::new();
+
+        for column in Column::ALL {
+            let text = match column {
+                // room for the whole key
+                Column::PublicKey => Text::from(con.pubkey().as_str().to_string()),
+                _ => column.cell(con, theme),
+            };
+            rows.push(row(column.title(), text));
+
+            if column == Column::Endpoint {
+                if let Some(endpoint) = con.config().peer.endpoint().filter(|e| e.ip().is_none()) {
+                    let resolved = match &*self.resolved.lock().expect("resolution never panics") {
+                        None => Text::from("resolving…").italic(),
+                        Some(Ok(ips)) => Text::from(
+                            ips.iter()
+                                .map(|ip| format!("{ip} ({})", endpoint.host()))
+                                .collect::<Vec<_>>()
+                                .join("\n"),
+                        ),
+                        Some(Err(e)) => Text::styled(e.clone(), theme.error),
+                    };
+                    rows.push(row("Resolved", resolved));
+                }
+
+                let current = match con.status().endpoint() {
+                    Some(endpoint) => Text::from(endpoint.to_string()),
+                    None => Text::from("none").dim(),
+                };
+                rows.push(row("Current endpoint", current));
+            }
+        }
+
+        if let Some(path) = con.origin().path() {
+            rows.push(row("File", Text::from(path.display().to_string())));
+        }
+        if con.is_changed() {
+            rows.push(row(
+                "Config",
+                Text::styled("changed on disk, restart to apply", theme.error),
+            ));
+        }
+        if let Some(error) = con.error() {
+            rows.push(row("Error", Text::styled(error.to_string(), theme.error)));
+        }
+
+        if let Some(unit) = &self.unit {
+            rows.extend(self.unit_rows(&unit.lock().expect("unit query never panics")));
+        }
+
+        let height = rows.iter().map(|(_, h)| h).sum::<u16>() + 2;
+        let area = popup::centered(area, 80, height);
+        Clear.render(area, buf);
+        let table = Table::new(
+            rows.into_iter().map(|(row, _)| row),
+            [Constraint::Length(17), Constraint::Fill(1)],
+        )
+        .block(popup::block(&self.name));
+        Widget::render(table, area, buf);
+    }
+}
+
+impl Details {
+    fn unit_rows(&self, unit: &Option<Unit>) -> Vec<(Row<'static>, u16)> {
+        let (state, journal) = match unit {
+            None => return vec![row("Unit", Text::from("loading…").italic())],
+            Some((Some(state), journal)) if state.exists() => (state, journal),
+            Some(_) => return vec![row("Unit", Text::from("none").dim())],
+        };
+
+        let mut rows = vec![row(
+            "Unit",
+            Text::from(format!(
+                "{}, {}, {}",
+                systemd::unit(&self.name),
+                state.file,
+                state.active
+            )),
+        )];
+        if !journal.is_empty() {
+            rows.push(row("Journal", Text::from(journal.join("\n")).dim()));
+        }
+        rows
+    }
+}
+
+/// A row of the details table and its height.
+fn row(title: &str, text: Text<'static>) -> (Row<'static>, u16) {
+    let height = text.height().max(1) as u16;
+    (
+        Row::new([Cell::from(title.to_string().bold()), Cell::from(text)]).height(height),
+        height,
+    )
+}
@@ -0,0 +1,325 @@
+//! Differences between the configuration file of a connection and what its interface runs.
+//!
+//! They appear after `wg set`, with `SaveConfig` or when the file is edited while connected.
+use ratatui::{prelude::*, widgets::*};
+
+use super::{popup, theme::Theme};
+use crate::wg::{self, WgConfig, types::Cidr};
+
+/// A setting of the file compared with the one the interface runs.
+struct Field {
+    label: &'static str,
+    file: String,
+    running: String,
+    differs: bool,
+    /// Section, key and value writing the running setting to the file, [None] removing the key.
+    update: (&'static str, &'static str, Option<String>),
+}
+
+/// The comparison of a connection's file with its interface.
+pub struct Drift {
+    /// Name of the connection.
+    pub name: String,
+    fields: Vec<Field>,
+    /// Error of the latest action.
+    error: Option<String>,
+}
+
+impl Drift {
+    pub fn new(name: &str, file: &WgConfig, running: &WgConfig) -> Self {
+        Self {
+            name: name.to_string(),
+            fields: compare(file, running),
+            error: None,
+        }
+    }
+
+    /// Tells whether the interface runs something else than the file.
+    pub fn differs(&self) -> bool {
+        self.fields.iter().any(|f| f.differs)
+    }
+
+    /// The raw file `text` with the settings which differ replaced by the running ones.
+    pub fn running_text(&self, text: &str) -> String {
+        self.fields
+            .iter()
+            .filter(|f| f.differs)
+            .fold(text.to_string(), |text, field| {
+                let (section, key, value) = &field.update;
+                wg::with_value(&text, section, key, value.as_deref())
+            })
+    }
+
+    pub fn set_error(&mut self, error: String) {
+        self.error = Some(error);
+    }
+
+    pub fn render(&self, area: Rect, buf: &mut Buffer, theme: &Theme) {
+        let header = Row::new(["", "File", "Running"]).bold();
+        let rows: Vec<_> = self
+            .fields
+            .iter()
+            .map(|field| {
+                let row = Row::new([
+                    Cell::from(field.label.bold()),
+                    Cell::from(field.file.clone()),
+                    Cell::from(field.running.clone()),
+                ]);
+                match field.differs {
+                    true => row.style(theme.error),
+                    false => row,
+                }
+            })
+            .collect();
+
+        let mut lines = vec![match self.differs() {
+            true => Line::styled("The interface doesn't run the file", theme.error),
+            false => Line::styled("The interface runs the file", theme.success),
+        }];
+        if let Some(error) = &self.error {
+            lines.push(Line::styled(error.clone(), theme.error));
+        }
+        lines.push(Line::default());
+        lines.push(Line::from(vec![
+            "a".bold(),
+            ": apply the file to the interface, without disconnecting".into(),
+        ]));
+        lines.push(Line::from(vec![
+            "w".bold(),
+            ": write what the interface runs to the file".into(),
+        ]));
+        lines.push(Line::from(vec!["Esc".bold(), ": close".into()]));
+
+        let height = rows.len() as u16 + lines.len() as u16 + 4;
+        let area = popup::centered(area, 90, height);
+        Clear.render(area, buf);
+        let block = popup::block(&self.name);
+        let inner = block.inner(area);
+        block.render(area, buf);
+
+        let [table_area, text_area] = Layout::vertical([
+            Constraint::Length(rows.len() as u16 + 2),
+            Constraint::Fill(1),
+        ])
+        .areas(inner);
+        Widget::render(
+            Table::new(
+                rows,
+                [
+                    Constraint::Length(14),
+                    Constraint::Fill(1),
+                    Constraint::Fill(1),
+                ],
+            )
+            .header(header.bottom_margin(1)),
+            table_area,
+            buf,
+        );
+        Paragraph::new(lines).render(text_area, buf);
+    }
+}
+
+/// Compares the settings `wg show` knows, and the addresses.
+fn compare(file: &WgConfig, running: &WgConfig) -> Vec<Field> {
+    let (fi, ri) = (&file.interface, &running.interface);
+    let (fp, rp) = (&file.peer, &running.peer);
+    let or_none = |value: Option<String>| value.unwrap_or_else(|| "none".to_string());
+    let sorted = |cidrs: &[Cidr]| {
+        let mut cidrs: Vec<_> = cidrs.iter().map(Cidr::to_string).collect();
+        cidrs.sort();
+        cidrs
+    };
+
+    let fwmark = |fwmark: Option<u32>| fwmark.map(|mark| format!("{mark:#x}"));
+    let file_endpoint = fp.endpoint();
+    let running_endpoint = rp.endpoint();
+    // the kernel only knows the IP of a hostname
+    let endpoint_differs = match (file_endpoint, running_endpoint) {
+        (Some(f), Some(r)) => f != r && (f.ip().is_some() || f.port() != r.port()),
+        (Some(_), None) => true,
+        // the peer sets it when it reaches us
+        (None, _) => false,
+    };
+    let preshared = |config: &WgConfig| {
+        config
+            .peer
+            .preshared_key()
+            .map(|_| "set".to_string())
+            .unwrap_or_else(|| "none".to_string())
+    };
+    let keepalive = |keepalive: Option<u16>| keepalive.map(|secs| secs.to_string());
+
+    vec![
+        Field {
+            label: "Public key",
+            file: fi.pubkey().truncated(),
+            running: ri.pubkey().truncated(),
+            differs: fi.pubkey() != ri.pubkey(),
+            update: (
+                "Interface",
+                "PrivateKey",
+                Some(ri.private_key().as_str().to_string()),
+            ),
+        },
+        Field {
+            label: "Listen port",
+            file: fi
+                .listen_port()
+                .map(|port| port.to_string())
+                .unwrap_or_else(|| "random".to_string()),
+            running: or_none(ri.listen_port().map(|port| port.to_string())),
+            // without one in the file, the kernel picks any
+            differs: fi.listen_port().is_some() && fi.listen_port() != ri.listen_port(),
+            update: (
+                "Interface",
+                "ListenPort",
+                ri.listen_port().map(|port| port.to_string()),
+            ),
+        },
+        Field {
+            label: "FwMark",
+            file: fwmark(fi.fwmark()).unwrap_or_else(|| "auto".to_string()),
+            running: or_none(fwmark(ri.fwmark())),
+            // without one in the file, wg-quick picks one for default routes
+            differs: fi.fwmark().is_some() && fi.fwmark() != ri.fwmark(),
+            update: ("Interface", "FwMark", fwmark(ri.fwmark())),
+        },
+        Field {
+            label: "Address",
+            file: Cidr::join(fi.address(), ", "),
+            running: Cidr::join(ri.address(), ", "),
+            differs: sorted(fi.address()) != sorted(ri.address()),
+            update: (
+                "Interface",
+                "Address",
+                Some(Cidr::join(ri.address(), ", ")).filter(|a| !a.is_empty()),
+            ),
+        },
+        Field {
+            label: "Peer",
+            file: fp.pubkey().truncated(),
+            running: rp.pubkey().truncated(),
+            differs: fp.pubkey() != rp.pubkey(),
+            update: ("Peer", "PublicKey", Some(rp.pubkey().as_str().to_string())),
+        },
+        Field {
+            label: "Preshared key",
+            file: preshared(file),
+            running: preshared(running),
+            differs: fp.preshared_key() != rp.preshared_key(),
+            update: (
+                "Peer",
+                "PresharedKey",
+                rp.preshared_key().map(|key| key.as_str().to_string()),
+            ),
+        },
+        Field {
+            label: "Endpoint",
+            file: or_none(file_endpoint.map(ToString::to_string)),
+            running: or_none(running_endpoint.map(ToString::to_string)),
+            differs: endpoint_differs,
+            update: (
+                "Peer",
+                "Endpoint",
+                running_endpoint.map(ToString::to_string),
+            ),
+        },
+        Field {
+            label: "Allowed IPs",
+            file: Cidr::join(fp.allowed_ips(), ", "),
+            running: Cidr::join(rp.allowed_ips(), ", "),
+            differs: sorted(fp.allowed_ips()) != sorted(rp.allowed_ips()),
+            update: (
+                "Peer",
+                "AllowedIPs",
+                Some(Cidr::join(rp.allowed_ips(), ", ")).filter(|ips| !ips.is_empty()),
+            ),
+        },
+        Field {
+            label: "Keepalive",
+            file: or_none(keepalive(fp.persistent_keepalive())),
+            running: or_none(keepalive(rp.persistent_keepalive())),
+            differs: fp.persistent_keepalive() != rp.persistent_keepalive(),
+            update: (
+                "Peer",
+                "PersistentKeepalive",
+                keepalive(rp.persistent_keepalive()),
+            ),
+        },
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FILE: &str = "[Interface]
+# laptop
+PrivateKey = oMVUWFwDf+20fIfeRUe7c0rlUKSYnHk2K0y2920SX1c=
+Address = 10.0.0.2/24
+DNS = 10.1.0.1
+
+[Peer]
+PublicKey = 60TUAvOo+Wi4SCyir581cCyBx4wIcHtrIrUgBv/iqRM=
+AllowedIPs = 10.1.0.0/16
+Endpoint = vpn.example.com:51820
+";
+
+    #[test]
+    fn same() {
+        let running = WgConfig::from(
+            "[Interface]
+PrivateKey = oMVUWFwDf+20fIfeRUe7c0rlUKSYnHk2K0y2920SX1c=
+Address = 10.0.0.2/24
+ListenPort = 40123
+
+[Peer]
+PublicKey = 60TUAvOo+Wi4SCyir581cCyBx4wIcHtrIrUgBv/iqRM=
+AllowedIPs = 10.1.0.0/16
+Endpoint = 203.0.113.1:51820
+",
+        );
+
+        assert!(!Drift::new("wg0", &WgConfig::from(FILE), &running).differs());
+    }
+
+    #[test]
+    fn save_running() {
+        let running = WgConfig::from(
+            "[Interface]
+PrivateKey = oMVUWFwDf+20fIfeRUe7c0rlUKSYnHk2K0y2920SX1c=
+Address = 10.0.0.2/24
+
+[Peer]
+PublicKey = 60TUAvOo+Wi4SCyir581cCyBx4wIcHtrIrUgBv/iqRM=
+AllowedIPs = 10.2.0.0/16, 10.1.0.0/16
+Endpoint = 203.0.113.1:51821
+PersistentKeepalive = 25
+",
+        );
+        let drift = Drift::new("wg0", &WgConfig::from(FILE), &running);
+        let differing: Vec<_> = drift
+            .fields
+            .iter()
+            .filter(|f| f.differs)
+            .map(|f| f.label)
+            .collect();
+        assert_eq!(differing, vec!["Endpoint", "Allowed IPs", "Keepalive"]);
+
+        assert_eq!(
+            drift.running_text(FILE),
+            "[Interface]
+# laptop
+PrivateKey = oMVUWFwDf+20fIfeRUe7c0rlUKSYnHk2K0y2920SX1c=
+Address = 10.0.0.2/24
+DNS = 10.1.0.1
+
+[Peer]
+PublicKey = 60TUAvOo+Wi4SCyir581cCyBx4wIcHtrIrUgBv/iqRM=
+AllowedIPs = 10.2.0.0/16, 10.1.0.0/16
+Endpoint = 203.0.113.1:51821
+PersistentKeepalive = 25
+"
+        );
+    }
+}
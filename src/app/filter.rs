@@ -0,0 +1,107 @@
+//! Filtering of the connections list.
+use super::{connection::Connection, input::Input};
+use crate::wg::types::{Cidr, Endpoint};
+
+/// Which connections are shown.
+#[derive(Default)]
+pub struct Filter {
+    /// Text searched in the name, address, endpoint and allowed IPs.
+    pub query: Input,
+    /// Only show connected connections.
+    pub connected: bool,
+    /// Only show connections with a stale handshake.
+    pub stale: bool,
+}
+
+impl Filter {
+    pub fn matches(&self, con: &Connection) -> bool {
+        let query = self.query.value().to_lowercase();
+        let config = con.config();
+
+        (!self.connected || con.status().is_connected())
+            && (!self.stale || con.status().is_stale())
+            && (query.is_empty()
+                || [
+                    con.name().to_string(),
+                    config
+                        .peer
+                        .endpoint()
+                        .map(Endpoint::to_string)
+                        .unwrap_or_default(),
+                ]
+                .into_iter()
+                .chain(config.interface.address().iter().map(Cidr::to_string))
+                .chain(config.peer.allowed_ips().iter().map(Cidr::to_string))
+                .any(|field| field.to_lowercase().contains(&query)))
+    }
+
+    /// Short description of the active filters, empty if none.
+    pub fn describe(&self) -> String {
+        let mut filters = Vec::new();
+        if !self.query.value().is_empty() {
+            filters.push(format!("/{}", self.query.value()));
+        }
+        if self.connected {
+            filters.push("connected".to_string());
+        }
+        if self.stale {
+            filters.push("stale".to_string());
+        }
+        filters.join(", ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wg::WgConfig;
+
+    fn connection() -> Connection {
+        Connection::new(
+            "office",
+            WgConfig::from(
+                r#"[Interface]
+PrivateKey = oMVUWFwDf+20fIfeRUe7c0rlUKSYnHk2K0y2920SX1c=
+Address = 192.168.5.2
+
+[Peer]
+PublicKey = 60TUAvOo+Wi4SCyir581cCyBx4wIcHtrIrUgBv/iqRM=
+AllowedIPs = 10.8.0.0/16, 192.168.6.0/24
+Endpoint = vpn.example.com:51820"#,
+            ),
+        )
+    }
+
+    #[test]
+    fn query() {
+        let con = connection();
+        let filter = |query: &str| Filter {
+            query: Input::from(query),
+            ..Default::default()
+        };
+
+        assert!(filter("").matches(&con));
+        assert!(filter("OFF").matches(&con));
+        assert!(filter("192.168.5").matches(&con));
+        assert!(filter("example.com").matches(&con));
+        assert!(filter("10.8.").matches(&con));
+        assert!(!filter("home").matches(&con));
+    }
+
+    #[test]
+    fn status() {
+        let con = connection();
+
+        let connected = Filter {
+            connected: true,
+            ..Default::default()
+        };
+        let stale = Filter {
+            stale: true,
+            ..Default::default()
+        };
+
+        assert!(!connected.matches(&con));
+        assert!(!stale.matches(&con));
+    }
+}
@@ -0,0 +1,439 @@
+//! A root helper bringing the connections of `/etc/wireguard` up and down for wtui run by a
+//! normal user, over a Unix socket.
+//!
+//! Clients send a single request line, then read the answer until the helper closes the socket:
+//! `ok` and the result on the next lines, or `error <message>`. Requests are:
+//!
+//! - `list`: names of the configurations, one per line
+//! - `status`: `wg show all dump`, with the keys hidden
+//! - `config <name>`: public key of the interface, then the configuration with its keys hidden
+//! - `up <name>` and `down <name>`
+//!
+//! Only root and the members of the helper's group are served, checked with the credentials of the
+//! socket peer.
+use std::{
+    collections::HashMap,
+    env,
+    ffi::CString,
+    fs::{self, Permissions},
+    io::{self, BufRead, BufReader, Read, Write},
+    mem,
+    os::{
+        fd::{AsRawFd, FromRawFd},
+        unix::{
+            fs::PermissionsExt,
+            net::{UnixListener, UnixStream},
+        },
+    },
+    path::{Path, PathBuf},
+    process,
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+use super::{
+    CONFIG_DIR, file_backend, import::is_valid_interface_name, settings::Settings, source::Source,
+};
+use crate::wg::{HIDDEN, WgConfig, runtime};
+
+/// Where the helper listens.
+pub const SOCKET: &str = "/run/wtui.sock";
+
+/// Group allowed to use the helper besides root, unless another one is given.
+pub const GROUP: &str = "wtui";
+
+/// Longest request: a command and an interface name.
+const MAX_REQUEST: u64 = 64;
+
+/// Time to wait for an answer, `wg-quick` may take a while.
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Time for a client to send its request.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Delay between two status requests.
+const POLL: Duration = Duration::from_secs(1);
+
+/// The latest dump of each interface, polled from the helper in the background.
+#[derive(Default)]
+pub struct Statuses(Mutex<HashMap<String, String>>);
+
+impl Statuses {
+    /// Gets the statuses, then polls them every [POLL] until dropped.
+    pub fn watch() -> Arc<Self> {
+        let statuses = Arc::new(Self::default());
+        statuses.refresh();
+
+        let weak = Arc::downgrade(&statuses);
+        thread::spawn(move || {
+            loop {
+                thread::sleep(POLL);
+                match weak.upgrade() {
+                    Some(statuses) => statuses.refresh(),
+                    None => return,
+                }
+            }
+        });
+        statuses
+    }
+
+    /// `wg show <name> dump`, [None] if the interface is down or the helper unreachable.
+    pub fn dump(&self, name: &str) -> Option<String> {
+        self.0.lock().expect("not poisoned").get(name).cloned()
+    }
+
+    fn refresh(&self) {
+        let dumps = request("status", None)
+            .map(|all| runtime::split_dumps(&all))
+            .unwrap_or_default();
+        *self.0.lock().expect("not poisoned") = dumps;
+    }
+}
+
+/// Tells whether wtui runs as a normal user with a helper listening.
+pub fn available() -> bool {
+    // SAFETY: plain syscall
+    let root = unsafe { libc::geteuid() } == 0;
+    !root && Path::new(SOCKET).exists()
+}
+
+/// Sends a request to the helper, returns its result.
+pub fn request(command: &str, name: Option<&str>) -> io::Result<String> {
+    let mut stream = UnixStream::connect(SOCKET)?;
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    match name {
+        Some(name) => writeln!(stream, "{command} {name}")?,
+        None => writeln!(stream, "{command}")?,
+    }
+
+    let mut answer = String::new();
+    stream.read_to_string(&mut answer)?;
+    parse_answer(&answer)
+}
+
+/// The configuration of the `name` connection, with its keys hidden.
+pub fn config(name: &str) -> io::Result<WgConfig> {
+    let answer = request("config", Some(name))?;
+    let (pubkey, text) = answer.split_once('\n').unwrap_or((&answer, ""));
+    Ok(WgConfig::from_redacted(text, pubkey))
+}
+
+/// Serves requests until killed, as root.
+///
+/// Uses the socket passed by systemd when socket activated, else creates [SOCKET] for root and
+/// `group` only.
+pub fn serve(group: &str) -> io::Result<()> {
+    let gid = group_id(&fs::read_to_string("/etc/group")?, group)
+        .ok_or_else(|| io::Error::other(format!("no {group} group")))?;
+    let listener = match activated() {
+        Some(listener) => listener,
+        None => bind(gid)?,
+    };
+
+    for stream in listener.incoming() {
+        let Ok(stream) = stream else {
+            continue;
+        };
+        let group = group.to_string();
+        thread::spawn(move || serve_client(stream, &group));
+    }
+    Ok(())
+}
+
+/// Answers the request of a client.
+fn serve_client(mut stream: UnixStream, group: &str) {
+    // clients can't hold a thread forever
+    let timeouts = stream
+        .set_read_timeout(Some(REQUEST_TIMEOUT))
+        .and_then(|()| stream.set_write_timeout(Some(REQUEST_TIMEOUT)));
+    if timeouts.is_err() {
+        return;
+    }
+
+    let answer = peer_uid(&stream, group).and_then(|uid| {
+        let mut request = String::new();
+        BufReader::new((&stream).take(MAX_REQUEST)).read_line(&mut request)?;
+        let request = request.trim();
+        if request.starts_with("up ") || request.starts_with("down ") {
+            log(&format!("uid {uid}: {request}"));
+        }
+        handle(request)
+    });
+
+    let answer = match answer {
+        Ok(result) => format!("ok\n{result}"),
+        Err(e) => format!("error {e}\n"),
+    };
+    let _ = stream.write_all(answer.as_bytes());
+}
+
+/// User of the client, if allowed to use the helper.
+fn peer_uid(stream: &UnixStream, group: &str) -> io::Result<u32> {
+    let mut cred = libc::ucred {
+        pid: 0,
+        uid: 0,
+        gid: 0,
+    };
+    let mut len = mem::size_of::<libc::ucred>() as libc::socklen_t;
+    // SAFETY: `cred` is valid for `len` bytes
+    let result = unsafe {
+        libc::getsockopt(
+            stream.as_raw_fd(),
+            libc::SOL_SOCKET,
+            libc::SO_PEERCRED,
+            (&raw mut cred).cast(),
+            &mut len,
+        )
+    };
+    if result < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    // read each time, for new members to be allowed without restarting
+    let groups = fs::read_to_string("/etc/group")?;
+    let passwd = fs::read_to_string("/etc/passwd")?;
+    match authorized(cred.uid, cred.gid, group, &groups, &passwd) {
+        true => Ok(cred.uid),
+        false => {
+            log(&format!("uid {}: denied", cred.uid));
+            Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                format!("only root and the members of {group} may use the helper"),
+            ))
+        }
+    }
+}
+
+/// Logs `message` to the stderr of the helper, the journal when run by systemd.
+///
+/// Only connection changes and denied clients are logged, to audit who used the helper.
+fn log(message: &str) {
+    let _ = writeln!(io::stderr(), "{message}");
+}
+
+fn handle(request: &str) -> io::Result<String> {
+    let (command, name) = match request.split_once(' ') {
+        Some((command, name)) => (command, Some(name)),
+        None => (request, None),
+    };
+    if let Some(name) = name
+        && !is_valid_interface_name(name)
+    {
+        return Err(io::Error::other(format!("invalid name: {name}")));
+    }
+
+    match (command, name) {
+        ("list", None) => Ok(names().join("\n")),
+        ("status", None) => runtime::dump_all().map(|dump| redact_dump(&dump)),
+        ("config", Some(name)) => {
+            let config = WgConfig::from(fs::read_to_string(path(name)?)?.as_str());
+            Ok(format!(
+                "{}\n{}",
+                config.interface.pubkey().as_str(),
+                config.redacted()
+            ))
+        }
+        ("up", Some(name)) => file_backend(&Settings::load(), &path(name)?)
+            .up(name)
+            .map(|()| String::new()),
+        ("down", Some(name)) => file_backend(&Settings::load(), &path(name)?)
+            .down(name)
+            .map(|()| String::new()),
+        _ => Err(io::Error::other(format!("unknown request: {request}"))),
+    }
+}
+
+/// Names of the configurations of [CONFIG_DIR].
+fn names() -> Vec<String> {
+    let source = Source::Directory {
+        dir: PathBuf::from(CONFIG_DIR),
+        pattern: "*.conf".to_string(),
+    };
+    source
+        .files()
+        .into_iter()
+        .map(|file| file.name)
+        .filter(|name| is_valid_interface_name(name))
+        .collect()
+}
+
+/// The configuration file of the `name` connection, which must exist.
+fn path(name: &str) -> io::Result<PathBuf> {
+    let path = Path::new(CONFIG_DIR).join(format!("{name}.conf"));
+    match path.is_file() {
+        true => Ok(path),
+        false => Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("no {name} configuration"),
+        )),
+    }
+}
+
+/// `wg show all dump` without the private keys of the interfaces nor the preshared keys of the
+/// peers.
+fn redact_dump(dump: &str) -> String {
+    dump.lines()
+        .map(|line| {
+            let mut fields: Vec<_> = line.split('\t').collect();
+            // interfaces have a name, 2 keys, a port and a fwmark
+            let secret = if fields.len() == 5 { 1 } else { 2 };
+            if let Some(field) = fields.get_mut(secret)
+                && *field != "(none)"
+            {
+                *field = HIDDEN;
+            }
+            fields.join("\t") + "\n"
+        })
+        .collect()
+}
+
+/// Tells whether the user `uid`, whose primary group is `gid`, may use the helper: root and the
+/// members of `group`, as listed by the `groups` and `passwd` files.
+fn authorized(uid: u32, gid: u32, group: &str, groups: &str, passwd: &str) -> bool {
+    if uid == 0 {
+        return true;
+    }
+    let Some(fields) = entries(groups).find(|fields| fields[0] == group) else {
+        return false;
+    };
+    if fields.get(2).and_then(|g| g.parse().ok()) == Some(gid) {
+        return true;
+    }
+
+    let user = entries(passwd)
+        .find(|fields| fields.get(2).and_then(|u| u.parse().ok()) == Some(uid))
+        .map(|fields| fields[0]);
+    user.is_some_and(|user| {
+        fields
+            .get(3)
+            .is_some_and(|members| members.split(',').any(|m| m == user))
+    })
+}
+
+/// The id of `group`.
+fn group_id(groups: &str, group: &str) -> Option<u32> {
+    entries(groups)
+        .find(|fields| fields[0] == group)
+        .and_then(|fields| fields.get(2)?.parse().ok())
+}
+
+/// The colon separated fields of the lines of `/etc/group` or `/etc/passwd`.
+fn entries(text: &str) -> impl Iterator<Item = Vec<&str>> {
+    text.lines()
+        .filter(|line| !line.starts_with('#'))
+        .map(|line| line.split(':').collect())
+}
+
+/// The socket passed by systemd, see `sd_listen_fds(3)`.
+fn activated() -> Option<UnixListener> {
+    let pid: u32 = env::var("LISTEN_PID").ok()?.parse().ok()?;
+    let fds: u32 = env::var("LISTEN_FDS").ok()?.parse().ok()?;
+    if pid != process::id() || fds < 1 {
+        return None;
+    }
+    // SAFETY: systemd passes its sockets from descriptor 3, nothing else owns it
+    unsafe {
+        // not inherited by wg-quick
+        libc::fcntl(3, libc::F_SETFD, libc::FD_CLOEXEC);
+        Some(UnixListener::from_raw_fd(3))
+    }
+}
+
+/// Creates [SOCKET], only usable by root and the group `gid`.
+fn bind(gid: u32) -> io::Result<UnixListener> {
+    // left by a previous helper
+    let _ = fs::remove_file(SOCKET);
+    let listener = UnixListener::bind(SOCKET)?;
+    fs::set_permissions(SOCKET, Permissions::from_mode(0o660))?;
+
+    let path = CString::new(SOCKET).expect("no nul in the socket path");
+    // SAFETY: the path is a valid nul terminated string
+    if unsafe { libc::chown(path.as_ptr(), 0, gid) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(listener)
+}
+
+/// The result of an answer of the helper, see the [module](self) documentation.
+fn parse_answer(answer: &str) -> io::Result<String> {
+    if let Some(result) = answer.strip_prefix("ok\n") {
+        return Ok(result.to_string());
+    }
+    Err(io::Error::other(match answer.strip_prefix("error ") {
+        Some(error) => error.trim_end().to_string(),
+        None => "invalid answer from the helper".to_string(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GROUPS: &str = "root:x:0:
+wheel:x:10:alice
+wtui:x:980:bob,carol
+";
+    const PASSWD: &str = "root:x:0:0:root:/root:/bin/sh
+alice:x:1000:1000::/home/alice:/bin/sh
+bob:x:1001:1001::/home/bob:/bin/sh
+dave:x:1003:980::/home/dave:/bin/sh
+";
+
+    #[test]
+    fn authorization() {
+        assert!(authorized(0, 0, "wtui", GROUPS, PASSWD));
+        // member, or primary group
+        assert!(authorized(1001, 1001, "wtui", GROUPS, PASSWD));
+        assert!(authorized(1003, 980, "wtui", GROUPS, PASSWD));
+        assert!(!authorized(1000, 1000, "wtui", GROUPS, PASSWD));
+        assert!(authorized(1000, 1000, "wheel", GROUPS, PASSWD));
+        assert!(!authorized(1000, 1000, "missing", GROUPS, PASSWD));
+        assert_eq!(group_id(GROUPS, "wtui"), Some(980));
+    }
+
+    #[test]
+    fn requests() {
+        assert!(is_valid_interface_name("wg0"));
+        assert!(is_valid_interface_name("office-paris"));
+        assert!(!is_valid_interface_name("../shadow"));
+        assert!(!is_valid_interface_name(".."));
+        assert!(!is_valid_interface_name("a-name-far-too-long"));
+        assert!(!is_valid_interface_name(""));
+        assert!(handle("list extra").is_err());
+        assert!(handle("config ../../etc/shadow").is_err());
+        assert!(handle("reboot").is_err());
+
+        assert_eq!(parse_answer("ok\nwg0\nwg1").unwrap(), "wg0\nwg1");
+        assert_eq!(parse_answer("ok\n").unwrap(), "");
+        assert_eq!(
+            parse_answer("error no wg2 configuration\n")
+                .unwrap_err()
+                .to_string(),
+            "no wg2 configuration"
+        );
+        assert!(parse_answer("").is_err());
+    }
+
+    #[test]
+    fn redaction() {
+        let dump = "wg0\toMVUWFwDf+20fIfeRUe7c0rlUKSYnHk2K0y2920SX1c=\tnd3XDfjWvMtcUBQLt6ANT4dzKsGJhb2hrCsVA6SHJVQ=\t51820\toff
+wg0\t60TUAvOo+Wi4SCyir581cCyBx4wIcHtrIrUgBv/iqRM=\tFpCyhws9cxwWoV4xELtfJvjJN+zQVRPISllRWgeopVE=\t203.0.113.1:51820\t10.1.0.0/16\t1700000000\t1024\t2048\toff
+wg1\tcAbfQ4o7R4wMnTgcQGgXkYEgO6gGLCMUJ+DY0fmr+1c=\t5V6VEKBPvXSIoD9bhkGSR6tXbFxJ8hKfaIyn1YbjNGU=\t40123\toff
+wg1\t60TUAvOo+Wi4SCyir581cCyBx4wIcHtrIrUgBv/iqRM=\t(none)\t(none)\t10.2.0.0/16\t0\t0\t0\toff
+";
+        let dumps = runtime::split_dumps(&redact_dump(dump));
+        assert_eq!(
+            dumps["wg0"],
+            "(hidden)\tnd3XDfjWvMtcUBQLt6ANT4dzKsGJhb2hrCsVA6SHJVQ=\t51820\toff
+60TUAvOo+Wi4SCyir581cCyBx4wIcHtrIrUgBv/iqRM=\t(hidden)\t203.0.113.1:51820\t10.1.0.0/16\t1700000000\t1024\t2048\toff
+"
+        );
+        assert_eq!(
+            dumps["wg1"],
+            "(hidden)\t5V6VEKBPvXSIoD9bhkGSR6tXbFxJ8hKfaIyn1YbjNGU=\t40123\toff
+60TUAvOo+Wi4SCyir581cCyBx4wIcHtrIrUgBv/iqRM=\t(none)\t(none)\t10.2.0.0/16\t0\t0\t0\toff
+"
+        );
+    }
+}
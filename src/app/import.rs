@@ -0,0 +1,152 @@
+//! Import of a configuration from a file, the clipboard or a paste.
+use std::{
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    os::unix::fs::OpenOptionsExt,
+    path::Path,
+};
+
+use ratatui::{prelude::*, widgets::*};
+
+use super::{input::Input, popup, theme::Theme};
+use crate::wg::WgConfig;
+
+/// A configuration being imported.
+pub struct Import {
+    /// The imported text, saved as is to keep keys unknown to wtui.
+    text: String,
+    config: WgConfig,
+    problems: Vec<String>,
+    /// The interface name to save the configuration under.
+    pub name: Input,
+    /// Error of the latest save attempt.
+    error: Option<String>,
+}
+
+impl Import {
+    /// Parses the imported text.
+    ///
+    /// If the text is the path of a readable file, the content of this file is imported instead.
+    pub fn new(text: &str) -> Self {
+        let path = Path::new(text.trim());
+        let (text, name) = match fs::read_to_string(path) {
+            Ok(content) if path.is_file() => (
+                content,
+                path.file_stem()
+                    .map(|s| s.to_string_lossy().to_string())
+                    .unwrap_or_default(),
+            ),
+            _ => (text.to_string(), String::new()),
+        };
+
+        let config = WgConfig::from(text.as_str());
+
+        Self {
+            problems: config.validate(),
+            text,
+            config,
+            name: Input::from(name.as_str()),
+            error: None,
+        }
+    }
+
+    /// Writes the configuration as `<name>.conf` in `dir`.
+    ///
+    /// The file is only readable by its owner and is never overwritten.
+    pub fn save(&mut self, dir: &Path) -> io::Result<()> {
+        let name = self.name.value();
+
+        if !self.problems.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "the configuration is invalid",
+            ));
+        }
+        if !is_valid_interface_name(name) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "name must be 1 to 15 chars among a-z, A-Z, 0-9 and _=+.-",
+            ));
+        }
+
+        OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(0o600)
+            .open(dir.join(format!("{name}.conf")))?
+            .write_all(self.text.as_bytes())
+    }
+
+    pub fn set_error(&mut self, error: String) {
+        self.error = Some(error);
+    }
+
+    /// The text saved, as written in the file.
+    pub fn into_text(self) -> String {
+        self.text
+    }
+}
+
+/// Tells whether `name` can be used as an interface name by `wg-quick`.
+///
+/// Such a name is also a file name which can't escape its directory.
+pub fn is_valid_interface_name(name: &str) -> bool {
+    (1..=15).contains(&name.len())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "_=+.-".contains(c))
+        && name != "."
+        && name != ".."
+}
+
+impl Import {
+    pub fn render(&self, area: Rect, buf: &mut Buffer, theme: &Theme) {
+        let mut lines: Vec<Line> = self
+            .config
+            .redacted()
+            .lines()
+            .map(|l| Line::from(l.to_string()).dim())
+            .collect();
+
+        lines.push(Line::default());
+        if self.problems.is_empty() {
+            lines.push(Line::styled("Configuration is valid", theme.success));
+        }
+        for problem in &self.problems {
+            lines.push(Line::styled(problem.as_str(), theme.error));
+        }
+
+        lines.push(Line::default());
+        let mut name = Line::from(&self.name);
+        name.spans.insert(0, "Interface name: ".bold());
+        lines.push(name);
+        if let Some(error) = &self.error {
+            lines.push(Line::styled(error.as_str(), theme.error));
+        }
+
+        lines.push(Line::default());
+        lines.push(Line::from("Enter: save, Esc: cancel").italic());
+
+        let area = popup::centered(area, 70, lines.len() as u16 + 2);
+        Clear.render(area, buf);
+        Paragraph::new(lines)
+            .block(popup::block("Import"))
+            .render(area, buf);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interface_name() {
+        assert!(is_valid_interface_name("wg0"));
+        assert!(is_valid_interface_name("office_vpn-2"));
+        assert!(!is_valid_interface_name(""));
+        assert!(!is_valid_interface_name("a_very_long_name"));
+        assert!(!is_valid_interface_name("../wg0"));
+        assert!(!is_valid_interface_name("wg 0"));
+        assert!(!is_valid_interface_name(".."));
+    }
+}
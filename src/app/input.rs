@@ -0,0 +1,50 @@
+//! A single line text input.
+use ratatui::prelude::*;
+
+/// A single line text input.
+///
+/// The cursor is always at the end of the text.
+#[derive(Default)]
+pub struct Input {
+    value: String,
+}
+
+impl Input {
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    pub fn push(&mut self, c: char) {
+        self.value.push(c);
+    }
+
+    /// Appends the first line of `text`, ignoring control chars.
+    pub fn push_str(&mut self, text: &str) {
+        self.value.extend(
+            text.lines()
+                .next()
+                .unwrap_or_default()
+                .chars()
+                .filter(|c| !c.is_control()),
+        );
+    }
+
+    /// Removes the last char.
+    pub fn pop(&mut self) {
+        self.value.pop();
+    }
+}
+
+impl From<&str> for Input {
+    fn from(value: &str) -> Self {
+        Self {
+            value: value.to_string(),
+        }
+    }
+}
+
+impl From<&Input> for Line<'_> {
+    fn from(input: &Input) -> Self {
+        Line::from(vec![input.value.clone().into(), Span::from(" ").reversed()])
+    }
+}
@@ -0,0 +1,50 @@
+//! Log of what wtui did by itself, like restarting tunnels.
+use std::collections::VecDeque;
+
+use chrono::{DateTime, Local};
+use ratatui::{prelude::*, widgets::*};
+
+use super::popup;
+
+/// Entries kept, older ones are dropped.
+const CAPACITY: usize = 200;
+
+#[derive(Default)]
+pub struct Log {
+    entries: VecDeque<(DateTime<Local>, String)>,
+}
+
+impl Log {
+    pub fn push(&mut self, message: String) {
+        if self.entries.len() == CAPACITY {
+            self.entries.pop_front();
+        }
+        self.entries.push_back((Local::now(), message));
+    }
+
+    /// Renders the latest entries, the most recent at the bottom.
+    pub fn render(&self, area: Rect, buf: &mut Buffer) {
+        let area = popup::centered(area, 100, CAPACITY as u16);
+        let shown = area.height.saturating_sub(2) as usize;
+
+        let lines: Vec<Line> = match self.entries.is_empty() {
+            true => vec![Line::from("Nothing happened yet").italic()],
+            false => self
+                .entries
+                .iter()
+                .skip(self.entries.len().saturating_sub(shown))
+                .map(|(time, message)| {
+                    Line::from(vec![
+                        time.format("%H:%M:%S ").to_string().dim(),
+                        message.clone().into(),
+                    ])
+                })
+                .collect(),
+        };
+
+        Clear.render(area, buf);
+        Paragraph::new(lines)
+            .block(popup::block("Log"))
+            .render(area, buf);
+    }
+}
@@ -0,0 +1,121 @@
+//! Context menu of a connection, opened with a right click.
+use ratatui::{prelude::*, widgets::*};
+
+use super::popup;
+
+/// Actions available from the context menu.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MenuAction {
+    Connect,
+    Disconnect,
+    Yank,
+    QrCode,
+}
+
+impl MenuAction {
+    pub const ALL: [MenuAction; 4] = [
+        MenuAction::Connect,
+        MenuAction::Disconnect,
+        MenuAction::Yank,
+        MenuAction::QrCode,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            MenuAction::Connect => "Connect",
+            MenuAction::Disconnect => "Disconnect",
+            MenuAction::Yank => "Yank…",
+            MenuAction::QrCode => "QR code",
+        }
+    }
+}
+
+/// A context menu opened at a given position.
+pub struct Menu {
+    x: u16,
+    y: u16,
+    /// Position of the hovered action in [`MenuAction::ALL`].
+    pub cursor: usize,
+}
+
+impl Menu {
+    const WIDTH: u16 = 16;
+
+    pub fn new(x: u16, y: u16) -> Self {
+        Self { x, y, cursor: 0 }
+    }
+
+    pub fn selected(&self) -> MenuAction {
+        MenuAction::ALL[self.cursor]
+    }
+
+    /// Moves the cursor by `offset`, wrapping around.
+    pub fn move_cursor(&mut self, offset: isize) {
+        self.cursor =
+            (self.cursor as isize + offset).rem_euclid(MenuAction::ALL.len() as isize) as usize;
+    }
+
+    /// Area of the menu, kept inside `screen`.
+    pub fn area(&self, screen: Rect) -> Rect {
+        let width = Self::WIDTH.min(screen.width);
+        let height = (MenuAction::ALL.len() as u16 + 2).min(screen.height);
+
+        Rect::new(
+            self.x.min(screen.right().saturating_sub(width)),
+            self.y.min(screen.bottom().saturating_sub(height)),
+            width,
+            height,
+        )
+    }
+
+    /// The action at the given screen position, if any.
+    pub fn action_at(&self, screen: Rect, column: u16, row: u16) -> Option<MenuAction> {
+        let inner = self.area(screen).inner(Margin::new(1, 1));
+        inner
+            .contains(Position::new(column, row))
+            .then(|| MenuAction::ALL.get((row - inner.y) as usize).copied())
+            .flatten()
+    }
+
+    pub fn render(&self, screen: Rect, buf: &mut Buffer) {
+        let lines: Vec<Line> = MenuAction::ALL
+            .into_iter()
+            .enumerate()
+            .map(|(i, action)| match i == self.cursor {
+                true => Line::from(action.label()).reversed(),
+                false => Line::from(action.label()),
+            })
+            .collect();
+
+        let area = self.area(screen);
+        Clear.render(area, buf);
+        Paragraph::new(lines)
+            .block(popup::block("Actions"))
+            .render(area, buf);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn kept_inside_screen() {
+        let screen = Rect::new(0, 0, 80, 24);
+
+        assert_eq!(Menu::new(10, 5).area(screen), Rect::new(10, 5, 16, 6));
+        assert_eq!(Menu::new(79, 23).area(screen), Rect::new(64, 18, 16, 6));
+    }
+
+    #[test]
+    fn action_at() {
+        let screen = Rect::new(0, 0, 80, 24);
+        let menu = Menu::new(10, 5);
+
+        // border
+        assert_eq!(menu.action_at(screen, 11, 5), None);
+        assert_eq!(menu.action_at(screen, 11, 6), Some(MenuAction::Connect));
+        assert_eq!(menu.action_at(screen, 11, 9), Some(MenuAction::QrCode));
+        assert_eq!(menu.action_at(screen, 40, 6), None);
+    }
+}
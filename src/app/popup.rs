@@ -0,0 +1,25 @@
+//! Helpers to draw popups over the main view.
+use ratatui::{
+    layout::Flex,
+    prelude::*,
+    widgets::{block::Title, *},
+};
+
+/// Gets an area of at most `width` x `height`, centered in `area`.
+pub fn centered(area: Rect, width: u16, height: u16) -> Rect {
+    let [area] = Layout::horizontal([Constraint::Max(width)])
+        .flex(Flex::Center)
+        .areas(area);
+    let [area] = Layout::vertical([Constraint::Max(height)])
+        .flex(Flex::Center)
+        .areas(area);
+    area
+}
+
+/// The border of a popup.
+pub fn block(title: &str) -> Block<'_> {
+    Block::bordered()
+        .border_type(BorderType::Rounded)
+        .title(Title::from(format!(" {title} ")))
+        .title_alignment(Alignment::Center)
+}
@@ -0,0 +1,211 @@
+//! Routing problems across all the configurations.
+use std::{
+    cmp,
+    collections::BTreeMap,
+    net::IpAddr,
+    process::{Command, Stdio},
+};
+
+use ratatui::{prelude::*, widgets::*};
+
+use super::{popup, theme::Theme};
+use crate::wg::{
+    WgConfig,
+    types::{Cidr, Endpoint},
+};
+
+/// Finds problems in the configurations and in the routes they add.
+///
+/// Returns a human readable description of every problem found.
+///
+/// `lan` are the networks of the other interfaces of the host, see [`lan_networks`].
+pub fn analyze(configs: &[(&str, &WgConfig)], lan: &[Cidr]) -> Vec<String> {
+    let mut problems = Vec::new();
+
+    for &(name, config) in configs {
+        for problem in config.validate() {
+            problems.push(format!("{name}: {problem}"));
+        }
+    }
+
+    // without routes, AllowedIPs only filter the traffic
+    let routed: Vec<_> = configs
+        .iter()
+        .filter(|(_, config)| config.interface.adds_routes())
+        .collect();
+
+    for (i, &&(name, config)) in routed.iter().enumerate() {
+        for &&(other, other_config) in &routed[i + 1..] {
+            // the narrower of each overlapping pair
+            let shared: Vec<_> = config
+                .peer
+                .allowed_ips()
+                .iter()
+                .flat_map(|a| {
+                    other_config
+                        .peer
+                        .allowed_ips()
+                        .iter()
+                        .filter(move |b| a.overlaps(b))
+                        .map(move |b| *cmp::max_by_key(a, b, |c| c.prefix()))
+                })
+                .collect();
+            if !shared.is_empty() {
+                problems.push(format!(
+                    "{name} and {other} both route {}",
+                    Cidr::join(&shared, ", ")
+                ));
+            }
+        }
+
+        for ip in config.peer.allowed_ips() {
+            // wg-quick keeps the local routes in front of the default route
+            if ip.prefix() == 0 {
+                continue;
+            }
+
+            for network in lan
+                .iter()
+                .filter(|n| ip.overlaps(n) && ip.prefix() >= n.prefix())
+            {
+                problems.push(format!(
+                    "{name} routes {ip}, hiding the local network {network}"
+                ));
+            }
+
+            if let Some(endpoint) = config.peer.endpoint().and_then(Endpoint::ip)
+                && ip.contains(endpoint)
+                && config.interface.fwmark().is_none()
+            {
+                problems.push(format!(
+                    "{name} routes its own endpoint {endpoint} through the tunnel, set a FwMark"
+                ));
+            }
+        }
+    }
+
+    let mut addresses: BTreeMap<IpAddr, Vec<String>> = BTreeMap::new();
+    for &(name, config) in configs {
+        for address in config.interface.address() {
+            addresses
+                .entry(address.addr())
+                .or_default()
+                .push(name.to_string());
+        }
+    }
+    for (address, names) in addresses.into_iter().filter(|(_, n)| n.len() > 1) {
+        problems.push(format!("{} share the address {address}", names.join(", ")));
+    }
+
+    problems
+}
+
+/// Networks of the host interfaces, except the loopback and the `excluded` ones.
+pub fn lan_networks(excluded: &[&str]) -> Vec<Cidr> {
+    let Ok(output) = Command::new("ip")
+        .args(["-o", "address", "show"])
+        .stderr(Stdio::null())
+        .output()
+    else {
+        return Vec::new();
+    };
+
+    parse_addresses(&String::from_utf8_lossy(&output.stdout))
+        .filter(|(interface, _)| *interface != "lo" && !excluded.contains(interface))
+        .map(|(_, network)| network)
+        .collect()
+}
+
+/// Parses `ip -o address show` lines, e.g. `2: eth0    inet 192.168.1.23/24 brd ...`.
+fn parse_addresses(output: &str) -> impl Iterator<Item = (&str, Cidr)> {
+    output.lines().filter_map(|line| {
+        let mut words = line.split_whitespace().skip(1);
+        let interface = words.next()?;
+        let _family = words.next()?;
+        let network = words.next()?.parse::<Cidr>().ok()?.network();
+        Some((interface, network))
+    })
+}
+
+/// The problems panel.
+pub struct Problems(pub Vec<String>);
+
+impl Problems {
+    pub fn render(&self, area: Rect, buf: &mut Buffer, theme: &Theme) {
+        let mut lines: Vec<Line> = match self.0.is_empty() {
+            true => vec![Line::styled("No problem found", theme.success)],
+            false => self
+                .0
+                .iter()
+                .map(|p| Line::styled(p.as_str(), theme.error))
+                .collect(),
+        };
+
+        lines.push(Line::default());
+        lines.push(Line::from("Esc: close").italic());
+
+        let area = popup::centered(area, 80, lines.len() as u16 + 2);
+        Clear.render(area, buf);
+        Paragraph::new(lines)
+            .wrap(Wrap { trim: false })
+            .block(popup::block("Problems"))
+            .render(area, buf);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(address: &str, allowed_ips: &str, endpoint: &str) -> WgConfig {
+        WgConfig::from(
+            format!(
+                r#"[Interface]
+PrivateKey = oMVUWFwDf+20fIfeRUe7c0rlUKSYnHk2K0y2920SX1c=
+Address = {address}
+
+[Peer]
+PublicKey = 60TUAvOo+Wi4SCyir581cCyBx4wIcHtrIrUgBv/iqRM=
+AllowedIPs = {allowed_ips}
+Endpoint = {endpoint}"#
+            )
+            .as_str(),
+        )
+    }
+
+    #[test]
+    fn analyze_configs() {
+        let home = config("10.0.0.2/32", "0.0.0.0/0", "vpn.example.com:51820");
+        let work = config("10.0.0.2/32", "0.0.0.0/0", "203.0.113.5:51820");
+        let lab = config(
+            "10.1.0.2/32",
+            "192.168.1.0/24, 203.0.113.0/24",
+            "203.0.113.5:51820",
+        );
+        let lan = ["192.168.1.0/24".parse().unwrap()];
+
+        assert_eq!(
+            analyze(&[("home", &home), ("work", &work), ("lab", &lab)], &lan),
+            vec![
+                "home and work both route 0.0.0.0/0",
+                "home and lab both route 192.168.1.0/24, 203.0.113.0/24",
+                "work and lab both route 192.168.1.0/24, 203.0.113.0/24",
+                "lab routes 192.168.1.0/24, hiding the local network 192.168.1.0/24",
+                "lab routes its own endpoint 203.0.113.5 through the tunnel, set a FwMark",
+                "home, work share the address 10.0.0.2",
+            ]
+        );
+    }
+
+    #[test]
+    fn addresses() {
+        let output = "1: lo    inet 127.0.0.1/8 scope host lo\\       valid_lft forever
+2: wlan0    inet 192.168.1.23/24 brd 192.168.1.255 scope global dynamic wlan0\\       valid_lft 8000sec
+2: wlan0    inet6 fe80::1/64 scope link \\       valid_lft forever";
+
+        let addresses: Vec<_> = parse_addresses(output).collect();
+
+        assert_eq!(addresses.len(), 3);
+        assert_eq!(addresses[1], ("wlan0", "192.168.1.0/24".parse().unwrap()));
+    }
+}
@@ -0,0 +1,136 @@
+//! QR code of a configuration, meant to be scanned by the wireguard mobile apps.
+use qrcode::{EcLevel, QrCode, types::QrError};
+use ratatui::{prelude::*, widgets::*};
+
+use super::{popup, theme::Theme};
+
+/// Light modules around the code, required by scanners.
+const QUIET_ZONE: usize = 2;
+
+/// A QR code popup.
+pub struct Qr {
+    title: String,
+    code: QrCode,
+    /// Shown next to the code: the encoded configuration without its keys.
+    text: String,
+}
+
+impl Qr {
+    /// The code of the raw configuration `config`, described by `text`.
+    pub fn new(title: &str, config: &str, text: String) -> Result<Self, QrError> {
+        Ok(Self {
+            title: title.to_string(),
+            code: QrCode::with_error_correction_level(config, EcLevel::L)?,
+            text,
+        })
+    }
+
+    /// Width of the code in modules, quiet zone included.
+    fn size(&self) -> usize {
+        self.code.width() + 2 * QUIET_ZONE
+    }
+
+    /// Whether the module at `(x, y)` is dark, `(0, 0)` being the corner of the quiet zone.
+    fn is_dark(&self, x: usize, y: usize) -> bool {
+        let (Some(x), Some(y)) = (x.checked_sub(QUIET_ZONE), y.checked_sub(QUIET_ZONE)) else {
+            return false;
+        };
+        x < self.code.width() && y < self.code.width() && self.code[(x, y)] == qrcode::Color::Dark
+    }
+
+    /// Renders the code using half blocks, each module being `scale` cells wide and `scale` half
+    /// cells high.
+    fn render_code(&self, area: Rect, buf: &mut Buffer, scale: usize) {
+        let style = Style::new().fg(Color::Black).bg(Color::White);
+
+        for row in 0..(self.size() * scale).div_ceil(2) {
+            for col in 0..self.size() * scale {
+                let top = self.is_dark(col / scale, 2 * row / scale);
+                let bottom = self.is_dark(col / scale, (2 * row + 1) / scale);
+                let symbol = match (top, bottom) {
+                    (true, true) => "█",
+                    (true, false) => "▀",
+                    (false, true) => "▄",
+                    (false, false) => " ",
+                };
+
+                if let Some(cell) = buf.cell_mut((area.x + col as u16, area.y + row as u16)) {
+                    cell.set_symbol(symbol).set_style(style);
+                }
+            }
+        }
+    }
+}
+
+impl Qr {
+    pub fn render(&self, area: Rect, buf: &mut Buffer, theme: &Theme) {
+        let area = area.inner(Margin::new(2, 1));
+        Clear.render(area, buf);
+
+        let block = popup::block(&self.title)
+            .title_bottom(Line::from(" p: new peer  Esc: close ").centered());
+        let inner = block.inner(area);
+        block.render(area, buf);
+
+        let size = self.size();
+        let scale = (1..=4).rev().find(|scale| {
+            size * scale <= inner.width as usize
+                && (size * scale).div_ceil(2) <= inner.height as usize
+        });
+
+        let Some(scale) = scale else {
+            Paragraph::new(format!(
+                "The terminal is too small to show the QR code, it needs at least {}x{}.",
+                size + 2,
+                size.div_ceil(2) + 2,
+            ))
+            .wrap(Wrap { trim: true })
+            .style(theme.error)
+            .render(inner, buf);
+            return;
+        };
+
+        let width = (size * scale) as u16;
+        let [code, text] = Layout::horizontal([Constraint::Length(width), Constraint::Fill(1)])
+            .spacing(2)
+            .areas(inner);
+
+        self.render_code(code, buf, scale);
+        Paragraph::new(self.text.as_str()).dim().render(text, buf);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn qr() -> Qr {
+        Qr {
+            title: "wg0".to_string(),
+            code: QrCode::new("wtui").expect("short data always fits"),
+            text: String::new(),
+        }
+    }
+
+    #[test]
+    fn quiet_zone_is_light() {
+        let qr = qr();
+
+        assert!(!qr.is_dark(0, 0));
+        assert!(!qr.is_dark(QUIET_ZONE - 1, QUIET_ZONE));
+        // top left corner of the finder pattern
+        assert!(qr.is_dark(QUIET_ZONE, QUIET_ZONE));
+        assert!(!qr.is_dark(qr.size() - 1, qr.size() - 1));
+    }
+
+    #[test]
+    fn too_small() {
+        let area = Rect::new(0, 0, 20, 10);
+        let mut buf = Buffer::empty(area);
+
+        qr().render(area, &mut buf, &Theme::default());
+
+        let content: String = buf.content().iter().map(|c| c.symbol()).collect();
+        assert!(content.contains("too small"));
+    }
+}
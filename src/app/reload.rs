@@ -0,0 +1,228 @@
+//! Watches the configuration directories with inotify, to reload the files changed on disk.
+use std::{
+    collections::{HashMap, HashSet},
+    ffi::{CString, OsStr},
+    io,
+    os::{
+        fd::{AsRawFd, FromRawFd, OwnedFd},
+        unix::ffi::OsStrExt,
+    },
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver},
+    thread,
+    time::Duration,
+};
+
+/// Time for a burst of changes to end, e.g. an editor replacing a file.
+const SETTLE: Duration = Duration::from_millis(300);
+
+/// Changes worth a reload: files added, removed, renamed, written or with new permissions.
+const EVENTS: u32 = libc::IN_CREATE
+    | libc::IN_DELETE
+    | libc::IN_MOVED_FROM
+    | libc::IN_MOVED_TO
+    | libc::IN_CLOSE_WRITE
+    | libc::IN_ATTRIB;
+
+/// Sends the paths changed in the `dirs`, once per burst of changes.
+///
+/// Missing directories are watched through their nearest existing ancestor: their path is sent
+/// when they are created, then their files are watched. Watching stops with the next change after
+/// the receiver is dropped.
+pub fn watch(dirs: &[PathBuf]) -> io::Result<Receiver<Vec<PathBuf>>> {
+    // SAFETY: plain syscall, the descriptor is owned right after
+    let fd = unsafe { libc::inotify_init1(libc::IN_CLOEXEC) };
+    if fd < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let mut watcher = Watcher {
+        // SAFETY: `fd` is a freshly opened descriptor nobody else owns
+        fd: unsafe { OwnedFd::from_raw_fd(fd) },
+        dirs: dirs.to_vec(),
+        watched: HashMap::new(),
+        ancestors: HashSet::new(),
+    };
+    watcher.add_watches();
+
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        loop {
+            let mut changed = Vec::new();
+            if !watcher.read_events(&mut changed) {
+                return;
+            }
+            while ready(&watcher.fd, SETTLE) {
+                if !watcher.read_events(&mut changed) {
+                    return;
+                }
+            }
+            watcher.add_watches();
+            if sender.send(changed).is_err() {
+                return;
+            }
+        }
+    });
+
+    Ok(receiver)
+}
+
+struct Watcher {
+    fd: OwnedFd,
+    /// The directories to watch.
+    dirs: Vec<PathBuf>,
+    /// Watched directory of each watch descriptor.
+    watched: HashMap<i32, PathBuf>,
+    /// Watches of ancestors waiting for missing directories to be created.
+    ancestors: HashSet<i32>,
+}
+
+impl Watcher {
+    /// Watches the directories which exist, and the nearest existing ancestor of the others.
+    fn add_watches(&mut self) {
+        let mut ancestors = HashSet::new();
+        let mut wanted = HashSet::new();
+        for dir in self.dirs.clone() {
+            if let Some(wd) = self.add_watch(&dir, EVENTS) {
+                wanted.insert(wd);
+                continue;
+            }
+            if let Some(wd) = dir
+                .ancestors()
+                .skip(1)
+                .filter(|ancestor| !ancestor.as_os_str().is_empty())
+                .find_map(|ancestor| self.add_watch(ancestor, libc::IN_CREATE | libc::IN_MOVED_TO))
+            {
+                ancestors.insert(wd);
+            }
+        }
+
+        // ancestors of directories created since
+        for wd in self.ancestors.difference(&ancestors) {
+            if !wanted.contains(wd) {
+                // SAFETY: plain syscall on our descriptor
+                unsafe { libc::inotify_rm_watch(self.fd.as_raw_fd(), *wd) };
+                self.watched.remove(wd);
+            }
+        }
+        self.ancestors = ancestors;
+    }
+
+    /// Adds `mask` to the events watched in `dir`, [None] if it can't be watched, e.g. missing.
+    fn add_watch(&mut self, dir: &Path, mask: u32) -> Option<i32> {
+        let path = CString::new(dir.as_os_str().as_bytes()).ok()?;
+        // SAFETY: the path is a valid nul terminated string
+        let wd = unsafe {
+            libc::inotify_add_watch(
+                self.fd.as_raw_fd(),
+                path.as_ptr(),
+                mask | libc::IN_MASK_ADD | libc::IN_ONLYDIR,
+            )
+        };
+        (wd >= 0).then(|| {
+            self.watched.insert(wd, dir.to_path_buf());
+            wd
+        })
+    }
+
+    /// Waits for events and adds the paths they are about to `changed`, once each.
+    ///
+    /// `false` if the descriptor can't be read anymore.
+    fn read_events(&mut self, changed: &mut Vec<PathBuf>) -> bool {
+        let mut buf = [0u8; 4096];
+        // SAFETY: the buffer is valid for its length
+        let read = unsafe { libc::read(self.fd.as_raw_fd(), buf.as_mut_ptr().cast(), buf.len()) };
+        if read <= 0 {
+            return false;
+        }
+
+        for (wd, mask, name) in events(&buf[..read as usize]) {
+            let Some(dir) = self.watched.get(&wd) else {
+                continue;
+            };
+            let path = match name.is_empty() {
+                true => dir.clone(),
+                false => dir.join(OsStr::from_bytes(name)),
+            };
+            if !changed.contains(&path) {
+                changed.push(path);
+            }
+            // the directory is gone
+            if mask & libc::IN_IGNORED != 0 {
+                self.watched.remove(&wd);
+                self.ancestors.remove(&wd);
+            }
+        }
+        true
+    }
+}
+
+/// Splits the `struct inotify_event`s read into `(watch descriptor, mask, file name)`.
+fn events(mut buf: &[u8]) -> impl Iterator<Item = (i32, u32, &[u8])> {
+    const HEADER: usize = 16;
+    std::iter::from_fn(move || {
+        let header = buf.get(..HEADER)?;
+        let wd = i32::from_ne_bytes(header[..4].try_into().expect("4 bytes"));
+        let mask = u32::from_ne_bytes(header[4..8].try_into().expect("4 bytes"));
+        let len = u32::from_ne_bytes(header[12..16].try_into().expect("4 bytes")) as usize;
+        let name = buf.get(HEADER..HEADER + len)?;
+        buf = &buf[HEADER + len..];
+        // nul padded
+        let end = name.iter().position(|b| *b == 0).unwrap_or(name.len());
+        Some((wd, mask, &name[..end]))
+    })
+}
+
+/// Tells whether events arrive within `timeout`.
+fn ready(fd: &OwnedFd, timeout: Duration) -> bool {
+    let mut poll = libc::pollfd {
+        fd: fd.as_raw_fd(),
+        events: libc::POLLIN,
+        revents: 0,
+    };
+    // SAFETY: a single valid pollfd
+    unsafe { libc::poll(&mut poll, 1, timeout.as_millis() as i32) > 0 }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, process, slice};
+
+    use super::*;
+
+    #[test]
+    fn changes() {
+        let dir = env::temp_dir().join(format!("wtui-reload-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let receiver = watch(slice::from_ref(&dir)).unwrap();
+
+        fs::write(dir.join("wg0.conf"), "[Interface]").unwrap();
+        fs::write(dir.join("wg1.conf"), "[Interface]").unwrap();
+        let changed = receiver.recv_timeout(Duration::from_secs(5));
+        // both files in a single burst
+        let again = receiver.recv_timeout(SETTLE / 2);
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(
+            changed.unwrap(),
+            vec![dir.join("wg0.conf"), dir.join("wg1.conf")]
+        );
+        assert!(again.is_err());
+    }
+
+    #[test]
+    fn created_later() {
+        let root = env::temp_dir().join(format!("wtui-reload-later-{}", process::id()));
+        let dir = root.join("wireguard");
+        fs::create_dir_all(&root).unwrap();
+        let receiver = watch(slice::from_ref(&dir)).unwrap();
+
+        fs::create_dir(&dir).unwrap();
+        let created = receiver.recv_timeout(Duration::from_secs(5));
+        fs::write(dir.join("wg0.conf"), "[Interface]").unwrap();
+        let written = receiver.recv_timeout(Duration::from_secs(5));
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(created.unwrap(), vec![dir.clone()]);
+        assert_eq!(written.unwrap(), vec![dir.join("wg0.conf")]);
+    }
+}
@@ -0,0 +1,520 @@
+//! User settings of wtui.
+//!
+//! They are read from `$XDG_CONFIG_HOME/wtui/wtui.conf`, which uses the same format as wireguard
+//! configurations:
+//!
+//! ```ini
+//! [General]
+//! # auto, native, osc52 or tmux
+//! Clipboard = auto
+//! Mouse = true
+//! # always, ssh or never
+//! Confirm = always
+//! ExclusiveConnect = false
+//! # wg-quick or native
+//! Backend = wg-quick
+//! Systemd = true
+//! NetworkManager = true
+//!
+//! [Sources]
+//! # comma separated directories, globs on file names or single files
+//! Paths = /etc/wireguard/*.conf, ~/.config/wireguard/*.conf
+//!
+//! [Columns]
+//! Hidden = mtu, public-key
+//!
+//! [Theme mine]
+//! Base = light
+//! Connected = bold blue
+//!
+//! [Connection wg0]
+//! Autoconnect = true
+//! Watchdog = true
+//! WatchdogDelay = 30
+//! WatchdogRetries = 5
+//!
+//! [Connection office]
+//! # comma separated conditions on ssid, gateway or network, all matching
+//! ConnectWhen = not network 10.1.0.0/16
+//! DisconnectWhen = network 10.1.0.0/16
+//! ```
+use std::{
+    env,
+    fmt::{self, Display},
+    fs, io,
+    path::PathBuf,
+    time::Duration,
+};
+
+use super::{
+    autoconnect::{self, Rules},
+    backend::BackendKind,
+    clipboard::Clipboard,
+    column::Column,
+    confirm::ConfirmPolicy,
+    source::DEFAULT_SOURCES,
+    theme::{self, Theme},
+    watchdog::{self, WatchdogPolicy},
+};
+
+pub struct Settings {
+    pub clipboard: Clipboard,
+    /// Whether to capture the mouse, which prevents selecting text in the terminal.
+    pub mouse: bool,
+    /// When destructive actions must be confirmed.
+    pub confirm: ConfirmPolicy,
+    /// Whether connecting disconnects every other connection.
+    pub exclusive_connect: bool,
+    /// How connections are brought up and down.
+    pub backend: BackendKind,
+    /// Whether connections managed by a `wg-quick@` unit go through systemd.
+    pub systemd: bool,
+    /// Whether to list the WireGuard connections of NetworkManager.
+    pub network_manager: bool,
+    /// Where the configuration files are, see [Source](super::source::Source::new).
+    pub sources: Vec<String>,
+    /// Columns never shown in the table.
+    pub hidden_columns: Vec<Column>,
+    /// Name of the theme, built-in or user defined.
+    pub theme: String,
+    pub custom_themes: Vec<CustomTheme>,
+    pub connections: Vec<ConnectionSettings>,
+    /// Settings which were rejected, with why.
+    pub errors: Vec<String>,
+}
+
+/// Settings of a single connection.
+#[derive(Default)]
+pub struct ConnectionSettings {
+    pub name: String,
+    /// Whether to connect on startup.
+    pub autoconnect: bool,
+    /// When to connect and disconnect on network changes.
+    pub rules: Rules,
+    /// Whether to restart the connection when it dies by itself.
+    pub watchdog: bool,
+    pub watchdog_policy: WatchdogPolicy,
+}
+
+impl ConnectionSettings {
+    pub fn watchdog(&self) -> Option<WatchdogPolicy> {
+        self.watchdog.then_some(self.watchdog_policy)
+    }
+}
+
+/// A theme defined in the settings file.
+pub struct CustomTheme {
+    pub name: String,
+    /// Styles as written in the file, e.g. `("Connected", "bold blue")`.
+    ///
+    /// The `Base` key names the built-in theme to start from.
+    pub styles: Vec<(String, String)>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            clipboard: Clipboard::default(),
+            mouse: true,
+            confirm: ConfirmPolicy::default(),
+            exclusive_connect: false,
+            backend: BackendKind::default(),
+            systemd: true,
+            network_manager: true,
+            sources: DEFAULT_SOURCES.map(str::to_string).to_vec(),
+            hidden_columns: Vec::new(),
+            theme: "dark".to_string(),
+            custom_themes: Vec::new(),
+            connections: Vec::new(),
+            errors: Vec::new(),
+        }
+    }
+}
+
+impl Settings {
+    /// Path of the settings file.
+    ///
+    /// `$XDG_CONFIG_HOME/wtui/wtui.conf`, defaulting to `~/.config/wtui/wtui.conf`.
+    pub fn path() -> Option<PathBuf> {
+        env::var_os("XDG_CONFIG_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+            .map(|dir| dir.join("wtui").join("wtui.conf"))
+    }
+
+    /// Loads the settings file.
+    ///
+    /// Defaults are used if the file doesn't exist or can't be read.
+    pub fn load() -> Self {
+        Self::path()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .map(|content| Self::from(content.as_str()))
+            .unwrap_or_default()
+    }
+
+    /// Settings of the connection called `name`, if any.
+    pub fn connection(&self, name: &str) -> Option<&ConnectionSettings> {
+        self.connections.iter().find(|c| c.name == name)
+    }
+
+    /// Watchdog policy of the connection called `name`, [None] if it has no watchdog.
+    pub fn watchdog(&self, name: &str) -> Option<WatchdogPolicy> {
+        self.connection(name).and_then(ConnectionSettings::watchdog)
+    }
+
+    /// The theme to use.
+    ///
+    /// Colors are disabled if `NO_COLOR` is set, unknown themes fall back to the default one.
+    pub fn theme(&self) -> Theme {
+        if Theme::is_no_color() {
+            return Theme::no_color();
+        }
+
+        if let Some(custom) = self.custom_themes.iter().find(|t| t.name == self.theme) {
+            let base = custom
+                .styles
+                .iter()
+                .find(|(key, _)| key == "Base")
+                .and_then(|(_, base)| Theme::builtin(base))
+                .unwrap_or_default();
+
+            return custom.styles.iter().fold(base, |mut theme, (key, value)| {
+                theme.set(key, theme::parse_style(value));
+                theme
+            });
+        }
+
+        Theme::builtin(&self.theme).unwrap_or_default()
+    }
+
+    /// Writes the settings to the settings file.
+    ///
+    /// Comments and unknown keys of the file are lost.
+    pub fn save(&self) -> io::Result<()> {
+        let path = Self::path().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::NotFound, "no HOME nor XDG_CONFIG_HOME set")
+        })?;
+
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, self.to_string())
+    }
+}
+
+impl From<&str> for Settings {
+    fn from(content: &str) -> Self {
+        let mut settings = Settings::default();
+        let mut section = "";
+
+        for line in content.lines().map(str::trim) {
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                section = name.trim();
+                if let Some(name) = section.strip_prefix("Theme ") {
+                    settings.custom_themes.push(CustomTheme {
+                        name: name.trim().to_string(),
+                        styles: Vec::new(),
+                    });
+                }
+                if let Some(name) = section.strip_prefix("Connection ") {
+                    settings.connections.push(ConnectionSettings {
+                        name: name.trim().to_string(),
+                        ..Default::default()
+                    });
+                }
+            } else if let Some((key, value)) = line.split_once('=') {
+                let value = value.trim();
+                if section.starts_with("Theme ") {
+                    if let Some(theme) = settings.custom_themes.last_mut() {
+                        theme
+                            .styles
+                            .push((key.trim().to_string(), value.to_string()));
+                    }
+                    continue;
+                }
+                if section.starts_with("Connection ") {
+                    if let Some(connection) = settings.connections.last_mut()
+                        && let Err(e) = connection.set(key.trim(), value)
+                    {
+                        settings
+                            .errors
+                            .push(format!("[{section}] {}: {e}", key.trim()));
+                    }
+                    continue;
+                }
+
+                match (section, key.trim()) {
+                    ("General", "Clipboard") => {
+                        settings.clipboard = value.parse().unwrap_or_default()
+                    }
+                    ("General", "Mouse") => settings.mouse = value.parse().unwrap_or(true),
+                    ("General", "Confirm") => settings.confirm = value.parse().unwrap_or_default(),
+                    ("General", "ExclusiveConnect") => {
+                        settings.exclusive_connect = value.parse().unwrap_or_default()
+                    }
+                    ("General", "Backend") => settings.backend = value.parse().unwrap_or_default(),
+                    ("General", "Systemd") => settings.systemd = value.parse().unwrap_or(true),
+                    ("General", "NetworkManager") => {
+                        settings.network_manager = value.parse().unwrap_or(true)
+                    }
+                    ("General", "Theme") => settings.theme = value.to_string(),
+                    ("Sources", "Paths") => {
+                        settings.sources = value
+                            .split(',')
+                            .map(str::trim)
+                            .filter(|path| !path.is_empty())
+                            .map(str::to_string)
+                            .collect()
+                    }
+                    ("Columns", "Hidden") => {
+                        settings.hidden_columns =
+                            value.split(',').filter_map(|c| c.parse().ok()).collect()
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        settings
+    }
+}
+
+impl ConnectionSettings {
+    /// Sets the setting called `key`, ignoring unknown keys and invalid values.
+    ///
+    /// Rules with an invalid condition are rejected as a whole, they would match more than meant.
+    fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        let policy = &mut self.watchdog_policy;
+        match key {
+            "Autoconnect" => self.autoconnect = value.parse().unwrap_or_default(),
+            "ConnectWhen" => self.rules.connect_when = autoconnect::parse_conditions(value)?,
+            "DisconnectWhen" => self.rules.disconnect_when = autoconnect::parse_conditions(value)?,
+            "Watchdog" => self.watchdog = value.parse().unwrap_or_default(),
+            "WatchdogDelay" => {
+                if let Ok(secs) = value.parse()
+                    && Duration::from_secs(secs) <= watchdog::MAX_DELAY
+                {
+                    policy.delay = Duration::from_secs(secs)
+                }
+            }
+            "WatchdogRetries" => policy.retries = value.parse().unwrap_or(policy.retries),
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+impl Display for Settings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "[General]")?;
+        writeln!(f, "Clipboard = {}", self.clipboard)?;
+        writeln!(f, "Mouse = {}", self.mouse)?;
+        writeln!(f, "Confirm = {}", self.confirm)?;
+        writeln!(f, "ExclusiveConnect = {}", self.exclusive_connect)?;
+        writeln!(f, "Backend = {}", self.backend)?;
+        writeln!(f, "Systemd = {}", self.systemd)?;
+        writeln!(f, "NetworkManager = {}", self.network_manager)?;
+        writeln!(f, "Theme = {}", self.theme)?;
+
+        writeln!(f)?;
+        writeln!(f, "[Sources]")?;
+        writeln!(f, "Paths = {}", self.sources.join(", "))?;
+
+        writeln!(f)?;
+        writeln!(f, "[Columns]")?;
+        let hidden: Vec<_> = self.hidden_columns.iter().map(|c| c.id()).collect();
+        writeln!(f, "Hidden = {}", hidden.join(", "))?;
+
+        for theme in &self.custom_themes {
+            writeln!(f)?;
+            writeln!(f, "[Theme {}]", theme.name)?;
+            for (key, value) in &theme.styles {
+                writeln!(f, "{key} = {value}")?;
+            }
+        }
+
+        for connection in &self.connections {
+            writeln!(f)?;
+            writeln!(f, "[Connection {}]", connection.name)?;
+            writeln!(f, "Autoconnect = {}", connection.autoconnect)?;
+            let join = |conditions: &[autoconnect::Condition]| {
+                conditions
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            };
+            if !connection.rules.connect_when.is_empty() {
+                writeln!(f, "ConnectWhen = {}", join(&connection.rules.connect_when))?;
+            }
+            if !connection.rules.disconnect_when.is_empty() {
+                writeln!(
+                    f,
+                    "DisconnectWhen = {}",
+                    join(&connection.rules.disconnect_when)
+                )?;
+            }
+            writeln!(f, "Watchdog = {}", connection.watchdog)?;
+            let policy = connection.watchdog_policy;
+            writeln!(f, "WatchdogDelay = {}", policy.delay.as_secs())?;
+            writeln!(f, "WatchdogRetries = {}", policy.retries)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ratatui::style::{Style, Stylize};
+
+    use super::*;
+
+    #[test]
+    fn from_string() {
+        let settings = Settings::from(
+            r#"# wtui settings
+[General]
+Clipboard = osc52
+
+[Columns]
+Hidden = mtu, public-key"#,
+        );
+
+        assert_eq!(settings.clipboard, Clipboard::Osc52);
+        assert_eq!(
+            settings.hidden_columns,
+            vec![Column::Mtu, Column::PublicKey]
+        );
+    }
+
+    #[test]
+    fn round_trip() {
+        let settings = Settings {
+            clipboard: Clipboard::Tmux,
+            mouse: false,
+            confirm: ConfirmPolicy::Ssh,
+            exclusive_connect: true,
+            backend: BackendKind::Native,
+            systemd: false,
+            network_manager: false,
+            sources: vec!["~/vpn".to_string(), "/opt/vpn/office-*.conf".to_string()],
+            hidden_columns: vec![Column::Dns, Column::Rate],
+            theme: "mine".to_string(),
+            custom_themes: vec![CustomTheme {
+                name: "mine".to_string(),
+                styles: vec![("Idle".to_string(), "blue".to_string())],
+            }],
+            connections: vec![ConnectionSettings {
+                name: "wg0".to_string(),
+                autoconnect: true,
+                rules: Rules {
+                    connect_when: autoconnect::parse_conditions(
+                        "not ssid Office, gateway 10.0.0.1",
+                    )
+                    .unwrap(),
+                    disconnect_when: autoconnect::parse_conditions("network 10.1.0.0/16").unwrap(),
+                },
+                watchdog: true,
+                watchdog_policy: WatchdogPolicy {
+                    delay: Duration::from_secs(60),
+                    retries: 3,
+                },
+            }],
+            errors: Vec::new(),
+        };
+
+        let parsed = Settings::from(settings.to_string().as_str());
+
+        assert_eq!(parsed.clipboard, settings.clipboard);
+        assert_eq!(parsed.mouse, settings.mouse);
+        assert_eq!(parsed.confirm, settings.confirm);
+        assert_eq!(parsed.exclusive_connect, settings.exclusive_connect);
+        assert_eq!(parsed.backend, settings.backend);
+        assert_eq!(parsed.systemd, settings.systemd);
+        assert_eq!(parsed.network_manager, settings.network_manager);
+        assert_eq!(parsed.sources, settings.sources);
+        assert_eq!(parsed.hidden_columns, settings.hidden_columns);
+        assert_eq!(parsed.theme, settings.theme);
+        assert_eq!(parsed.custom_themes[0].name, "mine");
+        assert_eq!(
+            parsed.custom_themes[0].styles,
+            settings.custom_themes[0].styles
+        );
+        assert_eq!(
+            parsed
+                .connection("wg0")
+                .and_then(ConnectionSettings::watchdog),
+            settings.connections[0].watchdog()
+        );
+        assert!(parsed.connections[0].autoconnect);
+        assert_eq!(parsed.connections[0].rules, settings.connections[0].rules);
+        assert_eq!(parsed.connections[0].rules.connect_when.len(), 2);
+        assert!(parsed.errors.is_empty());
+    }
+
+    #[test]
+    fn invalid_rule() {
+        let settings = Settings::from(
+            r#"[Connection office]
+ConnectWhen = not ssid Office, gatway 10.0.0.1
+DisconnectWhen = ssid Office"#,
+        );
+
+        let rules = &settings.connections[0].rules;
+        assert!(rules.connect_when.is_empty());
+        assert_eq!(rules.disconnect_when.len(), 1);
+        assert_eq!(
+            settings.errors,
+            vec!["[Connection office] ConnectWhen: unknown condition: gatway"]
+        );
+    }
+
+    #[test]
+    fn custom_theme() {
+        let settings = Settings::from(
+            r#"[General]
+Theme = mine
+
+[Theme mine]
+Base = light
+Connected = bold blue"#,
+        );
+
+        // the test environment may disable colors
+        if !Theme::is_no_color() {
+            let theme = settings.theme();
+            assert_eq!(theme.connected, Style::new().bold().blue());
+            assert_eq!(theme.idle, Theme::light().idle);
+        }
+    }
+
+    #[test]
+    fn unknown_values() {
+        let settings = Settings::from(
+            r#"[General]
+Clipboard = carrier pigeon
+Unknown = key
+
+[Unknown]
+Clipboard = tmux"#,
+        );
+
+        assert_eq!(settings.clipboard, Clipboard::Auto);
+    }
+
+    #[test]
+    fn watchdog_delay() {
+        let settings = Settings::from(
+            r#"[Connection wg0]
+Watchdog = true
+WatchdogDelay = 18446744073709551615"#,
+        );
+
+        assert_eq!(
+            settings.watchdog("wg0").map(|p| p.delay),
+            Some(WatchdogPolicy::default().delay)
+        );
+    }
+}
@@ -0,0 +1,139 @@
+//! Sorting of the connections list.
+use std::cmp::Ordering;
+
+use super::{column::Column, connection::Connection};
+
+/// Columns the connections can be sorted by.
+const SORTABLE: [Column; 7] = [
+    Column::Name,
+    Column::Status,
+    Column::Received,
+    Column::Sent,
+    Column::Rate,
+    Column::Handshake,
+    Column::Endpoint,
+];
+
+/// How the connections are sorted.
+pub struct Sort {
+    pub column: Column,
+    pub descending: bool,
+}
+
+impl Default for Sort {
+    fn default() -> Self {
+        Self {
+            column: Column::Name,
+            descending: false,
+        }
+    }
+}
+
+impl Sort {
+    /// Tells whether connections can be sorted by `column`.
+    pub fn is_sortable(column: Column) -> bool {
+        SORTABLE.contains(&column)
+    }
+
+    /// Sorts by the next sortable column.
+    pub fn next_column(&mut self) {
+        let position = SORTABLE
+            .iter()
+            .position(|&c| c == self.column)
+            .unwrap_or_default();
+        self.column = SORTABLE[(position + 1) % SORTABLE.len()];
+    }
+
+    /// Arrow showing the direction, to display next to the sorted column title.
+    pub fn indicator(&self) -> &'static str {
+        match self.descending {
+            false => "▲",
+            true => "▼",
+        }
+    }
+
+    /// Compares two connections, connections being equal on the sorted column are sorted by name.
+    pub fn compare(&self, a: &Connection, b: &Connection) -> Ordering {
+        let ordering = match self.column {
+            Column::Status => a.status().health().cmp(&b.status().health()),
+            Column::Received => a
+                .status()
+                .bytes_received()
+                .map(|b| **b)
+                .cmp(&b.status().bytes_received().map(|b| **b)),
+            Column::Sent => a
+                .status()
+                .bytes_sent()
+                .map(|b| **b)
+                .cmp(&b.status().bytes_sent().map(|b| **b)),
+            Column::Rate => a.rate().map(|r| *r).cmp(&b.rate().map(|r| *r)),
+            Column::Handshake => a
+                .status()
+                .handshake_elapsed()
+                .cmp(&b.status().handshake_elapsed()),
+            Column::Endpoint => a.config().peer.endpoint().cmp(&b.config().peer.endpoint()),
+            _ => Ordering::Equal,
+        }
+        .then_with(|| a.cmp(b));
+
+        match self.descending {
+            false => ordering,
+            true => ordering.reverse(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wg::WgConfig;
+
+    fn connection(name: &str, endpoint: &str) -> Connection {
+        Connection::new(
+            name,
+            WgConfig::from(
+                format!(
+                    r#"[Interface]
+PrivateKey = oMVUWFwDf+20fIfeRUe7c0rlUKSYnHk2K0y2920SX1c=
+Address = 192.168.5.2
+
+[Peer]
+PublicKey = 60TUAvOo+Wi4SCyir581cCyBx4wIcHtrIrUgBv/iqRM=
+AllowedIPs = 0.0.0.0/0
+Endpoint = {endpoint}"#
+                )
+                .as_str(),
+            ),
+        )
+    }
+
+    #[test]
+    fn next_column() {
+        let mut sort = Sort::default();
+
+        for _ in 0..SORTABLE.len() {
+            sort.next_column();
+        }
+
+        assert_eq!(sort.column, Column::Name);
+    }
+
+    #[test]
+    fn compare() {
+        let a = connection("a", "vpn2.example.com:51820");
+        let b = connection("b", "vpn1.example.com:51820");
+        let c = connection("c", "vpn1.example.com:51820");
+
+        let mut sort = Sort::default();
+        assert_eq!(sort.compare(&a, &b), Ordering::Less);
+
+        sort.column = Column::Endpoint;
+        assert_eq!(sort.compare(&a, &b), Ordering::Greater);
+        // same endpoint, sorted by name
+        assert_eq!(sort.compare(&b, &c), Ordering::Less);
+
+        sort.descending = true;
+        assert_eq!(sort.compare(&a, &b), Ordering::Less);
+        assert_eq!(sort.compare(&b, &c), Ordering::Greater);
+    }
+}
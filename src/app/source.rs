@@ -0,0 +1,322 @@
+//! Where the configuration files are: directories filtered by a glob, or single files.
+use std::{
+    env,
+    ffi::CString,
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    os::unix::{ffi::OsStrExt, fs::OpenOptionsExt},
+    path::{Path, PathBuf},
+};
+
+/// Sources used when the settings don't name any.
+pub const DEFAULT_SOURCES: [&str; 2] = ["/etc/wireguard/*.conf", "~/.config/wireguard/*.conf"];
+
+/// A place to load configuration files from.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Source {
+    /// The files of `dir` whose name matches `pattern`, e.g. `*.conf`.
+    Directory {
+        dir: PathBuf,
+        pattern: String,
+    },
+    File(PathBuf),
+}
+
+/// A configuration file found in a source, or a source which couldn't be read.
+pub struct Found {
+    /// Name of the connection: the file name without `.conf`, or the path of an unreadable
+    /// directory.
+    pub name: String,
+    pub path: PathBuf,
+    pub content: io::Result<String>,
+}
+
+impl Found {
+    pub fn read(path: &Path) -> Self {
+        Self {
+            name: connection_name(path),
+            path: path.to_path_buf(),
+            content: fs::read_to_string(path),
+        }
+    }
+}
+
+/// Replaces the configuration file at `path` by `text` atomically: the text is written to a file
+/// only readable by its owner next to it, which then replaces it.
+///
+/// A crash never leaves a truncated configuration, nor a private key readable by others.
+pub fn write(path: &Path, text: &str) -> io::Result<()> {
+    let name = path
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "not a file"))?;
+    let tmp = path.with_file_name(format!(".{}.tmp", name.to_string_lossy()));
+    // left by a crash, maybe with other permissions
+    let _ = fs::remove_file(&tmp);
+
+    let result = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(&tmp)
+        .and_then(|mut file| {
+            file.write_all(text.as_bytes())?;
+            file.sync_all()
+        })
+        .and_then(|()| fs::rename(&tmp, path));
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+    result
+}
+
+impl Source {
+    /// Parses a path as written in the settings or on the command line.
+    ///
+    /// A leading `~` is the home directory. A glob in the file name, e.g. `~/vpn/*.conf`, filters
+    /// the files of its directory, a plain directory is filtered with `*.conf`.
+    pub fn new(path: &str) -> Self {
+        let path = expand_home(path.trim());
+        let glob = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .filter(|name| name.contains(['*', '?']));
+
+        match glob {
+            Some(pattern) => Self::Directory {
+                dir: match path.parent() {
+                    Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+                    _ => PathBuf::from("."),
+                },
+                pattern,
+            },
+            None if path.is_dir() => Self::Directory {
+                dir: path,
+                pattern: "*.conf".to_string(),
+            },
+            None => Self::File(path),
+        }
+    }
+
+    /// The directory containing the files of the source.
+    pub fn dir(&self) -> &Path {
+        match self {
+            Self::Directory { dir, .. } => dir,
+            Self::File(path) => path.parent().unwrap_or(Path::new(".")),
+        }
+    }
+
+    /// The directory where the configuration of a new connection `name` can be saved, if its
+    /// file would be listed by this source and the directory is writable.
+    pub fn dir_for(&self, name: &str) -> Option<&Path> {
+        let Self::Directory { dir, pattern } = self else {
+            return None;
+        };
+        let file = format!("{name}.conf");
+        let dir_path = CString::new(dir.as_os_str().as_bytes()).ok()?;
+        // SAFETY: the path is a valid C string
+        let writable = unsafe { libc::access(dir_path.as_ptr(), libc::W_OK) } == 0;
+
+        (writable && matches(pattern, &file)).then_some(dir.as_path())
+    }
+
+    /// The configuration files of the source, sorted by path.
+    ///
+    /// A missing directory has no files, any other error reading it is returned as a single
+    /// entry so that it shows up instead of being ignored.
+    pub fn files(&self) -> Vec<Found> {
+        match self.paths() {
+            Ok(paths) => paths.iter().map(|path| Found::read(path)).collect(),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => vec![Found {
+                name: self.dir().display().to_string(),
+                path: self.dir().to_path_buf(),
+                content: Err(e),
+            }],
+        }
+    }
+
+    /// The paths of the configuration files of the source, sorted, without reading them.
+    pub fn paths(&self) -> io::Result<Vec<PathBuf>> {
+        let (dir, pattern) = match self {
+            Self::File(path) => return Ok(vec![path.clone()]),
+            Self::Directory { dir, pattern } => (dir, pattern),
+        };
+
+        let mut paths: Vec<_> = fs::read_dir(dir)?
+            .filter_map(Result::ok)
+            .filter(|entry| matches(pattern, &entry.file_name().to_string_lossy()))
+            .map(|entry| entry.path())
+            .filter(|path| !path.is_dir())
+            .collect();
+        paths.sort();
+        Ok(paths)
+    }
+
+    /// Tells whether the file at `path` belongs to the source, existing or not.
+    pub fn lists(&self, path: &Path) -> bool {
+        match self {
+            Self::Directory { dir, pattern } => {
+                path.parent() == Some(dir.as_path())
+                    && path
+                        .file_name()
+                        .is_some_and(|name| matches(pattern, &name.to_string_lossy()))
+            }
+            Self::File(file) => file == path,
+        }
+    }
+}
+
+/// Name of the connection of a configuration file, its name without `.conf`.
+pub fn connection_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy())
+        .map(|name| name.trim_end_matches(".conf").to_string())
+        .unwrap_or_default()
+}
+
+/// Replaces a leading `~` by the home directory.
+fn expand_home(path: &str) -> PathBuf {
+    let home = env::var_os("HOME").filter(|home| !home.is_empty());
+    match (path.strip_prefix('~'), home) {
+        (Some(rest), Some(home)) if rest.is_empty() || rest.starts_with('/') => {
+            PathBuf::from(home).join(rest.trim_start_matches('/'))
+        }
+        _ => PathBuf::from(path),
+    }
+}
+
+/// Tells whether the file `name` matches the glob `pattern`, made of `*` and `?`.
+///
+/// Like shells, hidden files only match patterns starting with a dot.
+fn matches(pattern: &str, name: &str) -> bool {
+    fn glob(pattern: &[char], name: &[char]) -> bool {
+        match (pattern.split_first(), name.split_first()) {
+            (None, None) => true,
+            (Some(('*', rest)), _) => {
+                glob(rest, name) || (!name.is_empty() && glob(pattern, &name[1..]))
+            }
+            (Some(('?', rest)), Some((_, name))) => glob(rest, name),
+            (Some((p, rest)), Some((n, name))) if p == n => glob(rest, name),
+            _ => false,
+        }
+    }
+
+    if name.starts_with('.') && !pattern.starts_with('.') {
+        return false;
+    }
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    glob(&pattern, &name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dir_for() {
+        let dir = env::temp_dir();
+        let source = Source::Directory {
+            dir: dir.clone(),
+            pattern: "*.conf".to_string(),
+        };
+        assert_eq!(source.dir_for("wg0"), Some(dir.as_path()));
+
+        let source = Source::Directory {
+            dir: dir.clone(),
+            pattern: "office-*.conf".to_string(),
+        };
+        assert_eq!(source.dir_for("wg0"), None);
+        assert_eq!(source.dir_for("office-2"), Some(dir.as_path()));
+
+        let source = Source::new("/nonexistent/*.conf");
+        assert_eq!(source.dir_for("wg0"), None);
+        assert_eq!(Source::File(dir.join("wg0.conf")).dir_for("wg0"), None);
+    }
+
+    #[test]
+    fn glob() {
+        assert!(matches("*.conf", "wg0.conf"));
+        assert!(!matches("*.conf", "wg0.conf.bak"));
+        assert!(!matches("*.conf", "wg0.conf~"));
+        assert!(!matches("*.conf", ".wg0.conf.swp"));
+        assert!(!matches("*.conf", ".hidden.conf"));
+        assert!(matches("wg?.conf", "wg1.conf"));
+        assert!(!matches("wg?.conf", "wg10.conf"));
+        assert!(matches("office-*", "office-paris"));
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!(
+            Source::new("/etc/wireguard/*.conf"),
+            Source::Directory {
+                dir: PathBuf::from("/etc/wireguard"),
+                pattern: "*.conf".to_string()
+            }
+        );
+        assert_eq!(
+            Source::new("/tmp"),
+            Source::Directory {
+                dir: PathBuf::from("/tmp"),
+                pattern: "*.conf".to_string()
+            }
+        );
+        assert_eq!(
+            Source::new("/nowhere/wg0.conf"),
+            Source::File(PathBuf::from("/nowhere/wg0.conf"))
+        );
+        assert_eq!(connection_name(Path::new("/nowhere/wg0.conf")), "wg0");
+    }
+
+    #[test]
+    fn files() {
+        let dir = env::temp_dir().join(format!("wtui-source-{}", std::process::id()));
+        fs::create_dir_all(dir.join("sub.conf")).unwrap();
+        for file in ["b.conf", "a.conf", "a.conf.bak", "notes.txt"] {
+            fs::write(dir.join(file), "[Interface]").unwrap();
+        }
+
+        let source = Source::new(dir.to_str().unwrap());
+        let names: Vec<_> = source.files().into_iter().map(|f| f.name).collect();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(names, vec!["a", "b"]);
+
+        assert!(source.files().is_empty());
+        let missing = Source::File(dir.join("wg0.conf")).files();
+        assert_eq!(missing[0].name, "wg0");
+        assert!(missing[0].content.is_err());
+    }
+
+    #[test]
+    fn write_atomically() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = env::temp_dir().join(format!("wtui-write-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("wg0.conf");
+        fs::write(&path, "[Interface]\nPrivateKey = old\n").unwrap();
+
+        write(&path, "[Interface]\nPrivateKey = new\n").unwrap();
+        let text = fs::read_to_string(&path).unwrap();
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        let files = fs::read_dir(&dir).unwrap().count();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(text, "[Interface]\nPrivateKey = new\n");
+        assert_eq!(mode & 0o777, 0o600);
+        assert_eq!(files, 1);
+    }
+
+    #[test]
+    fn lists() {
+        let source = Source::new("/etc/wireguard/*.conf");
+        assert!(source.lists(Path::new("/etc/wireguard/wg0.conf")));
+        assert!(!source.lists(Path::new("/etc/wireguard/wg0.conf.bak")));
+        assert!(!source.lists(Path::new("/etc/wireguard/sub/wg0.conf")));
+
+        let source = Source::File(PathBuf::from("/home/me/vpn.conf"));
+        assert!(source.lists(Path::new("/home/me/vpn.conf")));
+        assert!(!source.lists(Path::new("/home/me/other.conf")));
+    }
+}
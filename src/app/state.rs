@@ -1,6 +1,31 @@
+use super::{
+    confirm::Confirm, details::Details, drift::Drift, import::Import, menu::Menu,
+    problems::Problems, qr::Qr, switch::Switch,
+};
+
 #[derive(Default)]
 pub enum State {
     #[default]
     Main,
     Yank,
+    Import(Box<Import>),
+    Qr(Box<Qr>),
+    /// Typing in the search bar.
+    Search,
+    /// Choosing the shown columns, with the position of the cursor.
+    Columns(usize),
+    /// Context menu of the selected connection.
+    Menu(Menu),
+    /// Waiting for a destructive action to be confirmed.
+    Confirm(Box<Confirm>),
+    /// Connecting a connection whose routes conflict with connected ones.
+    Switch(Box<Switch>),
+    /// Every field of a connection.
+    Details(Box<Details>),
+    /// The file of a connection compared with what its interface runs.
+    Drift(Box<Drift>),
+    /// What wtui did by itself.
+    Log,
+    /// Problems found in the configurations.
+    Problems(Problems),
 }
@@ -0,0 +1,51 @@
+//! Switching to a connection whose routes conflict with connected ones.
+use ratatui::{prelude::*, widgets::*};
+
+use super::{popup, theme::Theme};
+use crate::wg::types::Cidr;
+
+/// A connection about to be connected while others route the same networks.
+pub struct Switch {
+    /// Name of the connection to connect.
+    pub target: String,
+    /// Names of the connected connections, with the networks they share with the target.
+    pub conflicts: Vec<(String, Vec<Cidr>)>,
+}
+
+impl Switch {
+    /// Names of the connections to disconnect before connecting the target.
+    pub fn others(&self) -> Vec<String> {
+        self.conflicts
+            .iter()
+            .map(|(name, _)| name.clone())
+            .collect()
+    }
+
+    pub fn render(&self, area: Rect, buf: &mut Buffer, theme: &Theme) {
+        let mut lines = vec![Line::from(format!(
+            "{} routes networks already routed by:",
+            self.target
+        ))];
+        for (name, networks) in &self.conflicts {
+            lines.push(Line::styled(
+                format!("  {name}: {}", Cidr::join(networks, ", ")),
+                theme.error,
+            ));
+        }
+
+        lines.push(Line::default());
+        lines.push(Line::from(vec![
+            "s".bold(),
+            format!(": switch (disconnect {})", self.others().join(", ")).into(),
+        ]));
+        lines.push(Line::from(vec!["c".bold(), ": connect anyway".into()]));
+        lines.push(Line::from(vec!["Esc".bold(), ": cancel".into()]));
+
+        let width = lines.iter().map(Line::width).max().unwrap_or_default() as u16 + 4;
+        let area = popup::centered(area, width, lines.len() as u16 + 2);
+        Clear.render(area, buf);
+        Paragraph::new(lines)
+            .block(popup::block("Conflicting routes"))
+            .render(area, buf);
+    }
+}
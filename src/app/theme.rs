@@ -0,0 +1,197 @@
+//! Colors of the interface.
+use std::env;
+
+use ratatui::prelude::*;
+
+/// Styles used across the interface.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Theme {
+    /// Connection with a recent handshake.
+    pub connected: Style,
+    /// Connection up, but the peer never answered.
+    pub idle: Style,
+    /// Connection up, but its latest handshake is too old.
+    pub stale: Style,
+    pub disconnected: Style,
+    /// Units of byte counts.
+    pub unit: Style,
+    /// Selected row.
+    pub highlight: Style,
+    pub error: Style,
+    pub success: Style,
+}
+
+impl Theme {
+    /// Names of the built-in themes.
+    pub const BUILTIN: [&str; 4] = ["dark", "light", "high-contrast", "colorblind"];
+
+    pub fn dark() -> Self {
+        Self {
+            connected: Style::new().green(),
+            idle: Style::new().yellow(),
+            stale: Style::new().light_red(),
+            disconnected: Style::new().dim(),
+            unit: Style::new().cyan(),
+            highlight: Style::new().reversed(),
+            error: Style::new().red(),
+            success: Style::new().green(),
+        }
+    }
+
+    pub fn light() -> Self {
+        Self {
+            connected: Style::new().green(),
+            idle: Style::new().magenta(),
+            stale: Style::new().red(),
+            disconnected: Style::new().dark_gray(),
+            unit: Style::new().blue(),
+            highlight: Style::new().reversed(),
+            error: Style::new().red(),
+            success: Style::new().green(),
+        }
+    }
+
+    pub fn high_contrast() -> Self {
+        Self {
+            connected: Style::new().light_green().bold(),
+            idle: Style::new().light_yellow().bold(),
+            stale: Style::new().light_red().bold(),
+            disconnected: Style::new().white(),
+            unit: Style::new().light_cyan(),
+            highlight: Style::new().black().on_white().bold(),
+            error: Style::new().light_red().bold(),
+            success: Style::new().light_green().bold(),
+        }
+    }
+
+    /// Blue and orange instead of green and red, from the Okabe-Ito palette.
+    pub fn colorblind() -> Self {
+        let blue = Color::Rgb(0, 114, 178);
+        let orange = Color::Rgb(230, 159, 0);
+
+        Self {
+            connected: Style::new().fg(blue),
+            idle: Style::new().fg(orange),
+            stale: Style::new().fg(Color::Rgb(204, 121, 167)),
+            disconnected: Style::new().dim(),
+            unit: Style::new().fg(Color::Rgb(86, 180, 233)),
+            highlight: Style::new().reversed(),
+            error: Style::new().fg(Color::Rgb(213, 94, 0)),
+            success: Style::new().fg(blue),
+        }
+    }
+
+    /// Only text modifiers, see <https://no-color.org>.
+    pub fn no_color() -> Self {
+        Self {
+            connected: Style::new().bold(),
+            idle: Style::new(),
+            stale: Style::new().italic(),
+            disconnected: Style::new().dim(),
+            unit: Style::new(),
+            highlight: Style::new().reversed(),
+            error: Style::new().bold(),
+            success: Style::new(),
+        }
+    }
+
+    pub fn builtin(name: &str) -> Option<Self> {
+        match name {
+            "dark" => Some(Self::dark()),
+            "light" => Some(Self::light()),
+            "high-contrast" => Some(Self::high_contrast()),
+            "colorblind" => Some(Self::colorblind()),
+            _ => None,
+        }
+    }
+
+    /// Tells whether colors are disabled by the `NO_COLOR` environment variable.
+    pub fn is_no_color() -> bool {
+        env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty())
+    }
+
+    /// Sets the style called `key` (e.g. `Connected`) to `style`.
+    ///
+    /// Returns [None] if there is no such style.
+    pub fn set(&mut self, key: &str, style: Style) -> Option<()> {
+        *match key {
+            "Connected" => &mut self.connected,
+            "Idle" => &mut self.idle,
+            "Stale" => &mut self.stale,
+            "Disconnected" => &mut self.disconnected,
+            "Unit" => &mut self.unit,
+            "Highlight" => &mut self.highlight,
+            "Error" => &mut self.error,
+            "Success" => &mut self.success,
+            _ => return None,
+        } = style;
+        Some(())
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::dark()
+    }
+}
+
+/// Parses a style made of space separated words.
+///
+/// Words are modifiers (`bold`, `dim`, `italic`, `underlined`, `reversed`), colors (names,
+/// `#rrggbb` or 256 colors indexes) for the foreground, or `on` followed by a background color.
+/// Unknown words are ignored.
+pub fn parse_style(value: &str) -> Style {
+    let mut style = Style::new();
+    let mut words = value.split_whitespace();
+
+    while let Some(word) = words.next() {
+        style = match word.to_lowercase().as_str() {
+            "bold" => style.bold(),
+            "dim" => style.dim(),
+            "italic" => style.italic(),
+            "underlined" => style.underlined(),
+            "reversed" => style.reversed(),
+            "on" => match words.next().and_then(|c| c.parse().ok()) {
+                Some(color) => style.bg(color),
+                None => style,
+            },
+            color => match color.parse() {
+                Ok(color) => style.fg(color),
+                Err(_) => style,
+            },
+        };
+    }
+
+    style
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builtin() {
+        for name in Theme::BUILTIN {
+            assert!(Theme::builtin(name).is_some());
+        }
+        assert_eq!(Theme::builtin("unknown"), None);
+    }
+
+    #[test]
+    fn style() {
+        assert_eq!(parse_style("green"), Style::new().green());
+        assert_eq!(
+            parse_style("bold Light-Red"),
+            Style::new().bold().light_red()
+        );
+        assert_eq!(
+            parse_style("#ff8800 on black"),
+            Style::new().fg(Color::Rgb(255, 136, 0)).on_black()
+        );
+        assert_eq!(
+            parse_style("208 sparkly"),
+            Style::new().fg(Color::Indexed(208))
+        );
+        assert_eq!(parse_style(""), Style::new());
+    }
+}
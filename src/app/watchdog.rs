@@ -0,0 +1,225 @@
+//! Restarts tunnels that died by themselves.
+use std::time::{Duration, Instant};
+
+use crate::wg::Health;
+
+/// Longest delay before the first restart, a day.
+pub const MAX_DELAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// When a connection is restarted.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WatchdogPolicy {
+    /// How long the connection must be unhealthy before the first restart.
+    ///
+    /// Doubled after each attempt, to give the next one more time.
+    pub delay: Duration,
+    /// Attempts before giving up, until the connection is healthy again.
+    pub retries: u32,
+}
+
+impl Default for WatchdogPolicy {
+    fn default() -> Self {
+        Self {
+            delay: Duration::from_secs(30),
+            retries: 5,
+        }
+    }
+}
+
+/// What the watchdog decided on a check.
+#[derive(Debug, PartialEq)]
+pub enum Check {
+    /// Nothing to do.
+    Idle,
+    /// The connection must be restarted, `attempt` starting at 1.
+    Restart { attempt: u32 },
+    /// The latest attempt failed too, the connection is left as is.
+    GiveUp,
+    /// The connection is healthy after some restarts.
+    Recovered,
+}
+
+/// Watches the health of a connection the user wants up.
+pub struct Watchdog {
+    policy: WatchdogPolicy,
+    /// Whether the connection should be up, i.e. it was up and the user didn't disconnect it.
+    wanted: bool,
+    /// Since when the connection is unhealthy, reset after each attempt.
+    unhealthy_since: Option<Instant>,
+    attempts: u32,
+}
+
+impl Watchdog {
+    pub fn new(policy: WatchdogPolicy) -> Self {
+        Self {
+            policy,
+            wanted: false,
+            unhealthy_since: None,
+            attempts: 0,
+        }
+    }
+
+    /// Tells the watchdog whether the user wants the connection up.
+    pub fn set_wanted(&mut self, wanted: bool) {
+        self.wanted = wanted;
+        self.unhealthy_since = None;
+        self.attempts = 0;
+    }
+
+    /// Checks the `health` of the connection at `now`.
+    ///
+    /// Only connections [wanted](Self::set_wanted) are restarted, not the ones brought up by
+    /// other tools.
+    ///
+    /// Without `keepalive`, an idle tunnel has no handshake, so being stale isn't a failure.
+    pub fn check(&mut self, health: Health, keepalive: bool, now: Instant) -> Check {
+        if health == Health::Healthy {
+            let recovered = self.attempts > 0;
+            self.unhealthy_since = None;
+            self.attempts = 0;
+            return match recovered {
+                true => Check::Recovered,
+                false => Check::Idle,
+            };
+        }
+        if !self.wanted || self.attempts > self.policy.retries {
+            return Check::Idle;
+        }
+        if health == Health::Stale && !keepalive {
+            self.unhealthy_since = None;
+            return Check::Idle;
+        }
+
+        let since = *self.unhealthy_since.get_or_insert(now);
+        let delay = self
+            .policy
+            .delay
+            .saturating_mul(2u32.saturating_pow(self.attempts));
+        if now.duration_since(since) < delay {
+            return Check::Idle;
+        }
+
+        self.attempts += 1;
+        self.unhealthy_since = Some(now);
+        match self.attempts > self.policy.retries {
+            true => Check::GiveUp,
+            false => Check::Restart {
+                attempt: self.attempts,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff() {
+        let policy = WatchdogPolicy {
+            delay: Duration::from_secs(10),
+            retries: 2,
+        };
+        let mut watchdog = Watchdog::new(policy);
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+
+        watchdog.set_wanted(true);
+        assert_eq!(watchdog.check(Health::Healthy, true, at(100)), Check::Idle);
+        assert_eq!(watchdog.check(Health::Stale, true, at(101)), Check::Idle);
+        assert_eq!(
+            watchdog.check(Health::Stale, true, at(111)),
+            Check::Restart { attempt: 1 }
+        );
+        // doubled delays
+        assert_eq!(watchdog.check(Health::Down, true, at(130)), Check::Idle);
+        assert_eq!(
+            watchdog.check(Health::Down, true, at(131)),
+            Check::Restart { attempt: 2 }
+        );
+        assert_eq!(
+            watchdog.check(Health::NoHandshake, true, at(170)),
+            Check::Idle
+        );
+        assert_eq!(
+            watchdog.check(Health::NoHandshake, true, at(171)),
+            Check::GiveUp
+        );
+        assert_eq!(
+            watchdog.check(Health::NoHandshake, true, at(1000)),
+            Check::Idle
+        );
+
+        assert_eq!(
+            watchdog.check(Health::Healthy, true, at(1001)),
+            Check::Recovered
+        );
+        assert_eq!(watchdog.check(Health::Healthy, true, at(1002)), Check::Idle);
+    }
+
+    #[test]
+    fn long_delay() {
+        let policy = WatchdogPolicy {
+            delay: Duration::from_secs(u64::MAX),
+            retries: 40,
+        };
+        let mut watchdog = Watchdog::new(policy);
+        let start = Instant::now();
+
+        watchdog.set_wanted(true);
+        watchdog.attempts = 40;
+        assert_eq!(
+            watchdog.check(Health::Down, true, start + Duration::from_secs(1)),
+            Check::Idle
+        );
+    }
+
+    #[test]
+    fn idle_without_keepalive() {
+        let mut watchdog = Watchdog::new(WatchdogPolicy::default());
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+
+        watchdog.set_wanted(true);
+        watchdog.check(Health::Healthy, false, at(0));
+        assert_eq!(watchdog.check(Health::Stale, false, at(1)), Check::Idle);
+        assert_eq!(watchdog.check(Health::Stale, false, at(3600)), Check::Idle);
+        // unhealthy from when it's down, not from when it went stale
+        assert_eq!(watchdog.check(Health::Down, false, at(3601)), Check::Idle);
+        assert_eq!(
+            watchdog.check(Health::Down, false, at(3631)),
+            Check::Restart { attempt: 1 }
+        );
+    }
+
+    #[test]
+    fn connected_by_another_tool() {
+        let mut watchdog = Watchdog::new(WatchdogPolicy::default());
+        let start = Instant::now();
+
+        assert_eq!(watchdog.check(Health::Healthy, true, start), Check::Idle);
+        assert_eq!(
+            watchdog.check(Health::Down, true, start + Duration::from_secs(1)),
+            Check::Idle
+        );
+        assert_eq!(
+            watchdog.check(Health::Down, true, start + Duration::from_secs(3600)),
+            Check::Idle
+        );
+    }
+
+    #[test]
+    fn disconnected_by_user() {
+        let mut watchdog = Watchdog::new(WatchdogPolicy::default());
+        let start = Instant::now();
+
+        watchdog.set_wanted(true);
+        watchdog.check(Health::Healthy, true, start);
+        watchdog.set_wanted(false);
+
+        assert_eq!(
+            watchdog.check(Health::Down, true, start + Duration::from_secs(3600)),
+            Check::Idle
+        );
+    }
+}
@@ -0,0 +1,107 @@
+//! The yank (copy) menu.
+use ratatui::{prelude::*, widgets::*};
+
+use super::{connection::Connection, popup};
+use crate::wg::types::{Cidr, Endpoint};
+
+/// What can be copied from a connection.
+#[derive(Clone, Copy)]
+pub enum Yank {
+    InterfacePubkey,
+    PeerPubkey,
+    Endpoint,
+    Address,
+    AllowedIps,
+    Config,
+}
+
+impl Yank {
+    pub const ALL: [Yank; 6] = [
+        Yank::InterfacePubkey,
+        Yank::PeerPubkey,
+        Yank::Endpoint,
+        Yank::Address,
+        Yank::AllowedIps,
+        Yank::Config,
+    ];
+
+    /// The key selecting this entry in the menu.
+    pub fn key(self) -> char {
+        match self {
+            Yank::InterfacePubkey => 'i',
+            Yank::PeerPubkey => 'p',
+            Yank::Endpoint => 'e',
+            Yank::Address => 'a',
+            Yank::AllowedIps => 'l',
+            Yank::Config => 'c',
+        }
+    }
+
+    pub fn from_key(key: char) -> Option<Self> {
+        Self::ALL.into_iter().find(|y| y.key() == key)
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Yank::InterfacePubkey => "interface public key",
+            Yank::PeerPubkey => "peer public key",
+            Yank::Endpoint => "endpoint",
+            Yank::Address => "addresses",
+            Yank::AllowedIps => "allowed IPs",
+            Yank::Config => "config without secrets",
+        }
+    }
+
+    /// The text to copy from `con`.
+    pub fn content(self, con: &Connection) -> String {
+        let config = con.config();
+        match self {
+            Yank::InterfacePubkey => config.interface.pubkey().to_string(),
+            Yank::PeerPubkey => config.peer.pubkey().to_string(),
+            Yank::Endpoint => config
+                .peer
+                .endpoint()
+                .map(Endpoint::to_string)
+                .unwrap_or_default(),
+            Yank::Address => Cidr::join(config.interface.address(), ", "),
+            Yank::AllowedIps => Cidr::join(config.peer.allowed_ips(), ", "),
+            Yank::Config => config.redacted(),
+        }
+    }
+}
+
+/// The popup listing what can be copied.
+pub struct YankMenu;
+
+impl Widget for YankMenu {
+    fn render(self, area: Rect, buf: &mut Buffer)
+    where
+        Self: Sized,
+    {
+        let lines: Vec<Line> = Yank::ALL
+            .into_iter()
+            .map(|y| Line::from(vec![format!(" {} ", y.key()).bold(), y.label().into()]))
+            .collect();
+
+        let area = popup::centered(area, 30, lines.len() as u16 + 2);
+        Clear.render(area, buf);
+        Paragraph::new(lines)
+            .block(popup::block("Yank"))
+            .render(area, buf);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_are_unique() {
+        for yank in Yank::ALL {
+            assert_eq!(
+                Yank::from_key(yank.key()).map(Yank::label),
+                Some(yank.label())
+            );
+        }
+    }
+}
@@ -1,8 +1,31 @@
-use crate::app::{App, AppResult};
-use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use crate::app::{App, AppResult, State, Yank};
+use ratatui::crossterm::event::{
+    KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind,
+};
 
 /// Handles the key events and updates the state of [`App`].
 pub fn handle_key_events(key_event: KeyEvent, app: &mut App) -> AppResult<()> {
+    app.clear_message();
+
+    match app.state() {
+        State::Main => handle_main_key_events(key_event, app),
+        State::Yank => handle_yank_key_events(key_event, app),
+        State::Import(_) => handle_import_key_events(key_event, app),
+        State::Qr(_) | State::Problems(_) | State::Details(_) | State::Log => {
+            handle_popup_key_events(key_event, app)
+        }
+        State::Search => handle_search_key_events(key_event, app),
+        State::Columns(_) => handle_columns_key_events(key_event, app),
+        State::Menu(_) => handle_menu_key_events(key_event, app),
+        State::Confirm(_) => handle_confirm_key_events(key_event, app),
+        State::Switch(_) => handle_switch_key_events(key_event, app),
+        State::Drift(_) => handle_drift_key_events(key_event, app),
+    }
+    Ok(())
+}
+
+/// Handles the key events of the main view.
+fn handle_main_key_events(key_event: KeyEvent, app: &mut App) {
     match (key_event.modifiers, key_event.code) {
         // Exit application on `ESC`, `q` or 'Ctrl-C'
         (_, KeyCode::Esc | KeyCode::Char('q')) | (KeyModifiers::CONTROL, KeyCode::Char('c')) => {
@@ -17,8 +40,37 @@ pub fn handle_key_events(key_event: KeyEvent, app: &mut App) -> AppResult<()> {
         // Disconnect
         (_, KeyCode::Char('d')) => app.disconnect_selected(),
         (_, KeyCode::Char('D')) => app.disconnect_all(),
+        // Delete
+        (_, KeyCode::Char('x')) => app.delete_selected(),
+        // Rotate key
+        (_, KeyCode::Char('R')) => app.rotate_selected_key(),
+        // Details
+        (_, KeyCode::Enter) => app.details(),
         // Yank
         (_, KeyCode::Char('y')) => app.yank_menu(),
+        // Sort
+        (_, KeyCode::Char('s')) => app.next_sort_column(),
+        (_, KeyCode::Char('S')) => app.reverse_sort(),
+        // Search & filters
+        (_, KeyCode::Char('/')) => app.search(),
+        (_, KeyCode::Char('C')) => app.toggle_connected_filter(),
+        (_, KeyCode::Char('H')) => app.toggle_stale_filter(),
+        // Columns
+        (_, KeyCode::Char('v')) => app.column_chooser(),
+        // Import
+        (_, KeyCode::Char('i')) => app.import_clipboard(),
+        // QR code
+        (_, KeyCode::Char('Q')) => app.qr_code(),
+        // Problems
+        (_, KeyCode::Char('P')) => app.problems(),
+        // Log
+        (_, KeyCode::Char('L')) => app.log(),
+        // Start at boot
+        (_, KeyCode::Char('B')) => app.toggle_boot(),
+        // Compare with the running interface
+        (_, KeyCode::Char('=')) => app.drift(),
+        // Apply the file without disconnecting
+        (_, KeyCode::Char('A')) => app.apply_selected(),
         // Show help
         (_, KeyCode::Char('?')) => {
             todo!("Implement help popup")
@@ -26,5 +78,133 @@ pub fn handle_key_events(key_event: KeyEvent, app: &mut App) -> AppResult<()> {
         // Other handlers you could add here.
         _ => {}
     }
+}
+
+/// Handles the key events of popups only showing information.
+fn handle_popup_key_events(key_event: KeyEvent, app: &mut App) {
+    match (key_event.modifiers, key_event.code) {
+        (KeyModifiers::CONTROL, KeyCode::Char('c')) => app.quit(),
+        (_, KeyCode::Esc | KeyCode::Char('q')) => app.close_popup(),
+        (_, KeyCode::Char('p')) if matches!(app.state(), State::Qr(_)) => app.qr_new_peer(),
+        _ => {}
+    }
+}
+
+/// Handles the key events of the yank menu.
+fn handle_yank_key_events(key_event: KeyEvent, app: &mut App) {
+    match (key_event.modifiers, key_event.code) {
+        (KeyModifiers::CONTROL, KeyCode::Char('c')) => app.quit(),
+        (_, KeyCode::Esc | KeyCode::Char('q')) => app.close_popup(),
+        (_, KeyCode::Char(c)) => {
+            if let Some(yank) = Yank::from_key(c) {
+                app.yank(yank)
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Handles the key events of the search bar.
+fn handle_search_key_events(key_event: KeyEvent, app: &mut App) {
+    match (key_event.modifiers, key_event.code) {
+        (KeyModifiers::CONTROL, KeyCode::Char('c')) => app.quit(),
+        (_, KeyCode::Esc) => app.clear_search(),
+        // keep the search
+        (_, KeyCode::Enter) => app.close_popup(),
+        (_, KeyCode::Down) => app.down(),
+        (_, KeyCode::Up) => app.up(),
+        (_, KeyCode::Backspace) => app.search_pop(),
+        (_, KeyCode::Char(c)) => app.search_push(c),
+        _ => {}
+    }
+}
+
+/// Handles the key events of the column chooser.
+fn handle_columns_key_events(key_event: KeyEvent, app: &mut App) {
+    match (key_event.modifiers, key_event.code) {
+        (KeyModifiers::CONTROL, KeyCode::Char('c')) => app.quit(),
+        (_, KeyCode::Esc | KeyCode::Char('q') | KeyCode::Enter) => app.save_columns(),
+        (_, KeyCode::Char('j') | KeyCode::Down) => app.move_column_cursor(1),
+        (_, KeyCode::Char('k') | KeyCode::Up) => app.move_column_cursor(-1),
+        (_, KeyCode::Char(' ')) => app.toggle_column(),
+        _ => {}
+    }
+}
+
+/// Handles the key events of the context menu.
+fn handle_menu_key_events(key_event: KeyEvent, app: &mut App) {
+    match (key_event.modifiers, key_event.code) {
+        (KeyModifiers::CONTROL, KeyCode::Char('c')) => app.quit(),
+        (_, KeyCode::Esc | KeyCode::Char('q')) => app.close_popup(),
+        (_, KeyCode::Char('j') | KeyCode::Down) => app.move_menu_cursor(1),
+        (_, KeyCode::Char('k') | KeyCode::Up) => app.move_menu_cursor(-1),
+        (_, KeyCode::Enter) => app.run_selected_menu_action(),
+        _ => {}
+    }
+}
+
+/// Handles the key events of the confirmation popup.
+fn handle_confirm_key_events(key_event: KeyEvent, app: &mut App) {
+    match (key_event.modifiers, key_event.code) {
+        (KeyModifiers::CONTROL, KeyCode::Char('c')) => app.quit(),
+        (_, KeyCode::Char('y') | KeyCode::Enter) => app.confirm_action(),
+        (_, KeyCode::Esc | KeyCode::Char('n' | 'q')) => app.close_popup(),
+        _ => {}
+    }
+}
+
+/// Handles the key events of the conflicting routes popup.
+fn handle_switch_key_events(key_event: KeyEvent, app: &mut App) {
+    match (key_event.modifiers, key_event.code) {
+        (KeyModifiers::CONTROL, KeyCode::Char('c')) => app.quit(),
+        (_, KeyCode::Char('s') | KeyCode::Enter) => app.switch_connection(),
+        (_, KeyCode::Char('c')) => app.connect_anyway(),
+        (_, KeyCode::Esc | KeyCode::Char('q')) => app.close_popup(),
+        _ => {}
+    }
+}
+
+/// Handles the key events of the comparison of a file with its running interface.
+fn handle_drift_key_events(key_event: KeyEvent, app: &mut App) {
+    match (key_event.modifiers, key_event.code) {
+        (KeyModifiers::CONTROL, KeyCode::Char('c')) => app.quit(),
+        (_, KeyCode::Char('a')) => app.apply_file(),
+        (_, KeyCode::Char('w')) => app.save_running(),
+        (_, KeyCode::Esc | KeyCode::Char('q')) => app.close_popup(),
+        _ => {}
+    }
+}
+
+/// Handles the key events of the import popup.
+fn handle_import_key_events(key_event: KeyEvent, app: &mut App) {
+    match (key_event.modifiers, key_event.code) {
+        (KeyModifiers::CONTROL, KeyCode::Char('c')) => app.quit(),
+        (_, KeyCode::Esc) => app.close_popup(),
+        (_, KeyCode::Enter) => app.save_import(),
+        (_, KeyCode::Backspace) => {
+            if let Some(input) = app.input_mut() {
+                input.pop()
+            }
+        }
+        (_, KeyCode::Char(c)) => {
+            if let Some(input) = app.input_mut() {
+                input.push(c)
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Handles the mouse events and updates the state of [`App`].
+pub fn handle_mouse_events(mouse_event: MouseEvent, app: &mut App) -> AppResult<()> {
+    let (column, row) = (mouse_event.column, mouse_event.row);
+
+    match mouse_event.kind {
+        MouseEventKind::Down(MouseButton::Left) => app.click(column, row),
+        MouseEventKind::Down(MouseButton::Right) => app.right_click(column, row),
+        MouseEventKind::ScrollDown if matches!(app.state(), State::Main) => app.down(),
+        MouseEventKind::ScrollUp if matches!(app.state(), State::Main) => app.up(),
+        _ => {}
+    }
     Ok(())
 }
@@ -1,21 +1,30 @@
 use ratatui::Terminal;
 use ratatui::backend::CrosstermBackend;
-use std::io;
-use wtui::app::{App, AppResult};
+use std::{env, io};
+use wtui::app::{App, AppResult, helper};
 use wtui::event::{Event, EventHandler};
-use wtui::handler::handle_key_events;
+use wtui::handler::{handle_key_events, handle_mouse_events};
 use wtui::tui::Tui;
 
 fn main() -> AppResult<()> {
-    // Create an application.
-    let mut app = App::new()?;
+    let args: Vec<String> = env::args().skip(1).collect();
+    // Serve the connections to unprivileged instances, as root.
+    if args.first().is_some_and(|arg| arg == "--helper") {
+        return Ok(helper::serve(
+            args.get(1).map_or(helper::GROUP, String::as_str),
+        )?);
+    }
+
+    // Create an application, with the configurations given as arguments if any.
+    let paths = args;
+    let mut app = App::new(&paths)?;
 
     // Initialize the terminal user interface.
     let backend = CrosstermBackend::new(io::stderr());
     let terminal = Terminal::new(backend)?;
     let events = EventHandler::new(250);
     let mut tui = Tui::new(terminal, events);
-    tui.init()?;
+    tui.init(app.mouse_capture())?;
 
     // Start the main loop.
     while app.running {
@@ -25,8 +34,9 @@ fn main() -> AppResult<()> {
         match tui.events.next()? {
             Event::Tick => app.tick(),
             Event::Key(key_event) => handle_key_events(key_event, &mut app)?,
-            Event::Mouse(_) => {}
+            Event::Mouse(mouse_event) => handle_mouse_events(mouse_event, &mut app)?,
             Event::Resize(_, _) => {}
+            Event::Paste(text) => app.paste(&text),
         }
     }
 
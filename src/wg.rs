@@ -5,6 +5,7 @@
 mod connection_status;
 mod interface;
 mod peer;
+pub mod runtime;
 pub mod types;
 mod wgconfig;
 
@@ -1,25 +1,58 @@
-use std::{
-    char,
-    io::BufRead,
-    process::{Command, Stdio},
-};
+use std::{char, net::SocketAddr};
 
 use chrono::{DateTime, Local, TimeDelta, Utc};
 
 use super::types::Byte;
 
+/// Age of a session after which wireguard renews it with a new handshake, when sending data.
+pub const REKEY_AFTER_TIME: TimeDelta = TimeDelta::seconds(120);
+
+/// Age after which a handshake is considered stale.
+///
+/// [`REKEY_AFTER_TIME`], plus time for a keepalive to trigger the handshake, its retries and
+/// jitter.
+pub const STALE_HANDSHAKE: TimeDelta = REKEY_AFTER_TIME
+    .checked_add(&TimeDelta::seconds(60))
+    .unwrap();
+
+/// Health of a connection, from its latest handshake.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Health {
+    Down,
+    /// Up, but the peer never answered.
+    NoHandshake,
+    /// Up, but the latest handshake is older than [`STALE_HANDSHAKE`].
+    Stale,
+    Healthy,
+}
+
+impl Health {
+    pub fn label(self) -> &'static str {
+        match self {
+            Health::Down => "down",
+            Health::NoHandshake => "no handshake",
+            Health::Stale => "stale",
+            Health::Healthy => "up",
+        }
+    }
+}
+
 /// The status of the wireguard connection.
 ///
 /// Can be either disconnected or connected with data.
 #[derive(Default)]
 pub enum ConnectionStatus {
     Connected {
-        /// The timestamp of the latest handshake
-        latest_handshake: DateTime<Utc>,
+        /// The timestamp of the latest handshake, [None] if the peer never answered.
+        latest_handshake: Option<DateTime<Utc>>,
         /// The number of bytes received through this connection/interface.
         bytes_received: Byte,
         /// The number of bytes sent through this connection/interface.
         bytes_sent: Byte,
+        /// Where the kernel currently sends the packets, changes when the peer roams.
+        ///
+        /// [None] until the peer is reached.
+        endpoint: Option<SocketAddr>,
     },
     #[default]
     Disconnected,
@@ -53,48 +86,92 @@ impl ConnectionStatus {
         }
     }
 
-    /// Update this connection information from the given name.
+    /// Time elapsed since the latest handshake.
+    ///
+    /// [None] if the connection is disconnected or never had a handshake.
+    pub fn handshake_elapsed(&self) -> Option<TimeDelta> {
+        match self {
+            ConnectionStatus::Connected {
+                latest_handshake: Some(latest_handshake),
+                ..
+            } => Some(Utc::now().signed_duration_since(latest_handshake)),
+            _ => None,
+        }
+    }
+
+    pub fn health(&self) -> Health {
+        match self {
+            ConnectionStatus::Disconnected => Health::Down,
+            ConnectionStatus::Connected {
+                latest_handshake: None,
+                ..
+            } => Health::NoHandshake,
+            ConnectionStatus::Connected { .. } => match self.handshake_elapsed() {
+                Some(elapsed) if elapsed > STALE_HANDSHAKE => Health::Stale,
+                _ => Health::Healthy,
+            },
+        }
+    }
+
+    /// Tells whether the connection is connected but its latest handshake is too old.
+    pub fn is_stale(&self) -> bool {
+        self.health() == Health::Stale
+    }
+
+    /// Where the kernel currently sends the packets.
     ///
-    /// If anything fails inside of this method, the connection is considered disconnected.
-    pub fn update(&mut self, connection_name: &str) {
-        let result = Command::new("wg")
-            .arg("show")
-            .arg(connection_name)
-            .arg("dump")
-            .stderr(Stdio::null())
-            .output();
-
-        match result {
-            Ok(res) if res.status.success() => {
-                let Some(second_line) = res.stdout.lines().nth(1) else {
+    /// [None] if the connection is disconnected or the peer wasn't reached yet.
+    pub fn endpoint(&self) -> Option<SocketAddr> {
+        match self {
+            ConnectionStatus::Connected { endpoint, .. } => *endpoint,
+            ConnectionStatus::Disconnected => None,
+        }
+    }
+
+    /// Update this connection information from the output of `wg show <name> dump`.
+    ///
+    /// If the dump is missing, the connection is considered disconnected.
+    pub fn update(&mut self, dump: Option<&str>) {
+        match dump {
+            Some(dump) => {
+                let Some(second_line) = dump.lines().nth(1) else {
                     *self = ConnectionStatus::Disconnected;
                     return;
                 };
-                let second_line = second_line.expect("wg always returns valid utf-8");
                 let data: Vec<_> = second_line.split(char::is_whitespace).collect();
 
                 *self = ConnectionStatus::Connected {
-                    latest_handshake: DateTime::from_timestamp(
-                        data.get(4).unwrap_or(&"0").parse().unwrap(),
-                        0,
-                    )
-                    .expect("wg always returns a valid timestamp"),
+                    // 0 until the first handshake
+                    latest_handshake: data
+                        .get(4)
+                        .and_then(|t| t.parse().ok())
+                        .filter(|&t| t != 0)
+                        .and_then(|t| DateTime::from_timestamp(t, 0)),
                     bytes_received: data.get(5).unwrap_or(&"0").parse().unwrap_or(0.into()),
                     bytes_sent: data.get(6).unwrap_or(&"0").parse().unwrap_or(0.into()),
+                    // `(none)` until the peer is reached
+                    endpoint: data.get(2).and_then(|e| e.parse().ok()),
                 }
             }
-            _ => *self = ConnectionStatus::Disconnected,
+            None => *self = ConnectionStatus::Disconnected,
         }
     }
 
     /// Human readable representation of the elapsed time since the latest handshake.
+    ///
+    /// `never` if the peer never answered.
     pub fn handshake_since(&self) -> String {
         use chrono::format::*;
 
         let latest_handshake = match self {
             ConnectionStatus::Connected {
-                latest_handshake, ..
+                latest_handshake: Some(latest_handshake),
+                ..
             } => latest_handshake,
+            ConnectionStatus::Connected {
+                latest_handshake: None,
+                ..
+            } => return "never".to_string(),
             ConnectionStatus::Disconnected => return Default::default(),
         };
 
@@ -146,31 +223,71 @@ mod tests {
     #[test]
     fn is_connected() {
         let status = ConnectionStatus::Connected {
-            latest_handshake: DateTime::from_timestamp(0, 0).expect("0 is a valid timestamp"),
+            latest_handshake: None,
             bytes_received: 0.into(),
             bytes_sent: 0.into(),
+            endpoint: None,
         };
 
         let status2 = ConnectionStatus::Connected {
-            latest_handshake: DateTime::from_timestamp(1720868567, 0)
-                .expect("1720868567 is a valid timestamp"),
+            latest_handshake: Some(
+                DateTime::from_timestamp(1720868567, 0).expect("1720868567 is a valid timestamp"),
+            ),
             bytes_received: 1286.into(),
             bytes_sent: 1645.into(),
+            endpoint: None,
         };
 
         assert!(status.is_connected());
         assert!(status2.is_connected());
     }
 
+    #[test]
+    fn is_stale() {
+        let status = |ago| ConnectionStatus::Connected {
+            latest_handshake: Some(Utc::now() - ago),
+            bytes_received: 0.into(),
+            bytes_sent: 0.into(),
+            endpoint: None,
+        };
+
+        assert!(!status(TimeDelta::seconds(10)).is_stale());
+        assert!(status(TimeDelta::minutes(10)).is_stale());
+        assert!(!ConnectionStatus::Disconnected.is_stale());
+    }
+
+    #[test]
+    fn health() {
+        let status = |latest_handshake| ConnectionStatus::Connected {
+            latest_handshake,
+            bytes_received: 0.into(),
+            bytes_sent: 0.into(),
+            endpoint: None,
+        };
+
+        assert_eq!(ConnectionStatus::Disconnected.health(), Health::Down);
+        assert_eq!(status(None).health(), Health::NoHandshake);
+        assert_eq!(status(None).handshake_since(), "never");
+        assert_eq!(
+            status(Some(Utc::now() - TimeDelta::minutes(10))).health(),
+            Health::Stale
+        );
+        assert_eq!(
+            status(Some(Utc::now() - TimeDelta::seconds(30))).health(),
+            Health::Healthy
+        );
+    }
+
     mod latest_handshake {
         use super::*;
 
         #[test]
         fn one_second_ago() {
             let status = ConnectionStatus::Connected {
-                latest_handshake: (Local::now() - TimeDelta::seconds(1)).into(),
+                latest_handshake: Some((Local::now() - TimeDelta::seconds(1)).into()),
                 bytes_received: 0.into(),
                 bytes_sent: 0.into(),
+                endpoint: None,
             };
 
             assert_eq!(status.handshake_since(), "1s ago")
@@ -179,9 +296,10 @@ mod tests {
         #[test]
         fn one_minute_ago() {
             let status = ConnectionStatus::Connected {
-                latest_handshake: (Local::now() - TimeDelta::seconds(61)).into(),
+                latest_handshake: Some((Local::now() - TimeDelta::seconds(61)).into()),
                 bytes_received: 0.into(),
                 bytes_sent: 0.into(),
+                endpoint: None,
             };
 
             assert_eq!(status.handshake_since(), "1m, 1s ago")
@@ -190,9 +308,10 @@ mod tests {
         #[test]
         fn one_hour_ago() {
             let status = ConnectionStatus::Connected {
-                latest_handshake: (Local::now() - TimeDelta::minutes(61)).into(),
+                latest_handshake: Some((Local::now() - TimeDelta::minutes(61)).into()),
                 bytes_received: 0.into(),
                 bytes_sent: 0.into(),
+                endpoint: None,
             };
 
             assert_eq!(status.handshake_since(), "1h, 1m ago")
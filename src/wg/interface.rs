@@ -1,7 +1,7 @@
 use std::net::IpAddr;
 
 use super::types::{
-    Key,
+    Cidr, Key,
     key::{Private, Public},
 };
 
@@ -9,29 +9,99 @@ use super::types::{
 pub struct Interface {
     private_key: Key<Private>,
     pubkey: Key<Public>,
-    address: String,
-    mtu: u16,
+    address: Vec<Cidr>,
+    /// UDP port to listen on, random if [None].
+    listen_port: Option<u16>,
+    /// [None] to let the kernel choose.
+    mtu: Option<u16>,
     dns: Vec<IpAddr>,
+    /// Mark of the packets sent by the interface, to route them outside of the tunnel.
+    fwmark: Option<u32>,
+    /// Routing table of the AllowedIPs routes, `off` to not add routes.
+    table: Option<String>,
+    /// Commands run by `wg-quick` around bringing the interface up and down, in file order.
+    hooks: Vec<(Hook, String)>,
+}
+
+/// When a hook command runs.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Hook {
+    PreUp,
+    PostUp,
+    PreDown,
+    PostDown,
+}
+
+impl Hook {
+    pub const ALL: [Hook; 4] = [Hook::PreUp, Hook::PostUp, Hook::PreDown, Hook::PostDown];
+
+    /// Key of the hook in the configuration.
+    pub fn key(self) -> &'static str {
+        match self {
+            Hook::PreUp => "PreUp",
+            Hook::PostUp => "PostUp",
+            Hook::PreDown => "PreDown",
+            Hook::PostDown => "PostDown",
+        }
+    }
 }
 
 impl Interface {
-    pub fn new(private_key: &str, address: &str) -> Self {
+    pub fn new(private_key: &str, address: Vec<Cidr>) -> Self {
         let private_key: Key<Private> = private_key.into();
 
         Self {
             private_key: private_key.clone(),
-            pubkey: private_key.fetch_pubkey().expect("Failed to get pubkey"),
-            address: address.into(),
-            mtu: 1500,
+            pubkey: private_key.public_key(),
+            address,
+            listen_port: None,
+            mtu: None,
             dns: Vec::new(),
+            fwmark: None,
+            table: None,
+            hooks: Vec::new(),
         }
     }
 
-    pub fn address(&self) -> &str {
+    /// Addresses of the interface, with the prefix of their network.
+    pub fn address(&self) -> &[Cidr] {
         &self.address
     }
 
+    pub fn listen_port(&self) -> Option<u16> {
+        self.listen_port
+    }
+
+    pub fn set_listen_port(mut self, listen_port: Option<u16>) -> Self {
+        self.listen_port = listen_port;
+        self
+    }
+
+    /// Every hook, in file order.
+    pub fn hooks(&self) -> &[(Hook, String)] {
+        &self.hooks
+    }
+
+    /// Commands of the `hook` hooks.
+    pub fn commands(&self, hook: Hook) -> impl Iterator<Item = &str> {
+        self.hooks
+            .iter()
+            .filter(move |(h, _)| *h == hook)
+            .map(|(_, command)| command.as_str())
+    }
+
+    pub fn set_hooks(mut self, hooks: Vec<(Hook, String)>) -> Self {
+        self.hooks = hooks;
+        self
+    }
+
+    /// The MTU, 1500 if not set.
     pub fn mtu(&self) -> u16 {
+        self.mtu.unwrap_or(1500)
+    }
+
+    /// The MTU set in the configuration.
+    pub fn configured_mtu(&self) -> Option<u16> {
         self.mtu
     }
 
@@ -44,15 +114,44 @@ impl Interface {
         self
     }
 
-    pub fn set_mtu(mut self, mtu: u16) -> Self {
+    pub fn set_mtu(mut self, mtu: Option<u16>) -> Self {
         self.mtu = mtu;
         self
     }
 
+    pub fn fwmark(&self) -> Option<u32> {
+        self.fwmark
+    }
+
+    pub fn set_fwmark(mut self, fwmark: Option<u32>) -> Self {
+        self.fwmark = fwmark;
+        self
+    }
+
+    pub fn table(&self) -> Option<&str> {
+        self.table.as_deref()
+    }
+
+    pub fn set_table(mut self, table: Option<String>) -> Self {
+        self.table = table;
+        self
+    }
+
+    /// Tells whether `wg-quick` adds routes for the AllowedIPs.
+    pub fn adds_routes(&self) -> bool {
+        self.table.as_deref() != Some("off")
+    }
+
     pub fn pubkey(&self) -> &Key<Public> {
         &self.pubkey
     }
 
+    /// Replaces the public key, for configurations whose private key is hidden.
+    pub fn set_pubkey(mut self, pubkey: Key<Public>) -> Self {
+        self.pubkey = pubkey;
+        self
+    }
+
     pub fn private_key(&self) -> &Key<Private> {
         &self.private_key
     }
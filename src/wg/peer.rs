@@ -1,27 +1,65 @@
-use super::types::{Key, key::Public};
+use super::types::{
+    Cidr, Endpoint, Key,
+    key::{Private, Public},
+};
 
 pub struct Peer {
     pubkey: Key<Public>,
-    allowed_ips: Vec<String>,
-    /// Actual hostname or IP
-    endpoint: String,
+    allowed_ips: Vec<Cidr>,
+    /// Where to reach the peer, [None] if the peer connects to us.
+    endpoint: Option<Endpoint>,
+    /// Additional symmetric secret, kept private like a private key.
+    preshared_key: Option<Key<Private>>,
+    /// Seconds between keepalive packets, [None] if off.
+    persistent_keepalive: Option<u16>,
 }
 
 impl Peer {
-    pub fn new(public_key: &str, allowed_ips: Vec<String>, endpoint: String) -> Self {
+    pub fn new(public_key: &str, allowed_ips: Vec<Cidr>, endpoint: Option<Endpoint>) -> Self {
         Self {
             pubkey: public_key.into(),
             allowed_ips,
             endpoint,
+            preshared_key: None,
+            persistent_keepalive: None,
         }
     }
 
-    pub fn allowed_ips(&self) -> &[String] {
+    pub fn preshared_key(&self) -> Option<&Key<Private>> {
+        self.preshared_key.as_ref()
+    }
+
+    pub fn set_preshared_key(mut self, preshared_key: Option<Key<Private>>) -> Self {
+        self.preshared_key = preshared_key;
+        self
+    }
+
+    pub fn persistent_keepalive(&self) -> Option<u16> {
+        self.persistent_keepalive
+    }
+
+    pub fn set_persistent_keepalive(mut self, persistent_keepalive: Option<u16>) -> Self {
+        self.persistent_keepalive = persistent_keepalive;
+        self
+    }
+
+    pub fn allowed_ips(&self) -> &[Cidr] {
         &self.allowed_ips
     }
 
-    pub fn endpoint(&self) -> &str {
-        &self.endpoint
+    /// Allowed IPs of this peer that are also allowed by `other`.
+    ///
+    /// Connecting both peers at the same time makes their routes conflict.
+    pub fn overlapping(&self, other: &Peer) -> Vec<Cidr> {
+        self.allowed_ips
+            .iter()
+            .filter(|network| other.allowed_ips.iter().any(|o| network.overlaps(o)))
+            .copied()
+            .collect()
+    }
+
+    pub fn endpoint(&self) -> Option<&Endpoint> {
+        self.endpoint.as_ref()
     }
 
     pub fn pubkey(&self) -> &Key<Public> {
@@ -0,0 +1,414 @@
+//! The configuration the kernel runs, read from `wg show` and `ip address`.
+use std::{
+    collections::HashMap,
+    fmt::{self, Display},
+    io::{self, Write},
+    process::{Command, Stdio},
+};
+
+use super::{
+    Interface, WgConfig, strip,
+    types::{Cidr, Key, key::Private},
+    with_value,
+};
+
+/// Route changes made by [apply].
+#[derive(Debug, Default, PartialEq)]
+pub struct Applied {
+    pub added: Vec<Cidr>,
+    pub removed: Vec<Cidr>,
+    /// Default routes added or removed: they need the policy routing of `wg-quick`, set when
+    /// connecting.
+    pub skipped: Vec<Cidr>,
+}
+
+impl Display for Applied {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts = Vec::new();
+        if !self.added.is_empty() {
+            parts.push(format!("routes added: {}", Cidr::join(&self.added, ", ")));
+        }
+        if !self.removed.is_empty() {
+            parts.push(format!(
+                "routes removed: {}",
+                Cidr::join(&self.removed, ", ")
+            ));
+        }
+        if !self.skipped.is_empty() {
+            parts.push(format!(
+                "reconnect to route {}",
+                Cidr::join(&self.skipped, ", ")
+            ));
+        }
+        match parts.is_empty() {
+            true => write!(f, "routes unchanged"),
+            false => write!(f, "{}", parts.join("; ")),
+        }
+    }
+}
+
+/// The configuration the `name` interface runs.
+///
+/// Keys only known to `wg-quick` (DNS, MTU, Table and the hooks) are missing, and only the first
+/// peer is kept.
+pub fn config(name: &str) -> io::Result<WgConfig> {
+    let dump = dump(name)?;
+    let addresses = output("ip", &["-o", "address", "show", "dev", name])?;
+    Ok(WgConfig::from(
+        to_config(&dump, &parse_addresses(&addresses)).as_str(),
+    ))
+}
+
+/// The output of `wg show <name> dump`, an error if the interface is down.
+pub fn dump(name: &str) -> io::Result<String> {
+    output("wg", &["show", name, "dump"])
+}
+
+/// The output of `wg show all dump`: the lines of every interface, prefixed by its name.
+pub fn dump_all() -> io::Result<String> {
+    output("wg", &["show", "all", "dump"])
+}
+
+/// Splits `wg show all dump` into the `wg show <name> dump` of each interface.
+pub fn split_dumps(all: &str) -> HashMap<String, String> {
+    let mut dumps: HashMap<String, String> = HashMap::new();
+    for line in all.lines() {
+        if let Some((name, fields)) = line.split_once('\t') {
+            let dump = dumps.entry(name.to_string()).or_default();
+            dump.push_str(fields);
+            dump.push('\n');
+        }
+    }
+    dumps
+}
+
+/// What a `wg show <name> dump` configures, without the handshakes and transfers which change
+/// all the time.
+pub fn dump_settings(dump: &str) -> String {
+    to_config(dump, &[])
+}
+
+/// Sets the private key of the running `name` interface, like `wg set <name> private-key`.
+pub fn set_private_key(name: &str, key: &Key<Private>) -> io::Result<()> {
+    let mut child = Command::new("wg")
+        .args(["set", name, "private-key", "/dev/stdin"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    child
+        .stdin
+        .take()
+        .expect("stdin is piped")
+        .write_all(key.as_bytes())?;
+
+    match child.wait()?.success() {
+        true => Ok(()),
+        false => Err(io::Error::other("wg set failed")),
+    }
+}
+
+/// Makes the `name` interface run the raw configuration `text`, stripped of the keys of
+/// `wg-quick`, like `wg syncconf`.
+///
+/// The interface stays up: only what differs changes, e.g. peers whose allowed IPs didn't change
+/// keep their session. Peers missing from `text` are removed. Addresses and routes are left as
+/// they are.
+pub fn sync(name: &str, text: &str) -> io::Result<()> {
+    let mut child = Command::new("wg")
+        .args(["syncconf", name, "/dev/stdin"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    child
+        .stdin
+        .take()
+        .expect("stdin is piped")
+        .write_all(strip(text).as_bytes())?;
+
+    let output = child.wait_with_output()?;
+    match output.status.success() {
+        true => Ok(()),
+        false => Err(io::Error::other(
+            String::from_utf8_lossy(&output.stderr)
+                .lines()
+                .next()
+                .map(str::to_string)
+                .unwrap_or_else(|| format!("wg syncconf {name} failed")),
+        )),
+    }
+}
+
+/// Applies the raw configuration `text`, of every peer, to the running `name` interface without
+/// bringing it down.
+///
+/// The wg settings are [synced](sync), then routes are added for the new allowed IPs and removed
+/// for the dropped ones, in the table `wg-quick` uses. Addresses, DNS, MTU and the hooks are left
+/// as they are.
+pub fn apply(name: &str, text: &str) -> io::Result<Applied> {
+    let dump = dump(name)?;
+    // the allowed IPs of every peer, the parser adds them up
+    let config = WgConfig::from(text);
+    sync(name, &with_runtime_keys(text, &config, &dump))?;
+
+    let Some(table) = route_table(&config.interface) else {
+        return Ok(Applied::default());
+    };
+    let mut applied = route_changes(&dump_allowed_ips(&dump), config.peer.allowed_ips());
+    // the kernel routes the networks of the addresses, by itself
+    let connected: Vec<_> = config
+        .interface
+        .address()
+        .iter()
+        .map(Cidr::network)
+        .collect();
+    applied
+        .added
+        .retain(|network| !connected.contains(&network.network()));
+    applied
+        .removed
+        .retain(|network| !connected.contains(&network.network()));
+
+    for network in &applied.added {
+        route("replace", name, network, table)?;
+    }
+    for network in &applied.removed {
+        // already gone if someone removed it by hand
+        let _ = route("delete", name, network, table);
+    }
+    Ok(applied)
+}
+
+/// The raw configuration `text` with the listen port and fwmark of the `dump` when it has none,
+/// for the sync not to reset what the kernel or `wg-quick` picked, e.g. the fwmark of default
+/// routes.
+fn with_runtime_keys(text: &str, config: &WgConfig, dump: &str) -> String {
+    let interface: Vec<_> = dump
+        .lines()
+        .next()
+        .unwrap_or_default()
+        .split('\t')
+        .collect();
+    let mut text = text.to_string();
+    if config.interface.listen_port().is_none()
+        && let Some(port) = set(&interface, 2).filter(|p| *p != "0")
+    {
+        text = with_value(&text, "Interface", "ListenPort", Some(port));
+    }
+    if config.interface.fwmark().is_none()
+        && let Some(fwmark) = set(&interface, 3)
+    {
+        text = with_value(&text, "Interface", "FwMark", Some(fwmark));
+    }
+    text
+}
+
+/// The allowed IPs of every peer of `wg show <name> dump`.
+fn dump_allowed_ips(dump: &str) -> Vec<Cidr> {
+    dump.lines()
+        .skip(1)
+        .filter_map(|line| set(&line.split('\t').collect::<Vec<_>>(), 3).map(str::to_string))
+        .flat_map(|ips| {
+            ips.split(',')
+                .filter_map(|ip| ip.parse().ok())
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// Table of the routes of `interface`, [None] if `Table = off`.
+///
+/// Default routes aside, `wg-quick` adds them to the main table unless `Table` is set.
+fn route_table(interface: &Interface) -> Option<&str> {
+    match interface.table() {
+        Some("off") => None,
+        None | Some("auto") => Some("main"),
+        Some(table) => Some(table),
+    }
+}
+
+/// Routes to add and remove when the allowed IPs go from `before` to `after`.
+fn route_changes(before: &[Cidr], after: &[Cidr]) -> Applied {
+    let mut applied = Applied::default();
+    for network in after.iter().filter(|n| !before.contains(n)) {
+        match network.prefix() {
+            0 => applied.skipped.push(*network),
+            _ => applied.added.push(*network),
+        }
+    }
+    for network in before.iter().filter(|n| !after.contains(n)) {
+        match network.prefix() {
+            0 => applied.skipped.push(*network),
+            _ => applied.removed.push(*network),
+        }
+    }
+    applied
+}
+
+/// Runs `ip route <action>` for the route of `network` through the `name` interface.
+///
+/// Only touches the routes added like `wg-quick` does, with the `boot` protocol, never the ones of
+/// the kernel.
+fn route(action: &str, name: &str, network: &Cidr, table: &str) -> io::Result<()> {
+    let network = network.network().to_string();
+    output(
+        "ip",
+        &[
+            "route", action, &network, "dev", name, "table", table, "proto", "boot",
+        ],
+    )
+    .map(|_| ())
+}
+
+/// Output of a successful command.
+fn output(program: &str, args: &[&str]) -> io::Result<String> {
+    let output = Command::new(program)
+        .args(args)
+        .stderr(Stdio::null())
+        .output()?;
+
+    match output.status.success() {
+        true => Ok(String::from_utf8_lossy(&output.stdout).into_owned()),
+        false => Err(io::Error::other(format!(
+            "{program} {} failed",
+            args.join(" ")
+        ))),
+    }
+}
+
+/// Parses `ip -o address show`, e.g. `5: wg0    inet 10.0.0.2/24 scope global wg0`.
+fn parse_addresses(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut words = line.split_whitespace();
+            words.find(|w| *w == "inet" || *w == "inet6")?;
+            words.next().map(str::to_string)
+        })
+        .collect()
+}
+
+/// Writes `wg show <name> dump` and the `addresses` as a wireguard configuration.
+///
+/// The dump has a line for the interface, `private-key public-key listen-port fwmark`, then one per
+/// peer, `public-key preshared-key endpoint allowed-ips latest-handshake rx tx keepalive`. Unset
+/// values are `(none)`, or `off` for numbers.
+fn to_config(dump: &str, addresses: &[String]) -> String {
+    let mut lines = dump.lines().map(|l| l.split('\t').collect::<Vec<_>>());
+
+    let mut config = String::from("[Interface]\n");
+    let interface = lines.next().unwrap_or_default();
+    if let Some(key) = set(&interface, 0) {
+        config.push_str(&format!("PrivateKey = {key}\n"));
+    }
+    config.push_str(&format!("Address = {}\n", addresses.join(", ")));
+    if let Some(port) = set(&interface, 2).filter(|p| *p != "0") {
+        config.push_str(&format!("ListenPort = {port}\n"));
+    }
+    if let Some(fwmark) = set(&interface, 3) {
+        config.push_str(&format!("FwMark = {fwmark}\n"));
+    }
+
+    config.push_str("\n[Peer]\n");
+    let peer = lines.next().unwrap_or_default();
+    let keys = [
+        (0, "PublicKey"),
+        (1, "PresharedKey"),
+        (2, "Endpoint"),
+        (3, "AllowedIPs"),
+        (7, "PersistentKeepalive"),
+    ];
+    for (index, key) in keys {
+        if let Some(value) = set(&peer, index) {
+            config.push_str(&format!("{key} = {}\n", value.replace(',', ", ")));
+        }
+    }
+
+    config
+}
+
+/// The field at `index`, [None] if unset.
+fn set<'a>(fields: &[&'a str], index: usize) -> Option<&'a str> {
+    fields
+        .get(index)
+        .copied()
+        .filter(|v| !matches!(*v, "(none)" | "off" | ""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn routes() {
+        let cidrs =
+            |list: &str| -> Vec<Cidr> { list.split(',').map(|c| c.parse().unwrap()).collect() };
+        let applied = route_changes(
+            &cidrs("10.1.0.0/16,10.2.0.0/16"),
+            &cidrs("10.2.0.0/16,10.3.0.0/16,0.0.0.0/0"),
+        );
+
+        assert_eq!(applied.added, cidrs("10.3.0.0/16"));
+        assert_eq!(applied.removed, cidrs("10.1.0.0/16"));
+        assert_eq!(applied.skipped, cidrs("0.0.0.0/0"));
+        assert_eq!(
+            applied.to_string(),
+            "routes added: 10.3.0.0/16; routes removed: 10.1.0.0/16; reconnect to route 0.0.0.0/0"
+        );
+        assert_eq!(Applied::default().to_string(), "routes unchanged");
+    }
+
+    #[test]
+    fn runtime_keys() {
+        let dump = "key\tpub\t40123\t0xca6c
+peer1\t(none)\t(none)\t10.1.0.0/16,10.2.0.0/16\t0\t0\t0\toff
+peer2\t(none)\t(none)\t10.3.0.0/16\t0\t0\t0\toff
+";
+        assert_eq!(dump_allowed_ips(dump), {
+            let cidrs: Vec<Cidr> = ["10.1.0.0/16", "10.2.0.0/16", "10.3.0.0/16"]
+                .iter()
+                .map(|c| c.parse().unwrap())
+                .collect();
+            cidrs
+        });
+
+        let text = "[Interface]\nPrivateKey = key\n\n[Peer]\nPublicKey = peer1\n";
+        let kept = with_runtime_keys(text, &WgConfig::from(text), dump);
+        assert!(kept.contains("ListenPort = 40123\n"));
+        assert!(kept.contains("FwMark = 0xca6c\n"));
+
+        let text =
+            "[Interface]\nPrivateKey = key\nListenPort = 51820\n\n[Peer]\nPublicKey = peer1\n";
+        let kept = with_runtime_keys(text, &WgConfig::from(text), dump);
+        assert!(kept.contains("ListenPort = 51820\n"));
+        assert!(!kept.contains("40123"));
+    }
+
+    #[test]
+    fn dump() {
+        let addresses = parse_addresses(
+            "5: wg0    inet 10.0.0.2/24 scope global wg0\\       valid_lft forever preferred_lft forever
+5: wg0    inet6 fd00::2/64 scope global \\       valid_lft forever preferred_lft forever
+",
+        );
+        assert_eq!(addresses, vec!["10.0.0.2/24", "fd00::2/64"]);
+
+        let dump = "oMVUWFwDf+20fIfeRUe7c0rlUKSYnHk2K0y2920SX1c=\tnd3XDfjWvMtcUBQLt6ANT4dzKsGJhb2hrCsVA6SHJVQ=\t51820\toff
+60TUAvOo+Wi4SCyir581cCyBx4wIcHtrIrUgBv/iqRM=\t(none)\t203.0.113.1:51820\t10.1.0.0/16,10.2.0.0/16\t1700000000\t1024\t2048\toff
+";
+        assert_eq!(
+            to_config(dump, &addresses),
+            "[Interface]
+PrivateKey = oMVUWFwDf+20fIfeRUe7c0rlUKSYnHk2K0y2920SX1c=
+Address = 10.0.0.2/24, fd00::2/64
+ListenPort = 51820
+
+[Peer]
+PublicKey = 60TUAvOo+Wi4SCyir581cCyBx4wIcHtrIrUgBv/iqRM=
+Endpoint = 203.0.113.1:51820
+AllowedIPs = 10.1.0.0/16, 10.2.0.0/16
+"
+        );
+    }
+}
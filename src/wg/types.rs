@@ -1,5 +1,9 @@
 pub mod byte;
+pub mod cidr;
+pub mod endpoint;
 pub mod key;
 
 pub use byte::Byte;
+pub use cidr::Cidr;
+pub use endpoint::Endpoint;
 pub use key::Key;
@@ -27,18 +27,35 @@ impl Deref for Byte {
     }
 }
 
-impl From<&Byte> for Text<'_> {
-    fn from(value: &Byte) -> Self {
-        let (number, unit) = match value.0 {
-            ..=1_000 => (value.0 as f32, "B"),
-            1_001..=1_000_000 => (value.0 as f32 / 1_000., "KB"),
-            1_000_001..=1_000_000_000 => (value.0 as f32 / 1_000_000., "MB"),
-            1_000_000_001.. => (value.0 as f32 / 1_000_000_000., "GB"),
-        };
+impl Byte {
+    /// The number of bytes in the most fitting unit.
+    fn humanize(&self) -> (f32, &'static str) {
+        match self.0 {
+            ..=1_000 => (self.0 as f32, "B"),
+            1_001..=1_000_000 => (self.0 as f32 / 1_000., "KB"),
+            1_000_001..=1_000_000_000 => (self.0 as f32 / 1_000_000., "MB"),
+            1_000_000_001.. => (self.0 as f32 / 1_000_000_000., "GB"),
+        }
+    }
+
+    /// Human readable text, with the unit styled with `unit_style`.
+    pub fn text(&self, unit_style: Style) -> Text<'static> {
+        let (number, unit) = self.humanize();
+
+        Line::from(vec![
+            format!("{:.1} ", number).into(),
+            Span::styled(unit, unit_style),
+        ])
+        .into()
+    }
+
+    /// Text of this number of bytes as a rate per second.
+    pub fn rate_text(&self, unit_style: Style) -> Text<'static> {
+        let (number, unit) = self.humanize();
 
         Line::from(vec![
             format!("{:.1} ", number).into(),
-            Span::from(unit).cyan(),
+            Span::styled(format!("{unit}/s"), unit_style),
         ])
         .into()
     }
@@ -0,0 +1,145 @@
+use std::{
+    fmt::{self, Display},
+    net::IpAddr,
+    str::FromStr,
+};
+
+/// An IP network in CIDR notation, e.g. `10.0.0.0/8`.
+///
+/// A bare address is a network of only this address.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Cidr {
+    addr: IpAddr,
+    prefix: u8,
+}
+
+impl Cidr {
+    pub fn addr(&self) -> IpAddr {
+        self.addr
+    }
+
+    pub fn prefix(&self) -> u8 {
+        self.prefix
+    }
+
+    /// Writes the networks separated by `separator`.
+    pub fn join(networks: &[Cidr], separator: &str) -> String {
+        networks
+            .iter()
+            .map(Cidr::to_string)
+            .collect::<Vec<_>>()
+            .join(separator)
+    }
+
+    /// The network, with the host bits of the address cleared, e.g. `10.0.0.0/8` for `10.1.2.3/8`.
+    pub fn network(&self) -> Cidr {
+        let addr = match self.addr {
+            IpAddr::V4(addr) => {
+                let mask = u32::MAX.checked_shl(32 - self.prefix as u32).unwrap_or(0);
+                IpAddr::V4((u32::from(addr) & mask).into())
+            }
+            IpAddr::V6(addr) => {
+                let mask = u128::MAX.checked_shl(128 - self.prefix as u32).unwrap_or(0);
+                IpAddr::V6((u128::from(addr) & mask).into())
+            }
+        };
+        Cidr {
+            addr,
+            prefix: self.prefix,
+        }
+    }
+
+    /// Tells whether `ip` is in this network.
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        // checked first, prefixes of one family don't fit the other
+        self.addr.is_ipv4() == ip.is_ipv4()
+            && self.network()
+                == Cidr {
+                    addr: ip,
+                    prefix: self.prefix,
+                }
+                .network()
+    }
+
+    /// Tells whether the two networks share at least one address.
+    pub fn overlaps(&self, other: &Cidr) -> bool {
+        match self.prefix <= other.prefix {
+            true => self.contains(other.addr),
+            false => other.contains(self.addr),
+        }
+    }
+}
+
+impl FromStr for Cidr {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (addr, prefix) = match s.trim().split_once('/') {
+            Some((addr, prefix)) => (addr, Some(prefix)),
+            None => (s.trim(), None),
+        };
+
+        let addr: IpAddr = addr.parse().map_err(|_| format!("invalid address: {s}"))?;
+        let max = match addr {
+            IpAddr::V4(_) => 32,
+            IpAddr::V6(_) => 128,
+        };
+        let prefix = match prefix {
+            Some(prefix) => prefix
+                .parse()
+                .ok()
+                .filter(|&p| p <= max)
+                .ok_or_else(|| format!("invalid prefix length: {s}"))?,
+            None => max,
+        };
+
+        Ok(Self { addr, prefix })
+    }
+}
+
+impl From<IpAddr> for Cidr {
+    /// The network of only `addr`.
+    fn from(addr: IpAddr) -> Self {
+        let prefix = match addr {
+            IpAddr::V4(_) => 32,
+            IpAddr::V6(_) => 128,
+        };
+        Self { addr, prefix }
+    }
+}
+
+impl Display for Cidr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.addr, self.prefix)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cidr(s: &str) -> Cidr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!(cidr("10.0.0.0/8").prefix(), 8);
+        assert_eq!(cidr("10.0.0.1").prefix(), 32);
+        assert_eq!(cidr("fd00::/64").to_string(), "fd00::/64");
+        assert!("10.0.0.0/33".parse::<Cidr>().is_err());
+        assert!("vpn.example.com".parse::<Cidr>().is_err());
+        assert_eq!(cidr("192.168.1.23/24").network(), cidr("192.168.1.0/24"));
+    }
+
+    #[test]
+    fn overlaps() {
+        assert!(cidr("0.0.0.0/0").overlaps(&cidr("0.0.0.0/0")));
+        assert!(cidr("0.0.0.0/0").overlaps(&cidr("192.168.1.0/24")));
+        assert!(cidr("192.168.1.0/24").overlaps(&cidr("192.168.0.0/16")));
+        assert!(cidr("192.168.1.0/24").overlaps(&cidr("192.168.1.7")));
+        assert!(!cidr("192.168.1.0/24").overlaps(&cidr("192.168.2.0/24")));
+        assert!(!cidr("0.0.0.0/0").overlaps(&cidr("::/0")));
+        assert!(cidr("::/0").overlaps(&cidr("fd00::/64")));
+    }
+}
@@ -0,0 +1,109 @@
+use std::{
+    fmt::{self, Display},
+    io,
+    net::{IpAddr, ToSocketAddrs},
+    str::FromStr,
+};
+
+/// Address of a peer, a hostname or an IP, and a port.
+///
+/// IPv6 addresses are written between brackets, e.g. `[2001:db8::1]:51820`.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Endpoint {
+    host: String,
+    port: u16,
+}
+
+impl Endpoint {
+    /// Hostname or IP, without brackets.
+    pub fn host(&self) -> &str {
+        &self.host
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    /// The host if it's an IP.
+    pub fn ip(&self) -> Option<IpAddr> {
+        self.host.parse().ok()
+    }
+
+    /// Resolves the host to its IPs.
+    ///
+    /// Blocks until the resolver answers.
+    pub fn resolve(&self) -> io::Result<Vec<IpAddr>> {
+        let mut ips: Vec<IpAddr> = (self.host.as_str(), self.port)
+            .to_socket_addrs()?
+            .map(|addr| addr.ip())
+            .collect();
+        ips.dedup();
+        Ok(ips)
+    }
+}
+
+impl FromStr for Endpoint {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (host, port) = s
+            .rsplit_once(':')
+            .ok_or_else(|| format!("missing port: {s}"))?;
+
+        let host = match host.strip_prefix('[') {
+            Some(host) => host
+                .strip_suffix(']')
+                .ok_or_else(|| format!("unclosed bracket: {s}"))?,
+            // a bare IPv6 is ambiguous with the port
+            None if host.contains(':') => return Err(format!("IPv6 needs brackets: {s}")),
+            None => host,
+        };
+        if host.is_empty() {
+            return Err(format!("missing host: {s}"));
+        }
+
+        Ok(Self {
+            host: host.to_string(),
+            port: port.parse().map_err(|_| format!("invalid port: {s}"))?,
+        })
+    }
+}
+
+impl Display for Endpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.host.contains(':') {
+            true => write!(f, "[{}]:{}", self.host, self.port),
+            false => write!(f, "{}:{}", self.host, self.port),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv6Addr;
+
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let endpoint: Endpoint = "vpn.example.com:51820".parse().unwrap();
+        assert_eq!(endpoint.host(), "vpn.example.com");
+        assert_eq!(endpoint.port(), 51820);
+        assert_eq!(endpoint.ip(), None);
+
+        let endpoint: Endpoint = "[2001:db8::1]:51820".parse().unwrap();
+        assert_eq!(endpoint.host(), "2001:db8::1");
+        assert_eq!(
+            endpoint.ip(),
+            Some(IpAddr::V6(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1)))
+        );
+        assert_eq!(endpoint.to_string(), "[2001:db8::1]:51820");
+
+        assert!("vpn.example.com".parse::<Endpoint>().is_err());
+        assert!("2001:db8::1:51820".parse::<Endpoint>().is_err());
+        assert!("[2001:db8::1:51820".parse::<Endpoint>().is_err());
+        assert!(":51820".parse::<Endpoint>().is_err());
+        assert!("vpn.example.com:port".parse::<Endpoint>().is_err());
+    }
+}
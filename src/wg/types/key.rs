@@ -1,10 +1,13 @@
 use std::{
-    io::{self, Write},
+    fs::File,
+    io::{self, Read},
     marker::PhantomData,
     ops::Deref,
-    process::{Command, Stdio},
 };
 
+use base64::{Engine, prelude::BASE64_STANDARD};
+use x25519_dalek::{PublicKey, StaticSecret};
+
 #[derive(Clone, Debug, PartialEq)]
 pub struct Key<T: KeyType>(String, PhantomData<T>);
 
@@ -17,32 +20,53 @@ impl<T: KeyType> Key<T> {
         format!(
             "{}..{}",
             self.0.get(..4).unwrap_or_default(),
-            self.0.get(self.0.len() - 4..).unwrap_or_default(),
+            self.0
+                .get(self.0.len().saturating_sub(4)..)
+                .unwrap_or_default(),
         )
     }
+
+    /// Tells whether the key looks like a valid wireguard key.
+    ///
+    /// A wireguard key is 32 bytes encoded in base64, i.e. 43 base64 chars followed by a `=`.
+    pub fn is_valid(&self) -> bool {
+        self.0.len() == 44
+            && self.0.ends_with('=')
+            && self
+                .0
+                .bytes()
+                .take(43)
+                .all(|b| b.is_ascii_alphanumeric() || b == b'+' || b == b'/')
+    }
 }
 
 impl Key<Private> {
-    /// Gets the public key from the private key using `wg` command.
-    pub(crate) fn fetch_pubkey(&self) -> io::Result<Key<Public>> {
-        let mut child = Command::new("wg")
-            .arg("pubkey")
-            .stdin(Stdio::piped())
-            .stdout(Stdio::piped())
-            .stderr(Stdio::null())
-            .spawn()?;
-
-        child
-            .stdin
-            .take()
-            .expect("Failed to get stdin")
-            .write_all(self.0.as_bytes())?;
-
-        let output = child.wait_with_output()?;
-
-        String::from_utf8(output.stdout)
-            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
-            .map(Key::from)
+    /// Generates a new private key from `/dev/urandom`, clamped like `wg genkey`.
+    pub fn generate() -> io::Result<Self> {
+        let mut bytes = [0u8; 32];
+        File::open("/dev/urandom")?.read_exact(&mut bytes)?;
+        bytes[0] &= 248;
+        bytes[31] = (bytes[31] & 127) | 64;
+
+        Ok(Key::from(BASE64_STANDARD.encode(bytes)))
+    }
+
+    /// The public key of this private key, like `wg pubkey`.
+    ///
+    /// Empty if the private key is invalid.
+    pub(crate) fn public_key(&self) -> Key<Public> {
+        let bytes = BASE64_STANDARD
+            .decode(&self.0)
+            .ok()
+            .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok());
+
+        match bytes {
+            Some(bytes) => {
+                let public = PublicKey::from(&StaticSecret::from(bytes));
+                Key::from(BASE64_STANDARD.encode(public.as_bytes()))
+            }
+            None => Key::from(""),
+        }
     }
 }
 
@@ -101,4 +125,38 @@ mod tests {
             "CLjhKsWxLTR+N5fs/jMYqVXL7xwtuEzufupX82c7LCs=".to_string()
         )
     }
+
+    #[test]
+    fn truncated() {
+        assert_eq!(
+            Key::<Public>::from("CLjhKsWxLTR+N5fs/jMYqVXL7xwtuEzufupX82c7LCs=").truncated(),
+            "CLjh..LCs="
+        );
+        // missing or invalid keys
+        assert_eq!(Key::<Public>::from("").truncated(), "..");
+        assert_eq!(Key::<Public>::from("abc").truncated(), "..abc");
+    }
+
+    #[test]
+    fn public_key() {
+        assert_eq!(
+            Key::<Private>::from("oMVUWFwDf+20fIfeRUe7c0rlUKSYnHk2K0y2920SX1c=")
+                .public_key()
+                .as_str(),
+            "CLjhKsWxLTR+N5fs/jMYqVXL7xwtuEzufupX82c7LCs="
+        );
+        assert_eq!(Key::<Private>::from("invalid").public_key().as_str(), "");
+
+        let key = Key::<Private>::generate().unwrap();
+        assert!(key.is_valid());
+        assert!(key.public_key().is_valid());
+    }
+
+    #[test]
+    fn is_valid() {
+        assert!(Key::<Public>::from("CLjhKsWxLTR+N5fs/jMYqVXL7xwtuEzufupX82c7LCs=").is_valid());
+        assert!(!Key::<Public>::from("").is_valid());
+        assert!(!Key::<Public>::from("CLjhKsWxLTR+N5fs/jMYqVXL7xwtuEzufupX82c7LCs").is_valid());
+        assert!(!Key::<Public>::from("CLjhKsWxLTR+N5fs/jMYqVXL7xwtuEzufupX82c7L-s=").is_valid());
+    }
 }
@@ -1,32 +1,347 @@
-use std::net::IpAddr;
+use std::{
+    fmt,
+    net::{IpAddr, Ipv4Addr},
+};
 
-use super::{Interface, Peer};
+use super::{
+    Hook, Interface, Peer,
+    types::{Cidr, Endpoint, Key, key::Private},
+};
+
+/// Value of the keys of [redacted](WgConfig::redacted) configurations.
+pub const HIDDEN: &str = "(hidden)";
 
 pub struct WgConfig {
     pub interface: Interface,
     pub peer: Peer,
+    /// Values that couldn't be parsed, with their key.
+    unparsed: Vec<(String, String)>,
+}
+
+impl WgConfig {
+    /// Checks the config for missing or malformed values.
+    ///
+    /// Returns a human readable description of every problem found, empty if the config is valid.
+    pub fn validate(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+
+        // hidden on purpose, not lost
+        if !self.interface.private_key().is_valid()
+            && self.interface.private_key().as_str() != HIDDEN
+        {
+            problems.push("Interface: missing or invalid PrivateKey".to_string());
+        }
+        if self.interface.address().is_empty() {
+            problems.push("Interface: missing Address".to_string());
+        }
+        if !self.peer.pubkey().is_valid() {
+            problems.push("Peer: missing or invalid PublicKey".to_string());
+        }
+        if self.peer.allowed_ips().is_empty() {
+            problems.push("Peer: missing AllowedIPs".to_string());
+        }
+        for (key, value) in &self.unparsed {
+            problems.push(format!("invalid {key}: {value}"));
+        }
+
+        problems
+    }
+
+    /// The config in wireguard format, with the private key hidden.
+    ///
+    /// Meant to be displayed or shared without leaking secrets.
+    pub fn redacted(&self) -> String {
+        let mut config = String::new();
+        self.write(&mut config, true)
+            .expect("writing to a String never fails");
+        config
+    }
+
+    /// Parses a [redacted](Self::redacted) config, whose interface has the `pubkey` of the hidden
+    /// private key.
+    pub fn from_redacted(text: &str, pubkey: &str) -> Self {
+        let Self {
+            interface,
+            peer,
+            unparsed,
+        } = Self::from(text);
+        Self {
+            interface: interface.set_pubkey(pubkey.into()),
+            peer,
+            unparsed,
+        }
+    }
+
+    /// The config for a client such as the mobile apps: only the keys they know and the values
+    /// actually configured, without the defaults, routing keys and hooks of this host.
+    pub fn client(&self) -> String {
+        let mut config = format!(
+            "[Interface]\nPrivateKey = {}\nAddress = {}\n",
+            self.interface.private_key().as_str(),
+            Cidr::join(self.interface.address(), ", ")
+        );
+        if !self.interface.dns().is_empty() {
+            let dns: Vec<_> = self.interface.dns().iter().map(IpAddr::to_string).collect();
+            config.push_str(&format!("DNS = {}\n", dns.join(", ")));
+        }
+        if let Some(mtu) = self.interface.configured_mtu() {
+            config.push_str(&format!("MTU = {mtu}\n"));
+        }
+
+        config.push_str(&format!(
+            "\n[Peer]\nPublicKey = {}\n",
+            self.peer.pubkey().as_str()
+        ));
+        if let Some(key) = self.peer.preshared_key() {
+            config.push_str(&format!("PresharedKey = {}\n", key.as_str()));
+        }
+        config.push_str(&format!(
+            "AllowedIPs = {}\n",
+            Cidr::join(self.peer.allowed_ips(), ", ")
+        ));
+        if let Some(endpoint) = self.peer.endpoint() {
+            config.push_str(&format!("Endpoint = {endpoint}\n"));
+        }
+        if let Some(keepalive) = self.peer.persistent_keepalive() {
+            config.push_str(&format!("PersistentKeepalive = {keepalive}\n"));
+        }
+        config
+    }
+
+    /// The first address of the IPv4 network of the interface used neither by the interface nor by
+    /// its peer, for a new peer.
+    pub fn free_address(&self) -> Option<Cidr> {
+        let network = self
+            .interface
+            .address()
+            .iter()
+            .find(|a| a.addr().is_ipv4())?
+            .network();
+        let IpAddr::V4(first) = network.addr() else {
+            return None;
+        };
+        let hosts = (1u32 << (32 - network.prefix() as u32).min(31)) - 1;
+
+        (1..hosts)
+            .map(|host| IpAddr::V4(Ipv4Addr::from(u32::from(first) + host)))
+            .find(|&ip| {
+                !self.interface.address().iter().any(|a| a.addr() == ip)
+                    && !self.peer.allowed_ips().iter().any(|n| n.contains(ip))
+            })
+            .map(Cidr::from)
+    }
+
+    /// The config of a new peer of this interface, e.g. a phone, whose private key is `key` and
+    /// whose address is `address`.
+    ///
+    /// The peer reaches the networks of the interface through it, at `endpoint`.
+    pub fn new_peer(&self, key: &Key<Private>, address: Cidr, endpoint: &Endpoint) -> String {
+        let networks: Vec<Cidr> = self.interface.address().iter().map(Cidr::network).collect();
+        format!(
+            "[Interface]\nPrivateKey = {}\nAddress = {address}\n\n[Peer]\nPublicKey = {}\nAllowedIPs = {}\nEndpoint = {endpoint}\nPersistentKeepalive = 25\n",
+            key.as_str(),
+            self.interface.pubkey().as_str(),
+            Cidr::join(&networks, ", "),
+        )
+    }
+
+    fn write(&self, f: &mut impl fmt::Write, redact: bool) -> fmt::Result {
+        writeln!(f, "[Interface]")?;
+        if redact {
+            writeln!(f, "PrivateKey = {HIDDEN}")?;
+        } else {
+            writeln!(f, "PrivateKey = {}", self.interface.private_key().as_str())?;
+        }
+        writeln!(
+            f,
+            "Address = {}",
+            Cidr::join(self.interface.address(), ", ")
+        )?;
+        if let Some(port) = self.interface.listen_port() {
+            writeln!(f, "ListenPort = {port}")?;
+        }
+        writeln!(f, "MTU = {}", self.interface.mtu())?;
+        if let Some(fwmark) = self.interface.fwmark() {
+            writeln!(f, "FwMark = {fwmark:#x}")?;
+        }
+        if let Some(table) = self.interface.table() {
+            writeln!(f, "Table = {table}")?;
+        }
+        if !self.interface.dns().is_empty() {
+            let dns: Vec<_> = self
+                .interface
+                .dns()
+                .iter()
+                .map(|ip| ip.to_string())
+                .collect();
+            writeln!(f, "DNS = {}", dns.join(", "))?;
+        }
+        for (hook, command) in self.interface.hooks() {
+            writeln!(f, "{} = {command}", hook.key())?;
+        }
+
+        writeln!(f)?;
+        writeln!(f, "[Peer]")?;
+        writeln!(f, "PublicKey = {}", self.peer.pubkey().as_str())?;
+        match self.peer.preshared_key() {
+            Some(_) if redact => writeln!(f, "PresharedKey = {HIDDEN}")?,
+            Some(key) => writeln!(f, "PresharedKey = {}", key.as_str())?,
+            None => {}
+        }
+        writeln!(
+            f,
+            "AllowedIPs = {}",
+            Cidr::join(self.peer.allowed_ips(), ", ")
+        )?;
+        if let Some(endpoint) = self.peer.endpoint() {
+            writeln!(f, "Endpoint = {endpoint}")?;
+        }
+        if let Some(keepalive) = self.peer.persistent_keepalive() {
+            writeln!(f, "PersistentKeepalive = {keepalive}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The raw configuration `text` with only the keys known to `wg`, as `wg syncconf` expects.
+///
+/// Like `wg-quick strip`: the keys of `wg-quick` (Address, DNS, MTU, Table, SaveConfig and the
+/// hooks) are left out, every other line is kept, including all the peers.
+pub fn strip(text: &str) -> String {
+    const WG_QUICK: [&str; 9] = [
+        "Address",
+        "DNS",
+        "MTU",
+        "Table",
+        "SaveConfig",
+        "PreUp",
+        "PostUp",
+        "PreDown",
+        "PostDown",
+    ];
+    text.split_inclusive('\n')
+        .filter(|line| {
+            !line
+                .split_once('=')
+                .is_some_and(|(key, _)| WG_QUICK.contains(&key.trim()))
+        })
+        .collect()
+}
+
+/// The raw configuration `text` with its private and preshared keys hidden, every other line kept
+/// as is.
+pub fn redact(text: &str) -> String {
+    text.split_inclusive('\n')
+        .map(|line| match line.split_once('=') {
+            Some((key, _)) if ["PrivateKey", "PresharedKey"].contains(&key.trim()) => {
+                let line_end = &line[line.trim_end().len()..];
+                format!("{} = {HIDDEN}{line_end}", key.trim())
+            }
+            _ => line.to_string(),
+        })
+        .collect()
+}
+
+/// Replaces the private key of the raw configuration `text`.
+///
+/// The rest of the text is kept as is, including comments and keys unknown to wtui.
+pub fn with_private_key(text: &str, key: &Key<Private>) -> String {
+    with_value(text, "Interface", "PrivateKey", Some(key.as_str()))
+}
+
+/// Sets `key` of `section` to `value` in the raw configuration `text`, removes it if [None].
+///
+/// The key is replaced where it first appears and its repetitions are removed, or it's added at
+/// the end of the section. The rest of the text is kept as is.
+pub fn with_value(text: &str, section: &str, key: &str, value: Option<&str>) -> String {
+    let mut lines: Vec<String> = Vec::new();
+    let mut current = "";
+    let mut found = false;
+    // where to add the key if missing, after the last line of the section
+    let mut end = None;
+
+    for line in text.split_inclusive('\n') {
+        let trimmed = line.trim();
+        if let Some(name) = trimmed.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            current = name.trim();
+        } else if current == section
+            && line
+                .split_once('=')
+                .is_some_and(|(name, _)| name.trim() == key)
+        {
+            if let (false, Some(value)) = (found, value) {
+                let line_end = &line[line.trim_end().len()..];
+                lines.push(format!("{key} = {value}{line_end}"));
+                end = Some(lines.len());
+            }
+            found = true;
+            continue;
+        }
+
+        lines.push(line.to_string());
+        if current == section && !trimmed.is_empty() && !trimmed.starts_with('#') {
+            end = Some(lines.len());
+        }
+    }
+
+    if let (false, Some(value), Some(end)) = (found, value, end) {
+        if !lines[end - 1].ends_with('\n') {
+            lines[end - 1].push('\n');
+        }
+        lines.insert(end, format!("{key} = {value}\n"));
+    }
+    lines.concat()
+}
+
+impl fmt::Display for WgConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write(f, false)
+    }
 }
 
 impl From<&str> for WgConfig {
     fn from(config: &str) -> Self {
         // interface
         let mut private_key = String::new();
-        let mut address = String::new();
-        let mut mtu = 1500;
+        let mut address: Vec<Cidr> = Vec::new();
+        let mut mtu = None;
         let mut dns: Vec<IpAddr> = Vec::new();
+        let mut fwmark = None;
+        let mut table = None;
+        let mut listen_port = None;
+        let mut hooks = Vec::new();
 
         // peer
         let mut peer_pubkey = String::new();
-        let mut allowed_ips: Vec<String> = Vec::new();
-        let mut endpoint = String::new();
+        let mut allowed_ips: Vec<Cidr> = Vec::new();
+        let mut endpoint = None;
+        let mut preshared_key = None;
+        let mut persistent_keepalive = None;
+
+        let mut unparsed = Vec::new();
 
         for line in config.split('\n') {
             if let Some(pair) = line.trim().split_once('=') {
                 let value = pair.1.trim();
-                match pair.0.trim() {
+                let key = pair.0.trim();
+                if let Some(hook) = Hook::ALL.into_iter().find(|h| h.key() == key) {
+                    hooks.push((hook, value.to_string()));
+                    continue;
+                }
+                match key {
                     "PrivateKey" => private_key = value.into(),
-                    "Address" => address = value.into(),
-                    "MTU" => mtu = value.parse().unwrap_or(mtu),
+                    "Address" => address.extend(parse_networks("Address", value, &mut unparsed)),
+                    "ListenPort" => match value.parse() {
+                        Ok(port) => listen_port = Some(port),
+                        Err(_) => unparsed.push(("ListenPort".to_string(), value.to_string())),
+                    },
+                    "MTU" => mtu = value.parse().ok().or(mtu),
+                    "FwMark" => match parse_fwmark(value) {
+                        Some(mark) => fwmark = mark,
+                        None => unparsed.push(("FwMark".to_string(), value.to_string())),
+                    },
+                    "Table" => table = Some(value.to_string()),
                     "DNS" => {
                         dns = value
                             .split(',')
@@ -34,22 +349,67 @@ impl From<&str> for WgConfig {
                             .collect();
                     }
                     "PublicKey" => peer_pubkey = value.into(),
+                    "PresharedKey" => preshared_key = Some(value.into()),
+                    "PersistentKeepalive" => match value {
+                        "off" | "0" => persistent_keepalive = None,
+                        _ => match value.parse() {
+                            Ok(secs) => persistent_keepalive = Some(secs),
+                            Err(_) => unparsed
+                                .push(("PersistentKeepalive".to_string(), value.to_string())),
+                        },
+                    },
                     "AllowedIPs" => {
-                        allowed_ips = value.split(',').map(|ip| ip.trim().into()).collect();
+                        allowed_ips.extend(parse_networks("AllowedIPs", value, &mut unparsed))
                     }
-                    "Endpoint" => endpoint = value.into(),
+                    "Endpoint" => match value.parse() {
+                        Ok(value) => endpoint = Some(value),
+                        Err(_) => unparsed.push(("Endpoint".to_string(), value.to_string())),
+                    },
                     _ => {}
                 };
             }
         }
 
         Self {
-            interface: Interface::new(&private_key, &address)
+            interface: Interface::new(&private_key, address)
                 .set_dns(dns)
-                .set_mtu(mtu),
-            peer: Peer::new(&peer_pubkey, allowed_ips, endpoint),
+                .set_mtu(mtu)
+                .set_fwmark(fwmark)
+                .set_table(table)
+                .set_listen_port(listen_port)
+                .set_hooks(hooks),
+            peer: Peer::new(&peer_pubkey, allowed_ips, endpoint)
+                .set_preshared_key(preshared_key)
+                .set_persistent_keepalive(persistent_keepalive),
+            unparsed,
+        }
+    }
+}
+
+/// Parses a comma separated list of networks, adding the invalid ones to `unparsed`.
+fn parse_networks(key: &str, value: &str, unparsed: &mut Vec<(String, String)>) -> Vec<Cidr> {
+    let mut networks = Vec::new();
+    for network in value.split(',').map(str::trim).filter(|n| !n.is_empty()) {
+        match network.parse() {
+            Ok(network) => networks.push(network),
+            Err(_) => unparsed.push((key.to_string(), network.to_string())),
         }
     }
+    networks
+}
+
+/// Parses a `FwMark` value, decimal, hexadecimal with `0x` or `off`.
+///
+/// `Some(None)` if the mark is off.
+fn parse_fwmark(value: &str) -> Option<Option<u32>> {
+    match value {
+        "off" | "0" => Some(None),
+        _ => match value.strip_prefix("0x") {
+            Some(hex) => u32::from_str_radix(hex, 16).ok(),
+            None => value.parse().ok(),
+        }
+        .map(Some),
+    }
 }
 
 #[cfg(test)]
@@ -57,7 +417,7 @@ mod tests {
     use std::net::{IpAddr, Ipv4Addr};
 
     use crate::wg::types::{
-        Key,
+        Endpoint, Key,
         key::{Private, Public},
     };
 
@@ -87,7 +447,10 @@ Endpoint = vpn.example.com:51820"#;
             config.interface.pubkey(),
             &Key::<Public>::from("CLjhKsWxLTR+N5fs/jMYqVXL7xwtuEzufupX82c7LCs=")
         );
-        assert_eq!(config.interface.address(), "192.168.5.2");
+        assert_eq!(
+            config.interface.address(),
+            vec!["192.168.5.2/32".parse::<Cidr>().unwrap()]
+        );
         assert_eq!(config.interface.mtu(), 1420);
         assert_eq!(
             config.interface.dns(),
@@ -104,9 +467,15 @@ Endpoint = vpn.example.com:51820"#;
         );
         assert_eq!(
             config.peer.allowed_ips(),
-            vec!["192.168.5.0/24", "192.168.6.0/24"]
+            vec![
+                "192.168.5.0/24".parse::<Cidr>().unwrap(),
+                "192.168.6.0/24".parse().unwrap()
+            ]
+        );
+        assert_eq!(
+            config.peer.endpoint().map(Endpoint::to_string).as_deref(),
+            Some("vpn.example.com:51820")
         );
-        assert_eq!(config.peer.endpoint(), "vpn.example.com:51820");
     }
 
     #[test]
@@ -131,7 +500,10 @@ Endpoint = vpn.example.com:51820"#;
             config.interface.pubkey(),
             &Key::<Public>::from("CLjhKsWxLTR+N5fs/jMYqVXL7xwtuEzufupX82c7LCs=")
         );
-        assert_eq!(config.interface.address(), "2001:DB8::1");
+        assert_eq!(
+            config.interface.address(),
+            vec!["2001:DB8::1/128".parse::<Cidr>().unwrap()]
+        );
         assert_eq!(config.interface.mtu(), 1500);
         assert_eq!(config.interface.dns(), Vec::<IpAddr>::new());
 
@@ -140,7 +512,199 @@ Endpoint = vpn.example.com:51820"#;
             config.peer.pubkey(),
             &Key::<Public>::from("60TUAvOo+Wi4SCyir581cCyBx4wIcHtrIrUgBv/iqRM=")
         );
-        assert_eq!(config.peer.allowed_ips(), vec!["::/0"]);
-        assert_eq!(config.peer.endpoint(), "vpn.example.com:51820");
+        assert_eq!(
+            config.peer.allowed_ips(),
+            vec!["::/0".parse::<Cidr>().unwrap()]
+        );
+        assert_eq!(
+            config.peer.endpoint().map(Endpoint::to_string).as_deref(),
+            Some("vpn.example.com:51820")
+        );
+    }
+
+    #[test]
+    fn routing_keys() {
+        let data = r#"[Interface]
+PrivateKey = oMVUWFwDf+20fIfeRUe7c0rlUKSYnHk2K0y2920SX1c=
+Address = 192.168.5.2
+FwMark = 0xca6c
+Table = off
+
+[Peer]
+PublicKey = 60TUAvOo+Wi4SCyir581cCyBx4wIcHtrIrUgBv/iqRM=
+AllowedIPs = 0.0.0.0/0, 10.0.0.0/33"#;
+
+        let config = WgConfig::from(data);
+
+        assert_eq!(config.interface.fwmark(), Some(0xca6c));
+        assert!(!config.interface.adds_routes());
+        assert_eq!(config.peer.allowed_ips().len(), 1);
+        assert_eq!(config.validate(), vec!["invalid AllowedIPs: 10.0.0.0/33"]);
+    }
+
+    #[test]
+    fn redacted() {
+        let config = WgConfig::from(
+            "[Interface]
+PrivateKey = oMVUWFwDf+20fIfeRUe7c0rlUKSYnHk2K0y2920SX1c=
+Address = 10.0.0.2/24
+
+[Peer]
+PublicKey = 60TUAvOo+Wi4SCyir581cCyBx4wIcHtrIrUgBv/iqRM=
+PresharedKey = FpCyhws9cxwWoV4xELtfJvjJN+zQVRPISllRWgeopVE=
+AllowedIPs = 10.1.0.0/16
+",
+        );
+        let text = config.redacted();
+        assert!(!text.contains("oMVUWFwDf"));
+        assert!(!text.contains("FpCyhws9"));
+
+        let redacted = WgConfig::from_redacted(&text, config.interface.pubkey().as_str());
+        assert_eq!(redacted.interface.pubkey(), config.interface.pubkey());
+        assert!(redacted.validate().is_empty());
+    }
+
+    #[test]
+    fn runtime_keys() {
+        let data = r#"[Interface]
+PrivateKey = oMVUWFwDf+20fIfeRUe7c0rlUKSYnHk2K0y2920SX1c=
+Address = 192.168.5.2
+ListenPort = 51820
+PostUp = iptables -A FORWARD -i %i -j ACCEPT
+PostUp = echo up
+PreDown = echo down
+
+[Peer]
+PublicKey = 60TUAvOo+Wi4SCyir581cCyBx4wIcHtrIrUgBv/iqRM=
+PresharedKey = 4sGbSNd6R0xH8SOh1eTfn9UpHfnsxFwy4DJSm0Vxe2s=
+AllowedIPs = 192.168.5.0/24
+PersistentKeepalive = 25"#;
+
+        let config = WgConfig::from(data);
+
+        assert_eq!(config.interface.listen_port(), Some(51820));
+        assert_eq!(
+            config.interface.commands(Hook::PostUp).collect::<Vec<_>>(),
+            vec!["iptables -A FORWARD -i %i -j ACCEPT", "echo up"]
+        );
+        assert_eq!(config.interface.commands(Hook::PreUp).count(), 0);
+        assert!(config.peer.preshared_key().is_some());
+        assert_eq!(config.peer.persistent_keepalive(), Some(25));
+
+        assert!(config.redacted().contains("PresharedKey = (hidden)"));
+        let stripped = strip(data);
+        assert!(stripped.contains("ListenPort = 51820"));
+        assert!(stripped.contains("PresharedKey = 4sGbSNd6R0xH8SOh1eTfn9UpHfnsxFwy4DJSm0Vxe2s="));
+        assert!(!stripped.contains("Address"));
+        assert!(!stripped.contains("PostUp"));
+        assert!(stripped.contains("PersistentKeepalive = 25"));
+        let peers =
+            strip("[Peer]\nPublicKey = a\n\n[Peer]\nPublicKey = b\nAllowedIPs = 10.0.0.0/8\n");
+        assert_eq!(peers.matches("[Peer]").count(), 2);
+        let written = WgConfig::from(config.to_string().as_str());
+        assert_eq!(written.interface.hooks(), config.interface.hooks());
+        assert_eq!(written.peer.preshared_key(), config.peer.preshared_key());
+    }
+
+    #[test]
+    fn client() {
+        let config = WgConfig::from(
+            "[Interface]
+PrivateKey = oMVUWFwDf+20fIfeRUe7c0rlUKSYnHk2K0y2920SX1c=
+Address = 10.0.0.2/24
+Table = off
+PostUp = echo up
+
+[Peer]
+PublicKey = 60TUAvOo+Wi4SCyir581cCyBx4wIcHtrIrUgBv/iqRM=
+AllowedIPs = 10.0.0.0/24
+Endpoint = vpn.example.com:51820
+",
+        );
+
+        assert_eq!(
+            config.client(),
+            "[Interface]
+PrivateKey = oMVUWFwDf+20fIfeRUe7c0rlUKSYnHk2K0y2920SX1c=
+Address = 10.0.0.2/24
+
+[Peer]
+PublicKey = 60TUAvOo+Wi4SCyir581cCyBx4wIcHtrIrUgBv/iqRM=
+AllowedIPs = 10.0.0.0/24
+Endpoint = vpn.example.com:51820
+"
+        );
+    }
+
+    #[test]
+    fn new_peer() {
+        let config = WgConfig::from(
+            "[Interface]
+PrivateKey = oMVUWFwDf+20fIfeRUe7c0rlUKSYnHk2K0y2920SX1c=
+Address = 10.0.0.1/24
+ListenPort = 51820
+
+[Peer]
+PublicKey = 60TUAvOo+Wi4SCyir581cCyBx4wIcHtrIrUgBv/iqRM=
+AllowedIPs = 10.0.0.2/32
+",
+        );
+        let key = Key::<Private>::from("FpCyhws9cxwWoV4xELtfJvjJN+zQVRPISllRWgeopVE=");
+
+        let address = config.free_address().unwrap();
+        assert_eq!(address, "10.0.0.3/32".parse().unwrap());
+        let peer = WgConfig::from(
+            config
+                .new_peer(&key, address, &"host:51820".parse().unwrap())
+                .as_str(),
+        );
+        assert!(peer.validate().is_empty());
+        assert_eq!(
+            peer.peer.pubkey().as_str(),
+            config.interface.pubkey().as_str()
+        );
+        assert_eq!(peer.peer.allowed_ips(), ["10.0.0.0/24".parse().unwrap()]);
+
+        // no room left in a /31
+        let full =
+            WgConfig::from("[Interface]\nAddress = 10.0.0.0/31\n[Peer]\nAllowedIPs = 10.0.0.1\n");
+        assert_eq!(full.free_address(), None);
+    }
+
+    #[test]
+    fn redact_text() {
+        let text = "[Interface]\n# laptop\nPrivateKey=secret\r\n\n[Peer]\nPresharedKey = psk\nAllowedIPs = 10.0.0.0/8";
+
+        assert_eq!(
+            redact(text),
+            "[Interface]\n# laptop\nPrivateKey = (hidden)\r\n\n[Peer]\nPresharedKey = (hidden)\nAllowedIPs = 10.0.0.0/8"
+        );
+    }
+
+    #[test]
+    fn set_values() {
+        let text = "[Interface]\nPrivateKey = key\n\n[Peer]\n# office\nAllowedIPs = 10.1.0.0/16\nAllowedIPs = 10.2.0.0/16\nEndpoint = vpn.example.com:51820";
+
+        let text = with_value(text, "Peer", "AllowedIPs", Some("10.0.0.0/8"));
+        let text = with_value(&text, "Peer", "Endpoint", None);
+        let text = with_value(&text, "Peer", "PersistentKeepalive", Some("25"));
+        let text = with_value(&text, "Interface", "ListenPort", Some("51820"));
+
+        assert_eq!(
+            text,
+            "[Interface]\nPrivateKey = key\nListenPort = 51820\n\n[Peer]\n# office\nAllowedIPs = 10.0.0.0/8\nPersistentKeepalive = 25\n"
+        );
+    }
+
+    #[test]
+    fn replace_private_key() {
+        let text =
+            "[Interface]\n# laptop\nPrivateKey=old\r\nAddress = 10.0.0.2\nPostUp = echo up\n";
+        let key = Key::<Private>::from("oMVUWFwDf+20fIfeRUe7c0rlUKSYnHk2K0y2920SX1c=");
+
+        assert_eq!(
+            with_private_key(text, &key),
+            "[Interface]\n# laptop\nPrivateKey = oMVUWFwDf+20fIfeRUe7c0rlUKSYnHk2K0y2920SX1c=\r\nAddress = 10.0.0.2\nPostUp = echo up\n"
+        );
     }
 }